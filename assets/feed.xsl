@@ -0,0 +1,59 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<!-- Renders the feeds as a readable page when opened in a browser. -->
+<xsl:stylesheet version="1.0" xmlns:xsl="http://www.w3.org/1999/XSL/Transform" xmlns:atom="http://www.w3.org/2005/Atom">
+	<xsl:output method="html" encoding="UTF-8" indent="yes"/>
+
+	<xsl:template match="/rss/channel">
+		<html>
+			<head>
+				<meta name="viewport" content="width=device-width,initial-scale=1"/>
+				<title><xsl:value-of select="title"/></title>
+				<!-- An inline style would be blocked by the content security policy,
+				so the feed names its stylesheet in a processing instruction. -->
+				<link rel="stylesheet" href="{normalize-space(/processing-instruction('feed-stylesheet'))}"/>
+			</head>
+			<body>
+				<p class="feed-notice">
+					This is a feed. Copy its address into a feed reader to follow
+					new pages on <a href="{link}"><xsl:value-of select="link"/></a>.
+				</p>
+				<h1><xsl:value-of select="title"/></h1>
+				<ul class="feed-items">
+					<xsl:for-each select="item">
+						<li>
+							<h2><a href="{link}"><xsl:value-of select="title"/></a></h2>
+							<p><small><xsl:value-of select="pubDate"/></small></p>
+							<p><xsl:value-of select="description"/></p>
+						</li>
+					</xsl:for-each>
+				</ul>
+			</body>
+		</html>
+	</xsl:template>
+
+	<xsl:template match="/atom:feed">
+		<html>
+			<head>
+				<meta name="viewport" content="width=device-width,initial-scale=1"/>
+				<title><xsl:value-of select="atom:title"/></title>
+				<link rel="stylesheet" href="{normalize-space(/processing-instruction('feed-stylesheet'))}"/>
+			</head>
+			<body>
+				<p class="feed-notice">
+					This is a feed. Copy its address into a feed reader to follow
+					new pages on <a href="{atom:link[not(@rel)]/@href}"><xsl:value-of select="atom:link[not(@rel)]/@href"/></a>.
+				</p>
+				<h1><xsl:value-of select="atom:title"/></h1>
+				<ul class="feed-items">
+					<xsl:for-each select="atom:entry">
+						<li>
+							<h2><a href="{atom:link/@href}"><xsl:value-of select="atom:title"/></a></h2>
+							<p><small><xsl:value-of select="atom:updated"/></small></p>
+							<p><xsl:value-of select="atom:summary"/></p>
+						</li>
+					</xsl:for-each>
+				</ul>
+			</body>
+		</html>
+	</xsl:template>
+</xsl:stylesheet>
@@ -1,8 +1,21 @@
+use std::{fs, path::Path};
+
 mod assets_build {
     include!("src/assets_build.rs");
 }
 
 fn main() -> anyhow::Result<()> {
-    assets_build::write_files_and_manifest()?;
+    let assets =
+        assets_build::hash_assets(Path::new("assets/"), Path::new("target/assets_hashed/"))?;
+
+    let mut manifest = String::from(
+        "pub static ASSET_MANIFEST: phf::Map<&'static str, &'static str> = phf::phf_map! {\n",
+    );
+    for (original_path, hashed_name) in assets {
+        manifest.push_str(&format!("    {:?} => {:?},\n", original_path, hashed_name));
+    }
+    manifest.push_str("};\n");
+    fs::write(Path::new("target/generated_asset_manifest.rs"), manifest)?;
+
     Ok(())
 }
@@ -0,0 +1,137 @@
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::{Body, HttpBody},
+    http::{Method, Request, Response},
+    middleware::Next,
+};
+use tokio_stream::StreamExt;
+
+use crate::config::load_config;
+
+/// Logs every request with its status, response size and handling time.
+/// Static assets are logged at debug level to keep the info log readable.
+pub async fn log_access(request: Request<Body>, next: Next) -> Response<Body> {
+    let start = Instant::now();
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+
+    let response = next.run(request).await;
+
+    let entry = AccessEntry {
+        method,
+        asset: is_asset_path(load_config().base_path(), &path),
+        path,
+        status: response.status().as_u16(),
+        elapsed: start.elapsed(),
+    };
+    // Streamed and compressed bodies only have a size once they are sent.
+    let (parts, body) = response.into_parts();
+    let body = match body.size_hint().exact() {
+        Some(size) => {
+            entry.log(size);
+            body
+        }
+        None => count_body(body, move |size| entry.log(size)),
+    };
+
+    Response::from_parts(parts, body)
+}
+
+struct AccessEntry {
+    method: Method,
+    path: String,
+    status: u16,
+    elapsed: Duration,
+    asset: bool,
+}
+
+impl AccessEntry {
+    fn log(&self, size: u64) {
+        let AccessEntry {
+            method,
+            path,
+            status,
+            elapsed,
+            asset,
+        } = self;
+        if *asset {
+            tracing::debug!("{method} {path} {status} {size} {elapsed:?}");
+        } else {
+            tracing::info!("{method} {path} {status} {size} {elapsed:?}");
+        }
+    }
+}
+
+/// Passes `body` through, calling `done` with the number of bytes sent once
+/// it has been sent in full or dropped part way.
+fn count_body(body: Body, done: impl FnOnce(u64) + Send + 'static) -> Body {
+    let mut counter = ByteCounter {
+        sent: 0,
+        done: Some(done),
+    };
+    Body::from_stream(body.into_data_stream().map(move |chunk| {
+        // Taken as a whole, so the counter is dropped along with the stream.
+        let counter = &mut counter;
+        if let Ok(bytes) = &chunk {
+            counter.sent += bytes.len() as u64;
+        }
+        chunk
+    }))
+}
+
+/// Reports the bytes counted when dropped along with the body stream.
+struct ByteCounter<F: FnOnce(u64)> {
+    sent: u64,
+    done: Option<F>,
+}
+
+impl<F: FnOnce(u64)> Drop for ByteCounter<F> {
+    fn drop(&mut self) {
+        if let Some(done) = self.done.take() {
+            done(self.sent);
+        }
+    }
+}
+
+fn is_asset_path(base_path: &str, path: &str) -> bool {
+    let path = path.strip_prefix(base_path).unwrap_or(path);
+    path.starts_with("/assets/") || path == "/favicon.ico"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        sync::{
+            Arc,
+            atomic::{AtomicU64, Ordering},
+        },
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_is_asset_path() {
+        assert!(is_asset_path("", "/assets/styles.1a2b.css"));
+        assert!(is_asset_path("/docs", "/docs/assets/styles.1a2b.css"));
+        assert!(is_asset_path("", "/favicon.ico"));
+        assert!(!is_asset_path("", "/notes/assets"));
+        assert!(!is_asset_path("/docs", "/docs/search"));
+    }
+
+    #[tokio::test]
+    async fn test_count_body_reports_streamed_bytes() {
+        let counted = Arc::new(AtomicU64::new(u64::MAX));
+        let chunks = tokio_stream::iter(["first ", "second"]).map(Ok::<_, Infallible>);
+        let body = count_body(Body::from_stream(chunks), {
+            let counted = counted.clone();
+            move |size| counted.store(size, Ordering::SeqCst)
+        });
+        assert_eq!(counted.load(Ordering::SeqCst), u64::MAX);
+
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        assert_eq!(bytes.len(), 12);
+        assert_eq!(counted.load(Ordering::SeqCst), 12);
+    }
+}
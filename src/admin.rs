@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use serde_json::json;
+use tokio::sync::RwLock;
+
+use crate::{
+    config::load_config,
+    search::{SearchIndex, compact_index},
+};
+
+/// Maintenance endpoints, only reachable with the configured admin token.
+pub fn admin_routes(search_index: Arc<RwLock<SearchIndex>>) -> Router {
+    Router::new()
+        .route("/admin/reindex", post(reindex_handler))
+        .route("/admin/compact", post(compact_handler))
+        .route("/admin/stats", get(stats_handler))
+        .with_state(search_index)
+}
+
+async fn reindex_handler(
+    State(search_index): State<Arc<RwLock<SearchIndex>>>,
+    headers: HeaderMap,
+) -> Response {
+    if !is_authorized(&headers, load_config().admin_token().as_deref()) {
+        return unauthorized();
+    }
+
+    tracing::info!("🔑 Reindex requested through the admin endpoint");
+    let indexed = match search_index.read().await.reindex().await {
+        Ok(indexed) => indexed,
+        Err(e) => {
+            tracing::error!("💥 Requested reindex failed: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    if let Err(e) = search_index.write().await.swap_indexes().await {
+        tracing::error!("💥 Swapping indexes failed: {}", e);
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
+    Json(json!({ "indexed": indexed })).into_response()
+}
+
+async fn compact_handler(
+    State(search_index): State<Arc<RwLock<SearchIndex>>>,
+    headers: HeaderMap,
+) -> Response {
+    if !is_authorized(&headers, load_config().admin_token().as_deref()) {
+        return unauthorized();
+    }
+
+    tracing::info!("🔑 Compaction requested through the admin endpoint");
+    match compact_index(&search_index).await {
+        Ok((before, after)) => Json(json!({ "before": before, "after": after })).into_response(),
+        Err(e) => {
+            tracing::error!("💥 Requested compaction failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn stats_handler(
+    State(search_index): State<Arc<RwLock<SearchIndex>>>,
+    headers: HeaderMap,
+) -> Response {
+    if !is_authorized(&headers, load_config().admin_token().as_deref()) {
+        return unauthorized();
+    }
+
+    match search_index.read().await.stats() {
+        Ok(stats) => Json(stats).into_response(),
+        Err(e) => {
+            tracing::error!("💥 Reading search index stats failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+fn unauthorized() -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Bearer")],
+    )
+        .into_response()
+}
+
+/// Checks for an `Authorization: Bearer <token>` header matching the
+/// configured token. Without a configured token nobody is authorized.
+fn is_authorized(headers: &HeaderMap, token: Option<&str>) -> bool {
+    let Some(token) = token.filter(|token| !token.is_empty()) else {
+        return false;
+    };
+
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| constant_time_eq(given.trim().as_bytes(), token.as_bytes()))
+}
+
+/// Compares without returning early, so response timing does not reveal how
+/// much of the token was guessed correctly.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn headers(authorization: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(authorization).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_is_authorized() {
+        assert!(is_authorized(&headers("Bearer secret"), Some("secret")));
+        assert!(!is_authorized(&headers("Bearer wrong"), Some("secret")));
+        assert!(!is_authorized(&headers("secret"), Some("secret")));
+        assert!(!is_authorized(&HeaderMap::new(), Some("secret")));
+        assert!(!is_authorized(&headers("Bearer "), Some("")));
+        assert!(!is_authorized(&headers("Bearer secret"), None));
+    }
+}
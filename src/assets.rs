@@ -1,13 +1,35 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, path::Path, sync::RwLock};
 
-use axum::{Router, http::HeaderValue};
+use anyhow::{Result, bail};
+use axum::{
+    Router,
+    http::{HeaderMap, HeaderValue, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    routing::get,
+};
 use hyper::header;
 use lazy_static::lazy_static;
 use rust_embed::Embed;
 use tower_http::{services::ServeDir, set_header::SetResponseHeaderLayer};
 
+#[cfg(debug_assertions)]
+use notify::{RecommendedWatcher, RecursiveMode};
+#[cfg(debug_assertions)]
+use notify_debouncer_full::{DebouncedEvent, Debouncer, RecommendedCache, new_debouncer};
+
+#[cfg(debug_assertions)]
+use crate::assets_build::hash_assets;
+use crate::config::{Config, load_config};
+
 include!("../target/generated_asset_manifest.rs");
 
+#[cfg(debug_assertions)]
+const ASSETS_PATH: &str = "assets";
+const HASHED_ASSETS_PATH: &str = "target/assets_hashed";
+
+/// Assets that pages can not render properly without.
+const CORE_ASSETS: [&str; 2] = ["styles.css", "script.js"];
+
 lazy_static! {
     pub static ref ASSET_MANAGER: AssetManager = {
         let manifest = ASSET_MANIFEST
@@ -18,34 +40,264 @@ lazy_static! {
     };
 }
 
+/// A fully transparent 1x1 icon, served when no favicon is configured.
+const DEFAULT_FAVICON: [u8; 70] = [
+    0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x01, 0x01, 0x00, 0x00, 0x01, 0x00, 0x20, 0x00, 0x30, 0x00,
+    0x00, 0x00, 0x16, 0x00, 0x00, 0x00, 0x28, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00,
+    0x00, 0x00, 0x01, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
 pub fn asset_routes() -> Router {
-    let static_service =
-        ServeDir::new("target/assets_hashed").append_index_html_on_directories(false);
+    let static_service = ServeDir::new(HASHED_ASSETS_PATH)
+        .append_index_html_on_directories(false)
+        .fallback(get(embedded_asset_handler));
 
-    Router::new().nest_service("/assets", static_service).layer(
-        SetResponseHeaderLayer::if_not_present(
+    Router::new()
+        .nest_service("/assets", static_service)
+        .route("/favicon.ico", get(favicon_handler))
+        .layer(SetResponseHeaderLayer::if_not_present(
             header::CACHE_CONTROL,
             HeaderValue::from_static("public, max-age=31536000, immutable"),
-        ),
+        ))
+}
+
+/// Serves assets embedded in the binary when they are missing on disk.
+async fn embedded_asset_handler(uri: Uri, headers: HeaderMap) -> Response {
+    let hashed_name = uri.path().trim_start_matches('/');
+    let Some(file) = EmbeddedAssets::get(hashed_name) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let content_type = mime_guess::from_path(hashed_name).first_or_octet_stream();
+    let etag = asset_etag(hashed_name);
+    if let Some(etag) = &etag {
+        if matches_if_none_match(&headers, etag) {
+            return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag.clone())]).into_response();
+        }
+    }
+
+    let mut response = (
+        [(header::CONTENT_TYPE, content_type.essence_str().to_string())],
+        file.data.into_owned(),
     )
+        .into_response();
+    if let Some(etag) = etag.and_then(|etag| HeaderValue::from_str(&etag).ok()) {
+        response.headers_mut().insert(header::ETAG, etag);
+    }
+    response
+}
+
+/// Strong ETag from the content hash that `assets_build` puts in hashed
+/// names, e.g. `"1a2b3c"` for `styles.1a2b3c.css`.
+fn asset_etag(hashed_name: &str) -> Option<String> {
+    let mut parts = hashed_name.rsplitn(3, '.');
+    let (_extension, hash, _stem) = (parts.next()?, parts.next()?, parts.next()?);
+    Some(format!("\"{hash}\""))
+}
+
+/// Whether any of the client's `If-None-Match` tags matches, using the weak
+/// comparison RFC 9110 prescribes for this header.
+fn matches_if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+async fn favicon_handler() -> Response {
+    let favicon = load_config()
+        .favicon()
+        .as_ref()
+        .and_then(|favicon| Some((image_mime_type(favicon)?, ASSET_MANAGER.read(favicon)?)));
+
+    match favicon {
+        Some((mime_type, content)) => {
+            ([(header::CONTENT_TYPE, mime_type)], content).into_response()
+        }
+        None => (
+            [(header::CONTENT_TYPE, "image/x-icon")],
+            DEFAULT_FAVICON.as_slice(),
+        )
+            .into_response(),
+    }
+}
+
+/// Content type for the image formats used as icons.
+pub fn image_mime_type(path: &str) -> Option<&'static str> {
+    match path.rsplit_once('.')?.1.to_ascii_lowercase().as_str() {
+        "png" => Some("image/png"),
+        "svg" => Some("image/svg+xml"),
+        "webp" => Some("image/webp"),
+        "ico" => Some("image/x-icon"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        _ => None,
+    }
+}
+
+/// Warns about core assets that are neither on disk nor embedded, or fails when
+/// `strict_assets` is enabled.
+pub fn check_assets(config: &Config) -> Result<()> {
+    let missing = missing_core_assets(Path::new(HASHED_ASSETS_PATH), |hashed_name| {
+        EmbeddedAssets::get(hashed_name).is_some()
+    });
+
+    if let Some(stylesheet) = config.dark_stylesheet() {
+        if ASSET_MANAGER.hashed_name(stylesheet).is_none() {
+            tracing::warn!("⚠️ Dark stylesheet {stylesheet} is not among the assets");
+        }
+    }
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let message = format!(
+        "Core assets missing from {HASHED_ASSETS_PATH} and the embedded assets: {}",
+        missing.join(", ")
+    );
+    if *config.strict_assets() {
+        bail!(message);
+    }
+    tracing::warn!("⚠️ {message}");
+
+    Ok(())
+}
+
+fn missing_core_assets(dir: &Path, is_embedded: impl Fn(&str) -> bool) -> Vec<&'static str> {
+    CORE_ASSETS
+        .into_iter()
+        .filter(|asset| match ASSET_MANAGER.hashed_name(asset) {
+            Some(hashed_name) => !dir.join(&hashed_name).is_file() && !is_embedded(&hashed_name),
+            None => true,
+        })
+        .collect()
 }
 
 #[derive(Embed)]
 #[folder = "target/assets_hashed/"]
 struct EmbeddedAssets;
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct AssetManager {
-    manifest: HashMap<String, String>,
+    manifest: RwLock<HashMap<String, String>>,
 }
 
 impl AssetManager {
     fn new(manifest: HashMap<String, String>) -> Self {
-        Self { manifest }
+        Self {
+            manifest: RwLock::new(manifest),
+        }
+    }
+
+    fn hashed_name(&self, original_path: &str) -> Option<String> {
+        self.manifest
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(original_path)
+            .cloned()
     }
 
     pub fn hashed_route(&self, original_path: &str) -> Option<String> {
-        let asset = self.manifest.get(original_path);
-        asset.map(|a| format!("/assets/{a}"))
+        let asset = self.hashed_name(original_path);
+        asset.map(|a| load_config().route(&format!("/assets/{a}")))
+    }
+
+    /// Reads an asset from disk, falling back on the embedded copy.
+    pub fn read(&self, original_path: &str) -> Option<Vec<u8>> {
+        let hashed_name = self.hashed_name(original_path)?;
+        std::fs::read(Path::new(HASHED_ASSETS_PATH).join(&hashed_name))
+            .ok()
+            .or_else(|| EmbeddedAssets::get(&hashed_name).map(|file| file.data.into_owned()))
+    }
+
+    #[cfg(debug_assertions)]
+    fn replace_manifest(&self, manifest: HashMap<String, String>) {
+        *self.manifest.write().unwrap_or_else(|e| e.into_inner()) = manifest;
+    }
+}
+
+/// Rehashes the assets whenever they change, so edits show up without
+/// rebuilding. Debug builds only, release builds use the manifest generated at
+/// compile time. The watch stops when the returned debouncer is dropped.
+#[cfg(debug_assertions)]
+pub fn watch_assets() -> Result<Debouncer<RecommendedWatcher, RecommendedCache>> {
+    let mut debouncer = new_debouncer(
+        std::time::Duration::from_millis(100),
+        None,
+        |res: Result<Vec<DebouncedEvent>, _>| match res {
+            Ok(events) if events.iter().any(|event| !event.kind.is_access()) => {
+                match hash_assets(Path::new(ASSETS_PATH), Path::new(HASHED_ASSETS_PATH)) {
+                    Ok(assets) => {
+                        ASSET_MANAGER.replace_manifest(assets.into_iter().collect());
+                        tracing::info!("🎨 Assets changed, manifest rebuilt");
+                    }
+                    Err(e) => tracing::error!("💥 Rehashing assets failed: {}", e),
+                }
+            }
+            Ok(_) => {}
+            Err(e) => tracing::error!("💥 Asset watcher error: {:?}", e),
+        },
+    )?;
+
+    debouncer.watch(ASSETS_PATH, RecursiveMode::Recursive)?;
+    tracing::info!("🎨 Watching assets for changes at path: {ASSETS_PATH}");
+
+    Ok(debouncer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asset_etag() {
+        assert_eq!(
+            asset_etag("styles.1a2b3c.css").as_deref(),
+            Some("\"1a2b3c\"")
+        );
+        assert_eq!(asset_etag("styles.css"), None);
+    }
+
+    #[test]
+    fn test_matches_if_none_match() {
+        let headers = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static(value));
+            headers
+        };
+        assert!(matches_if_none_match(&headers("\"1a2b\""), "\"1a2b\""));
+        assert!(matches_if_none_match(
+            &headers("\"other\", W/\"1a2b\""),
+            "\"1a2b\""
+        ));
+        assert!(matches_if_none_match(&headers("*"), "\"1a2b\""));
+        assert!(!matches_if_none_match(&headers("\"other\""), "\"1a2b\""));
+        assert!(!matches_if_none_match(&HeaderMap::new(), "\"1a2b\""));
+    }
+
+    #[test]
+    fn test_default_favicon_is_an_icon() {
+        assert_eq!(DEFAULT_FAVICON[..4], [0x00, 0x00, 0x01, 0x00]);
+        assert_eq!(image_mime_type("favicon.ICO"), Some("image/x-icon"));
+        assert_eq!(image_mime_type("favicon"), None);
+    }
+
+    #[test]
+    fn test_missing_core_assets_in_empty_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = missing_core_assets(dir.path(), |_| false);
+        assert_eq!(missing, CORE_ASSETS.to_vec());
+    }
+
+    #[test]
+    fn test_embedded_core_assets_are_not_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = missing_core_assets(dir.path(), |_| true);
+        assert!(missing.is_empty());
     }
 }
@@ -1,27 +1,23 @@
-use std::{fs, path::PathBuf};
+use std::{fs, path::Path};
 
 use anyhow::Result;
 use walkdir::WalkDir;
 use xxhash_rust::xxh64::xxh64;
 
-pub fn write_files_and_manifest() -> Result<()> {
-    let asset_dir = PathBuf::from("assets/");
-    let hashed_dir = PathBuf::from("target/assets_hashed/");
-    std::fs::create_dir_all(&hashed_dir)?;
+/// Copies every file in `asset_dir` to `hashed_dir` with its content hash in
+/// the file name, returning pairs of original path and hashed name.
+pub fn hash_assets(asset_dir: &Path, hashed_dir: &Path) -> Result<Vec<(String, String)>> {
+    fs::create_dir_all(hashed_dir)?;
 
-    let mut manifest = String::from(
-        "pub static ASSET_MANIFEST: phf::Map<&'static str, &'static str> = phf::phf_map! {\n",
-    );
-    fs::create_dir_all(&hashed_dir)?;
-
-    for entry in WalkDir::new(&asset_dir) {
+    let mut assets = Vec::new();
+    for entry in WalkDir::new(asset_dir) {
         let entry = entry?;
         if entry.file_type().is_file() {
             let content = fs::read(entry.path())?;
             let hash = xxh64(&content, 0);
             let original_path = entry
                 .path()
-                .strip_prefix(&asset_dir)?
+                .strip_prefix(asset_dir)?
                 .to_str()
                 .unwrap()
                 .replace('\\', "/");
@@ -32,15 +28,9 @@ pub fn write_files_and_manifest() -> Result<()> {
                 entry.path().extension().unwrap().to_str().unwrap()
             );
             fs::copy(entry.path(), hashed_dir.join(&hashed_name))?;
-            manifest.push_str(&format!("    {:?} => {:?},\n", original_path, hashed_name));
+            assets.push((original_path, hashed_name));
         }
     }
 
-    manifest.push_str("};\n");
-    fs::write(
-        PathBuf::from("target/generated_asset_manifest.rs"),
-        manifest,
-    )?;
-
-    Ok(())
+    Ok(assets)
 }
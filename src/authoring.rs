@@ -0,0 +1,159 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Component, PathBuf},
+};
+
+use anyhow::{Result, bail};
+use ulid::Ulid;
+use walkdir::WalkDir;
+
+use crate::page::{Frontmatter, Page, PageError, PageRoot};
+
+/// Adds a generated `id` to the frontmatter of every page lacking one, so the
+/// id stays stable when the title changes. Returns the changed files.
+pub fn fix_ids() -> Result<Vec<PathBuf>> {
+    let mut changed = Vec::new();
+
+    let walks = PageRoot::all().into_iter().flat_map(|root| {
+        WalkDir::new(root.path).into_iter().filter_entry(|entry| {
+            entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.')
+        })
+    });
+    for entry in walks {
+        let entry = entry?;
+        let path = entry.path();
+        if !entry.file_type().is_file() || path.extension() != Some("md".as_ref()) {
+            continue;
+        }
+
+        let content = fs::read_to_string(path)?;
+        if let Some(updated) = insert_id(&content, Ulid::new())? {
+            fs::write(path, updated)?;
+            changed.push(path.to_path_buf());
+        }
+    }
+
+    Ok(changed)
+}
+
+/// Creates a new page at the URL `path` with a generated id, refusing to
+/// overwrite an existing file. Returns the path of the created file.
+pub fn new_page(path: &str, title: Option<String>, tags: Vec<String>) -> Result<PathBuf> {
+    let (root, url_path) = PageRoot::for_url(path);
+    let relative_path = PathBuf::from(Page::url_to_file_path(url_path));
+    if relative_path
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        bail!("Page path must stay within the pages directory: {path}");
+    }
+
+    let file_path = root.path.join(relative_path);
+    if file_path.exists() {
+        bail!("Page already exists: {}", file_path.to_string_lossy());
+    }
+
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&file_path, page_template(Ulid::new(), title, tags)?)?;
+
+    Ok(file_path)
+}
+
+fn page_template(id: Ulid, title: Option<String>, tags: Vec<String>) -> Result<String> {
+    let heading = title.clone().unwrap_or_else(|| "New page".into());
+    let frontmatter = serde_yaml::to_string(&Frontmatter {
+        id: Some(id),
+        title,
+        tags: (!tags.is_empty()).then(|| tags.into_iter().collect::<HashSet<_>>()),
+        ..Default::default()
+    })?;
+
+    Ok(format!(
+        "---\n{frontmatter}---\n# {heading}\n\nWrite the page content here.\n"
+    ))
+}
+
+/// Inserts `id` as the first frontmatter field, adding a YAML frontmatter block
+/// if there is none. Returns `None` when the page already has an id.
+fn insert_id(content: &str, id: Ulid) -> Result<Option<String>, PageError> {
+    let (frontmatter, _) = Page::split_frontmatter(content)?;
+    if frontmatter.id.is_some() {
+        return Ok(None);
+    }
+
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let (first_line, rest) = content.split_once('\n').unwrap_or((content, ""));
+    let updated = match first_line.trim_end() {
+        "---" => format!("{first_line}\nid: {id}\n{rest}"),
+        "+++" => format!("{first_line}\nid = \"{id}\"\n{rest}"),
+        _ => format!("---\nid: {id}\n---\n{content}"),
+    };
+
+    Ok(Some(updated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_template() {
+        let id = Ulid::new();
+        let content = page_template(id, Some("Hello: World".into()), vec!["rust".into()]).unwrap();
+
+        let (frontmatter, markdown) = Page::split_frontmatter(&content).unwrap();
+        assert_eq!(frontmatter.id, Some(id));
+        assert_eq!(frontmatter.title, Some("Hello: World".into()));
+        assert_eq!(frontmatter.tags, Some(HashSet::from(["rust".into()])));
+        assert!(markdown.starts_with("# Hello: World"));
+    }
+
+    #[test]
+    fn test_new_page_rejects_paths_outside_pages() {
+        assert!(new_page("../outside", None, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_insert_id_into_yaml_frontmatter() {
+        let id = Ulid::new();
+        let updated = insert_id("---\ntitle: Test\n---\n# Body\n", id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            updated,
+            format!("---\nid: {id}\ntitle: Test\n---\n# Body\n")
+        );
+
+        let (frontmatter, markdown) = Page::split_frontmatter(&updated).unwrap();
+        assert_eq!(frontmatter.id, Some(id));
+        assert_eq!(frontmatter.title, Some("Test".into()));
+        assert_eq!(markdown, "# Body");
+    }
+
+    #[test]
+    fn test_insert_id_into_toml_frontmatter() {
+        let id = Ulid::new();
+        let updated = insert_id("+++\ntitle = \"Test\"\n+++\n# Body\n", id)
+            .unwrap()
+            .unwrap();
+        let (frontmatter, _) = Page::split_frontmatter(&updated).unwrap();
+        assert_eq!(frontmatter.id, Some(id));
+        assert_eq!(frontmatter.title, Some("Test".into()));
+    }
+
+    #[test]
+    fn test_insert_id_without_frontmatter() {
+        let id = Ulid::new();
+        let updated = insert_id("# Body\n", id).unwrap().unwrap();
+        assert_eq!(updated, format!("---\nid: {id}\n---\n# Body\n"));
+    }
+
+    #[test]
+    fn test_insert_id_is_idempotent() {
+        let content = format!("---\nid: {}\n---\n# Body\n", Ulid::new());
+        assert_eq!(insert_id(&content, Ulid::new()).unwrap(), None);
+    }
+}
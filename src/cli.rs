@@ -1,7 +1,6 @@
-use anyhow::Result;
-use clap::Parser;
+use std::path::PathBuf;
 
-use crate::web::start_server;
+use clap::{Parser, Subcommand};
 
 /// Website server command-line interface
 #[derive(Parser, Debug)]
@@ -13,18 +12,38 @@ use crate::web::start_server;
     propagate_version = true
 )]
 pub struct Cli {
-    
-};
+    /// Directory of the pages, search index and logs, overriding `data_path`
+    /// in the config file
+    #[arg(long, global = true, env = "WEBSITE_DATA_PATH")]
+    pub data_path: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Start the web server, the default when no command is given
+    Serve,
+    /// Report internal links that do not resolve to a page, exits non-zero if any are found
+    CheckLinks,
+    /// Add a generated id to the frontmatter of pages that lack one
+    FixIds,
+    /// Create a new page from a template
+    New {
+        /// URL path of the page, e.g. `blog/first-post`
+        path: String,
+        /// Title of the page
+        #[arg(long)]
+        title: Option<String>,
+        /// Comma separated list of tags
+        #[arg(long, value_delimiter = ',')]
+        tags: Vec<String>,
+    },
+}
 
 impl Cli {
     /// Parse command-line arguments with Clap
     pub fn parse_args() -> Self {
         Self::parse()
     }
-
-    /// Start web server
-    pub async fn start(&self) -> Result<()> {
-        tracing::info!("🚀 Starting website server in production mode...");
-        start_server().await
-    }
 }
@@ -1,12 +1,60 @@
-use std::{path::PathBuf, str::FromStr, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    str::FromStr,
+    sync::OnceLock,
+    time::Duration,
+};
 
-use cached::proc_macro::cached;
+use bytesize::ByteSize;
 use derive_getters::Getters;
+use milli::Criterion;
 use serde::{Deserialize, Deserializer, Serialize};
 use tracing::Level;
 
-#[cached]
+use crate::{gone::normalize_gone_path, page::is_language_tag};
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Overrides of the config file settings, from command-line flags or their
+/// environment variables.
+#[derive(Debug, Default)]
+pub struct ConfigOverrides {
+    pub data_path: Option<PathBuf>,
+}
+
+/// Loads the config with `overrides` applied, to be called once on start
+/// before anything calls [`load_config`]. Later calls return the config
+/// loaded first, without applying their overrides.
+pub fn init_config(overrides: ConfigOverrides) -> Config {
+    CONFIG.get_or_init(|| read_config(overrides)).clone()
+}
+
+/// The config of the running server, read from the config file the first
+/// time it is needed unless [`init_config`] was called before.
 pub fn load_config() -> Config {
+    CONFIG
+        .get_or_init(|| read_config(default_overrides()))
+        .clone()
+}
+
+#[cfg(not(test))]
+fn default_overrides() -> ConfigOverrides {
+    ConfigOverrides::default()
+}
+
+/// Tests get a data directory of their own, rather than the one of a site
+/// served from the machine.
+#[cfg(test)]
+fn default_overrides() -> ConfigOverrides {
+    ConfigOverrides {
+        data_path: Some(std::env::temp_dir().join(format!("website-test-{}", std::process::id()))),
+    }
+}
+
+/// Reads the config file, applying `overrides` on top of it. Missing settings
+/// fall back on their defaults.
+fn read_config(overrides: ConfigOverrides) -> Config {
     let config_path = dirs::config_local_dir()
         .unwrap_or(PathBuf::from_str("./config").unwrap())
         .join("website/config.toml");
@@ -16,12 +64,12 @@ pub fn load_config() -> Config {
         config_path.to_string_lossy()
     );
 
-    let mut config = None;
+    let mut parsed = None;
     if let Ok(data) = std::fs::read_to_string(config_path) {
         match toml::from_str::<ConfigParsed>(&data) {
-            Ok(parsed) => {
+            Ok(config) => {
                 println!(" found and loaded!");
-                config = Some(Config::from(parsed));
+                parsed = Some(config);
             }
             Err(error) => {
                 println!();
@@ -30,26 +78,236 @@ pub fn load_config() -> Config {
         };
     }
 
-    if config.is_none() {
-        config = Some(Config::from(ConfigParsed::default()));
+    let mut parsed = parsed.unwrap_or_else(|| {
         eprintln!("⚠️ Unable to find config file, falling back on default config");
-    }
-
-    config.unwrap()
+        ConfigParsed::default()
+    });
+    parsed.apply(overrides);
+    Config::from(parsed)
 }
 
 #[derive(Default, Serialize, Deserialize)]
 pub struct ConfigParsed {
     title: Option<String>,
     port: Option<u16>,
+    /// Public URL of the site, used where absolute links are required.
+    base_url: Option<String>,
+    /// Path prefix when hosting the site below the domain root, e.g. `/docs`.
+    base_path: Option<String>,
     data_path: Option<PathBuf>,
     log_level: Option<ConfigLogLevel>,
+    /// Name of the log files in the logs directory, suffixed with the date
+    /// and hour of the period they cover when rotated.
+    log_file_name: Option<String>,
+    /// How often to start a new log file, `hourly`, `daily` or `never`.
+    log_rotation: Option<ConfigLogRotation>,
+    /// Number of rotated log files to keep, deleting the oldest ones beyond
+    /// it. All are kept when unset.
+    log_max_files: Option<usize>,
+    /// Refuse to start when core assets are missing instead of only warning.
+    strict_assets: Option<bool>,
+    /// Fail indexing when two pages share the same id, or URL when that is
+    /// the search primary key, instead of only warning, as one of them would
+    /// silently replace the other.
+    strict_ids: Option<bool>,
+    /// Render `$...$` and `$$...$$` LaTeX math to MathML on the server.
+    render_math: Option<bool>,
+    /// Render straight quotes as curly ones, `--` and `---` as en and em
+    /// dashes and `...` as an ellipsis.
+    smart_typography: Option<bool>,
+    /// File served for URL paths ending in `/`, and linked to by the URL of
+    /// its directory, e.g. `README.md` or `_index.md`. Defaults to
+    /// `index.md`.
+    directory_index: Option<String>,
+    /// Levels added to the markdown headings of pages, so with `1` a `#`
+    /// heading renders as `<h2>`. Headings never go below `<h6>`.
+    heading_offset: Option<u8>,
+    /// Casing of page tags, `lowercase` by default so `Rust` and `rust` are
+    /// the same tag, or `preserve` to keep them as written. Whitespace in tags
+    /// is trimmed and collapsed either way.
+    tag_case: Option<ConfigTagCase>,
+    /// Put between the page title and the site title, or the suffix a page
+    /// or section sets, in document titles. Defaults to ` - `.
+    title_separator: Option<String>,
+    /// Language of pages without a language in their file name, and of the
+    /// version served when none of a page's translations match the
+    /// `Accept-Language` of a request. Defaults to `en`.
+    default_language: Option<String>,
+    /// Open links to other hosts than `base_url` in a new tab.
+    external_links_new_tab: Option<bool>,
+    /// Let browsers load page images lazily and decode them off the main
+    /// thread. On by default.
+    lazy_images: Option<bool>,
+    /// How often to reindex all pages, e.g. `30m`, defaults to an hour. Set
+    /// to `never` or `0` to only index on start, on file changes when
+    /// `watch_pages` is on and through `/admin/reindex`.
     #[serde(
         default,
-        deserialize_with = "deserialize_option_duration",
+        deserialize_with = "deserialize_option_interval",
         skip_serializing_if = "Option::is_none"
     )]
     search_reindex_interval: Option<Duration>,
+    /// How often to compact the search index, e.g. `1d`, reclaiming disk
+    /// space left behind by reindexes. Off when unset.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_option_duration",
+        skip_serializing_if = "Option::is_none"
+    )]
+    search_compact_interval: Option<Duration>,
+    /// Reindex shortly after page files change. On by default, turn off for
+    /// read-only content or mounts where filesystem events do not work, to
+    /// rely on the periodic reindex alone.
+    watch_pages: Option<bool>,
+    /// Index all pages before the server starts listening, so searches right
+    /// after a deploy do not miss pages. Delays startup on large sites.
+    warm_index_on_start: Option<bool>,
+    /// Time after which a request is aborted with `408 Request Timeout`,
+    /// e.g. `30s`.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_option_duration",
+        skip_serializing_if = "Option::is_none"
+    )]
+    request_timeout: Option<Duration>,
+    /// Maximum number of requests handled at the same time, further requests
+    /// wait for a free slot.
+    max_concurrent_requests: Option<usize>,
+    /// Report how long reading and rendering pages took in a `Server-Timing`
+    /// header. Off by default as it reveals internals.
+    server_timing: Option<bool>,
+    /// Encodings used to compress responses, in order of preference.
+    compression_encodings: Option<Vec<ConfigEncoding>>,
+    /// Compression level per encoding, either `fastest`, `default`, `best`
+    /// or a number in the range of the algorithm, e.g. `{ br = 4 }`.
+    compression_levels: Option<HashMap<ConfigEncoding, ConfigCompressionLevel>>,
+    /// Responses smaller than this many bytes are sent uncompressed.
+    compression_min_size: Option<u16>,
+    /// `Cache-Control` header of page responses, e.g.
+    /// `public, max-age=60, stale-while-revalidate=300`. Drafts are always
+    /// sent with `no-store`.
+    page_cache_control: Option<String>,
+    /// URL paths of removed pages answered with `410 Gone` rather than `404
+    /// Not Found`, so search engines drop them sooner, e.g. `["old/page"]`.
+    /// Further paths can be listed one per line in `gone.txt` in the data
+    /// directory.
+    gone_paths: Option<Vec<String>>,
+    /// Pages larger than this, e.g. `10MiB`, are skipped with a warning.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_option_byte_size",
+        skip_serializing_if = "Option::is_none"
+    )]
+    max_page_bytes: Option<ByteSize>,
+    /// Maximum size of each search index, e.g. `2GiB`. LMDB reserves this
+    /// much address space up front but only allocates disk and memory as the
+    /// index grows, so it is an upper bound rather than an allocation.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_option_byte_size",
+        skip_serializing_if = "Option::is_none"
+    )]
+    search_map_size: Option<ByteSize>,
+    /// Sentinel inserted before each search match, swapped for the
+    /// highlight tag when rendering excerpts. Should never occur in content.
+    highlight_prefix: Option<String>,
+    /// Sentinel inserted after each search match.
+    highlight_suffix: Option<String>,
+    /// HTML tag wrapping search matches in excerpts, e.g. `strong`.
+    highlight_tag: Option<String>,
+    /// Path of the favicon in the assets directory, e.g. `favicon.ico`. A
+    /// transparent placeholder is served when unset.
+    favicon: Option<String>,
+    /// Theme color of the installed web app and the browser interface, e.g.
+    /// `#336699`.
+    theme_color: Option<String>,
+    /// Theme color used when the reader prefers a dark color scheme.
+    theme_color_dark: Option<String>,
+    /// Stylesheet in the assets directory applied on top of `styles.css` when
+    /// the reader prefers a dark color scheme, e.g. `dark.css`.
+    dark_stylesheet: Option<String>,
+    /// Generate Open Graph preview images of pages at `/og/<page>.png`,
+    /// e.g. `{ background = "#1f2937", foreground = "#f9fafb", accent =
+    /// "#38bdf8", lines = ["site_title", "title", "tags"] }`. Off when unset,
+    /// an empty table enables them with the default colors and lines.
+    og_image: Option<ConfigOgImage>,
+    /// Animate navigation between pages with a cross-fade view transition.
+    /// On by default, turn off to style transitions in your own stylesheet.
+    view_transitions: Option<bool>,
+    /// Length of the view transition animation, e.g. `120ms`.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_option_duration",
+        skip_serializing_if = "Option::is_none"
+    )]
+    view_transition_duration: Option<Duration>,
+    /// CSS timing function of the view transition animation, e.g. `linear`
+    /// or `cubic-bezier(0.4, 0, 0.2, 1)`.
+    view_transition_timing: Option<String>,
+    /// Background color of the web app splash screen.
+    background_color: Option<String>,
+    /// Web app icons, with `src` as a path in the assets directory, e.g.
+    /// `{ src = "icon-512.png", sizes = "512x512" }`.
+    icons: Option<Vec<ConfigIcon>>,
+    /// Further page directories served below their own URL prefix, e.g.
+    /// `[[collections]]` tables with `name = "docs"`, `path = "docs"` and
+    /// `prefix = "docs"`. Paths are relative to the data directory unless
+    /// absolute. Searches and listings can be limited to a collection by name.
+    #[serde(default, deserialize_with = "deserialize_option_collections")]
+    collections: Option<Vec<ConfigCollection>>,
+    /// HTML allowed in pages and search excerpts on top of the safe defaults,
+    /// e.g. `{ tags = ["video"], attributes = { video = ["controls", "src"] },
+    /// classes = { span = ["note"] } }`. Every addition widens what page
+    /// authors can inject into the site, so only allow what is needed.
+    allowed_html: Option<ConfigAllowedHtml>,
+    /// Number of most recent pages included in feeds and other lists of
+    /// recent pages. Feeds are capped rather than listing every page, so
+    /// their responses stay small on large sites.
+    #[serde(default, deserialize_with = "deserialize_option_count")]
+    feed_item_count: Option<usize>,
+    /// Number of pages per page of author and other listings.
+    #[serde(default, deserialize_with = "deserialize_option_count")]
+    listing_page_size: Option<usize>,
+    /// Number of pages above which the sitemap is split into `sitemap-N.xml`
+    /// files listed by a sitemap index at `/sitemap.xml`. Pages are spread
+    /// over the files by URL, so each holds about this many. At most 40,000
+    /// to stay below the 50,000 URLs allowed per sitemap.
+    #[serde(default, deserialize_with = "deserialize_option_shard_size")]
+    sitemap_shard_size: Option<usize>,
+    /// Rules ranking search results, in order of importance. Any of `words`,
+    /// `typo`, `proximity`, `attribute`, `sort` and `exactness`, or a custom
+    /// `<field>:asc` or `<field>:desc` rule on `modified`, `title` or `url`.
+    /// Changes take effect once the pages are reindexed.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_option_ranking_rules",
+        serialize_with = "serialize_option_ranking_rules",
+        skip_serializing_if = "Option::is_none"
+    )]
+    ranking_rules: Option<Vec<Criterion>>,
+    /// Field identifying the documents in the search index, `id` or `url`.
+    /// Changing it requires deleting the search directory so the index is
+    /// rebuilt.
+    #[serde(default, deserialize_with = "deserialize_option_primary_key")]
+    search_primary_key: Option<String>,
+    /// Fields searched for query words, in order of importance, from
+    /// `title`, `description`, `markdown`, `tags`, `author` and `url`. The
+    /// `title:` operator needs `title` among them. Changing them requires
+    /// deleting the search directory so the index is rebuilt.
+    #[serde(default, deserialize_with = "deserialize_option_searchable_fields")]
+    search_searchable_fields: Option<Vec<String>>,
+    /// Bearer token required by the `/admin` endpoints. They reject every
+    /// request when no token is set.
+    admin_token: Option<String>,
+}
+
+impl ConfigParsed {
+    /// Replaces the settings given in `overrides`.
+    fn apply(&mut self, overrides: ConfigOverrides) {
+        if let Some(data_path) = overrides.data_path {
+            self.data_path = Some(data_path);
+        }
+    }
 }
 
 fn deserialize_option_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
@@ -61,13 +319,209 @@ where
         .transpose()
 }
 
+/// Like [`deserialize_option_duration`], reading `never` as a zero duration.
+fn deserialize_option_interval<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: Option<String> = Option::deserialize(deserializer)?;
+    s.map(|s| match s.trim() {
+        "never" => Ok(Duration::ZERO),
+        s => duration_str::parse(s).map_err(serde::de::Error::custom),
+    })
+    .transpose()
+}
+
+fn deserialize_option_byte_size<'de, D>(deserializer: D) -> Result<Option<ByteSize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: Option<String> = Option::deserialize(deserializer)?;
+    s.map(|s| s.parse::<ByteSize>().map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// Rejects a count of zero, which would leave out every page.
+fn deserialize_option_count<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<usize>::deserialize(deserializer)? {
+        Some(0) => Err(serde::de::Error::custom("must be at least 1")),
+        count => Ok(count),
+    }
+}
+
+/// Rejects sitemap shard sizes that leave out every page or could put more
+/// URLs in a shard than a sitemap allows.
+fn deserialize_option_shard_size<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<usize>::deserialize(deserializer)? {
+        Some(size) if !(1..=40_000).contains(&size) => {
+            Err(serde::de::Error::custom("must be between 1 and 40000"))
+        }
+        size => Ok(size),
+    }
+}
+
+/// Document fields with a unique value per page, usable as primary key.
+const PRIMARY_KEY_FIELDS: [&str; 2] = ["id", "url"];
+
+/// Document fields holding text that can be made searchable.
+const SEARCHABLE_FIELDS: [&str; 6] = ["title", "description", "markdown", "tags", "author", "url"];
+
+fn deserialize_option_primary_key<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let key: Option<String> = Option::deserialize(deserializer)?;
+    key.map(|key| known_field(key, &PRIMARY_KEY_FIELDS))
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+fn deserialize_option_searchable_fields<'de, D>(
+    deserializer: D,
+) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let fields: Option<Vec<String>> = Option::deserialize(deserializer)?;
+    match fields {
+        Some(fields) if fields.is_empty() => Err(serde::de::Error::custom(
+            "at least one searchable field is required",
+        )),
+        fields => fields
+            .map(|fields| {
+                fields
+                    .into_iter()
+                    .map(|field| known_field(field, &SEARCHABLE_FIELDS))
+                    .collect()
+            })
+            .transpose()
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+/// Checks that `field` is one of `known`, naming them in the error otherwise.
+fn known_field(field: String, known: &[&str]) -> Result<String, String> {
+    if known.contains(&field.as_str()) {
+        Ok(field)
+    } else {
+        Err(format!(
+            "unknown field `{field}`, expected one of {}",
+            known.join(", ")
+        ))
+    }
+}
+
+/// Document fields that custom `asc` and `desc` ranking rules may sort on.
+const RANKING_FIELDS: [&str; 3] = ["modified", "title", "url"];
+
+fn deserialize_option_ranking_rules<'de, D>(
+    deserializer: D,
+) -> Result<Option<Vec<Criterion>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let rules: Option<Vec<String>> = Option::deserialize(deserializer)?;
+    rules
+        .map(|rules| rules.iter().map(|rule| parse_ranking_rule(rule)).collect())
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+fn serialize_option_ranking_rules<S>(
+    rules: &Option<Vec<Criterion>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    rules
+        .as_ref()
+        .map(|rules| rules.iter().map(Criterion::to_string).collect::<Vec<_>>())
+        .serialize(serializer)
+}
+
+fn parse_ranking_rule(rule: &str) -> Result<Criterion, String> {
+    let criterion = rule
+        .parse::<Criterion>()
+        .map_err(|error| error.to_string())?;
+    match &criterion {
+        Criterion::Asc(field) | Criterion::Desc(field)
+            if !RANKING_FIELDS.contains(&field.as_str()) =>
+        {
+            Err(format!(
+                "`{rule}` ranks on an unknown field, expected one of {}",
+                RANKING_FIELDS.join(", ")
+            ))
+        }
+        _ => Ok(criterion),
+    }
+}
+
 #[derive(Clone, Getters, Serialize)]
 pub struct Config {
     title: String,
     port: u16,
+    base_url: String,
+    base_path: String,
     data_path: PathBuf,
     log_level: ConfigLogLevel,
-    search_reindex_interval: Duration,
+    log_file_name: String,
+    log_rotation: ConfigLogRotation,
+    log_max_files: Option<usize>,
+    strict_assets: bool,
+    strict_ids: bool,
+    render_math: bool,
+    smart_typography: bool,
+    directory_index: String,
+    heading_offset: u8,
+    tag_case: ConfigTagCase,
+    title_separator: String,
+    default_language: String,
+    external_links_new_tab: bool,
+    lazy_images: bool,
+    search_reindex_interval: Option<Duration>,
+    search_compact_interval: Option<Duration>,
+    watch_pages: bool,
+    warm_index_on_start: bool,
+    server_timing: bool,
+    compression_encodings: Vec<ConfigEncoding>,
+    compression_levels: HashMap<ConfigEncoding, ConfigCompressionLevel>,
+    compression_min_size: u16,
+    page_cache_control: String,
+    gone_paths: HashSet<String>,
+    max_page_bytes: u64,
+    search_map_size: usize,
+    request_timeout: Duration,
+    max_concurrent_requests: usize,
+    highlight_prefix: String,
+    highlight_suffix: String,
+    highlight_tag: String,
+    favicon: Option<String>,
+    theme_color: Option<String>,
+    theme_color_dark: Option<String>,
+    dark_stylesheet: Option<String>,
+    og_image: Option<ConfigOgImage>,
+    view_transitions: bool,
+    view_transition_duration: Duration,
+    view_transition_timing: String,
+    background_color: Option<String>,
+    icons: Vec<ConfigIcon>,
+    collections: Vec<ConfigCollection>,
+    allowed_html: ConfigAllowedHtml,
+    feed_item_count: usize,
+    listing_page_size: usize,
+    sitemap_shard_size: usize,
+    ranking_rules: Vec<Criterion>,
+    search_primary_key: String,
+    search_searchable_fields: Vec<String>,
+    #[serde(skip_serializing)]
+    admin_token: Option<String>,
 }
 
 impl Config {
@@ -82,6 +536,26 @@ impl Config {
     pub fn search_path(&self) -> PathBuf {
         self.data_path.join("search")
     }
+
+    /// Directory of the generated Open Graph preview images.
+    pub fn og_image_path(&self) -> PathBuf {
+        self.data_path.join("og")
+    }
+
+    /// Directory of the pages in `collection`.
+    pub fn collection_path(&self, collection: &ConfigCollection) -> PathBuf {
+        self.data_path.join(&collection.path)
+    }
+
+    /// Prefixes an absolute site path with the configured base path.
+    pub fn route(&self, path: &str) -> String {
+        format!("{}{}", self.base_path, path)
+    }
+
+    /// Turns an absolute site path into a full URL using the base URL.
+    pub fn absolute_url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, self.route(path))
+    }
 }
 
 impl From<ConfigParsed> for Config {
@@ -89,19 +563,365 @@ impl From<ConfigParsed> for Config {
         Self {
             title: value.title.unwrap_or("Welcome".into()),
             port: 4000,
+            base_url: value
+                .base_url
+                .map(|base_url| base_url.trim_end_matches('/').to_string())
+                .unwrap_or("http://localhost:4000".into()),
+            base_path: normalize_base_path(value.base_path.unwrap_or_default()),
             data_path: value.data_path.unwrap_or(
                 dirs::data_local_dir()
                     .unwrap_or(PathBuf::from_str("./data").unwrap())
                     .join("website/"),
             ),
             log_level: value.log_level.unwrap_or(ConfigLogLevel::Info),
+            log_file_name: value
+                .log_file_name
+                .filter(|name| !name.trim().is_empty())
+                .unwrap_or("website.log".into()),
+            log_rotation: value.log_rotation.unwrap_or_default(),
+            log_max_files: value.log_max_files.filter(|count| *count > 0),
+            strict_assets: value.strict_assets.unwrap_or(false),
+            strict_ids: value.strict_ids.unwrap_or(false),
+            render_math: value.render_math.unwrap_or(false),
+            smart_typography: value.smart_typography.unwrap_or(false),
+            directory_index: value
+                .directory_index
+                .map(|name| name.trim().to_string())
+                .filter(|name| {
+                    name.ends_with(".md") && name.len() > ".md".len() && !name.contains(['/', '\\'])
+                })
+                .unwrap_or("index.md".into()),
+            heading_offset: value.heading_offset.unwrap_or(0).min(5),
+            tag_case: value.tag_case.unwrap_or_default(),
+            title_separator: value.title_separator.unwrap_or(" - ".into()),
+            default_language: value
+                .default_language
+                .map(|language| language.trim().to_string())
+                .filter(|language| is_language_tag(language))
+                .unwrap_or("en".into()),
+            external_links_new_tab: value.external_links_new_tab.unwrap_or(false),
+            lazy_images: value.lazy_images.unwrap_or(true),
             search_reindex_interval: value
                 .search_reindex_interval
-                .unwrap_or(Duration::from_secs(3600)),
+                .map_or(Some(Duration::from_secs(3600)), |interval| {
+                    (!interval.is_zero()).then_some(interval)
+                }),
+            search_compact_interval: value.search_compact_interval,
+            watch_pages: value.watch_pages.unwrap_or(true),
+            warm_index_on_start: value.warm_index_on_start.unwrap_or(false),
+            server_timing: value.server_timing.unwrap_or(false),
+            compression_encodings: value.compression_encodings.unwrap_or(vec![
+                ConfigEncoding::Zstd,
+                ConfigEncoding::Br,
+                ConfigEncoding::Gzip,
+                ConfigEncoding::Deflate,
+            ]),
+            compression_levels: value.compression_levels.unwrap_or_default(),
+            compression_min_size: value.compression_min_size.unwrap_or(32),
+            page_cache_control: value
+                .page_cache_control
+                .unwrap_or("public, max-age=0, must-revalidate".into()),
+            gone_paths: value
+                .gone_paths
+                .unwrap_or_default()
+                .iter()
+                .map(|path| normalize_gone_path(path))
+                .collect(),
+            max_page_bytes: value
+                .max_page_bytes
+                .map(|size| size.as_u64())
+                .unwrap_or(10 * 1024 * 1024),
+            search_map_size: value
+                .search_map_size
+                .map(|size| size.as_u64() as usize)
+                .unwrap_or(1024 * 1024 * 1024),
+            request_timeout: value.request_timeout.unwrap_or(Duration::from_secs(30)),
+            max_concurrent_requests: value
+                .max_concurrent_requests
+                .filter(|limit| *limit > 0)
+                .unwrap_or(1024),
+            highlight_prefix: value
+                .highlight_prefix
+                .filter(|prefix| !prefix.is_empty())
+                .unwrap_or("\u{E000}".into()),
+            highlight_suffix: value
+                .highlight_suffix
+                .filter(|suffix| !suffix.is_empty())
+                .unwrap_or("\u{E001}".into()),
+            highlight_tag: value
+                .highlight_tag
+                .map(|tag| tag.trim().trim_matches(['<', '>']).to_ascii_lowercase())
+                .filter(|tag| !tag.is_empty() && tag.chars().all(|c| c.is_ascii_alphanumeric()))
+                .unwrap_or("mark".into()),
+            favicon: value.favicon,
+            theme_color: value.theme_color,
+            theme_color_dark: value.theme_color_dark,
+            dark_stylesheet: value.dark_stylesheet,
+            og_image: value.og_image,
+            view_transitions: value.view_transitions.unwrap_or(true),
+            view_transition_duration: value
+                .view_transition_duration
+                .unwrap_or(Duration::from_millis(50)),
+            view_transition_timing: value
+                .view_transition_timing
+                .map(|timing| timing.trim().to_string())
+                .filter(|timing| {
+                    !timing.is_empty()
+                        && timing
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || " -_(),.".contains(c))
+                })
+                .unwrap_or("ease-in-out".into()),
+            background_color: value.background_color,
+            icons: value.icons.unwrap_or_default(),
+            collections: value.collections.unwrap_or_default(),
+            allowed_html: value.allowed_html.unwrap_or_default(),
+            feed_item_count: value.feed_item_count.unwrap_or(20),
+            listing_page_size: value.listing_page_size.unwrap_or(50),
+            sitemap_shard_size: value.sitemap_shard_size.unwrap_or(10_000),
+            ranking_rules: value
+                .ranking_rules
+                .filter(|rules| !rules.is_empty())
+                .unwrap_or_else(milli::default_criteria),
+            search_primary_key: value.search_primary_key.unwrap_or("id".into()),
+            search_searchable_fields: value.search_searchable_fields.unwrap_or_else(|| {
+                ["title", "description", "markdown", "tags"]
+                    .map(String::from)
+                    .into()
+            }),
+            admin_token: value.admin_token,
         }
     }
 }
 
+fn normalize_base_path(base_path: String) -> String {
+    let base_path = base_path.trim().trim_matches('/');
+    if base_path.is_empty() {
+        String::new()
+    } else {
+        format!("/{base_path}")
+    }
+}
+
+/// A directory of pages served below a URL prefix.
+#[derive(Clone, Debug, Getters, Serialize, Deserialize)]
+pub struct ConfigCollection {
+    name: String,
+    path: PathBuf,
+    prefix: String,
+}
+
+fn deserialize_option_collections<'de, D>(
+    deserializer: D,
+) -> Result<Option<Vec<ConfigCollection>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let collections: Option<Vec<ConfigCollection>> = Option::deserialize(deserializer)?;
+    collections
+        .map(|collections| {
+            let mut names = HashSet::new();
+            let mut prefixes = HashSet::new();
+            collections
+                .into_iter()
+                .map(|mut collection| {
+                    collection.prefix = collection.prefix.trim_matches('/').to_string();
+                    if collection.name.trim().is_empty() || collection.prefix.is_empty() {
+                        Err("collections need a name and a prefix".to_string())
+                    } else if !names.insert(collection.name.clone()) {
+                        Err(format!("collection `{}` is defined twice", collection.name))
+                    } else if !prefixes.insert(collection.prefix.clone()) {
+                        Err(format!("prefix `{}` is used twice", collection.prefix))
+                    } else {
+                        Ok(collection)
+                    }
+                })
+                .collect()
+        })
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+/// Extra HTML let through the sanitizer, by tag name.
+#[derive(Clone, Debug, Default, Getters, Serialize, Deserialize)]
+pub struct ConfigAllowedHtml {
+    #[serde(default, deserialize_with = "deserialize_allowed_tags")]
+    tags: HashSet<String>,
+    #[serde(default, deserialize_with = "deserialize_allowed_attributes")]
+    attributes: HashMap<String, HashSet<String>>,
+    #[serde(default)]
+    classes: HashMap<String, HashSet<String>>,
+}
+
+/// Tags whose content the sanitizer always removes, which can not be allowed.
+const REMOVED_CONTENT_TAGS: [&str; 2] = ["script", "style"];
+
+fn deserialize_allowed_tags<'de, D>(deserializer: D) -> Result<HashSet<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let tags = HashSet::<String>::deserialize(deserializer)?;
+    match tags
+        .iter()
+        .find(|tag| REMOVED_CONTENT_TAGS.contains(&tag.to_ascii_lowercase().as_str()))
+    {
+        Some(tag) => Err(serde::de::Error::custom(format!(
+            "`{tag}` can not be allowed"
+        ))),
+        None => Ok(tags),
+    }
+}
+
+fn deserialize_allowed_attributes<'de, D>(
+    deserializer: D,
+) -> Result<HashMap<String, HashSet<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let attributes = HashMap::<String, HashSet<String>>::deserialize(deserializer)?;
+    if attributes
+        .values()
+        .any(|attributes| attributes.iter().any(|a| a.eq_ignore_ascii_case("class")))
+    {
+        return Err(serde::de::Error::custom(
+            "allow class names with `classes` rather than the `class` attribute",
+        ));
+    }
+    Ok(attributes)
+}
+
+/// Colors and content of the generated Open Graph preview images.
+#[derive(Clone, Debug, Getters, Serialize, Deserialize)]
+pub struct ConfigOgImage {
+    #[serde(
+        default = "default_og_background",
+        deserialize_with = "deserialize_color",
+        serialize_with = "serialize_color"
+    )]
+    background: [u8; 3],
+    #[serde(
+        default = "default_og_foreground",
+        deserialize_with = "deserialize_color",
+        serialize_with = "serialize_color"
+    )]
+    foreground: [u8; 3],
+    #[serde(
+        default = "default_og_accent",
+        deserialize_with = "deserialize_color",
+        serialize_with = "serialize_color"
+    )]
+    accent: [u8; 3],
+    /// What the image shows, from the top down.
+    #[serde(default = "default_og_lines")]
+    lines: Vec<ConfigOgLine>,
+}
+
+/// A block of text on an Open Graph preview image.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigOgLine {
+    SiteTitle,
+    Title,
+    Description,
+    Tags,
+    Author,
+}
+
+fn default_og_background() -> [u8; 3] {
+    [0x1f, 0x29, 0x37]
+}
+
+fn default_og_foreground() -> [u8; 3] {
+    [0xf9, 0xfa, 0xfb]
+}
+
+fn default_og_accent() -> [u8; 3] {
+    [0x38, 0xbd, 0xf8]
+}
+
+fn default_og_lines() -> Vec<ConfigOgLine> {
+    vec![
+        ConfigOgLine::SiteTitle,
+        ConfigOgLine::Title,
+        ConfigOgLine::Tags,
+    ]
+}
+
+/// Parses a `#rrggbb` or `#rgb` color.
+fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#')?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    match hex.len() {
+        3 => {
+            let mut rgb = [0; 3];
+            for (index, digit) in hex.chars().enumerate() {
+                rgb[index] = channel(&digit.to_string())? * 0x11;
+            }
+            Some(rgb)
+        }
+        6 => Some([
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        ]),
+        _ => None,
+    }
+}
+
+fn deserialize_color<'de, D>(deserializer: D) -> Result<[u8; 3], D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let color = String::deserialize(deserializer)?;
+    parse_color(&color)
+        .ok_or_else(|| serde::de::Error::custom(format!("`{color}` is not a `#rrggbb` color")))
+}
+
+fn serialize_color<S>(color: &[u8; 3], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_str(&format!(
+        "#{:02x}{:02x}{:02x}",
+        color[0], color[1], color[2]
+    ))
+}
+
+#[derive(Clone, Debug, Getters, Serialize, Deserialize)]
+pub struct ConfigIcon {
+    src: String,
+    sizes: Option<String>,
+    #[serde(rename = "type")]
+    mime_type: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigEncoding {
+    Gzip,
+    Deflate,
+    Br,
+    Zstd,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ConfigCompressionLevel {
+    Named(ConfigCompressionLevelName),
+    Precise(i32),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigCompressionLevelName {
+    Fastest,
+    Default,
+    Best,
+}
+
 #[repr(usize)]
 #[derive(Default, Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -114,6 +934,23 @@ pub enum ConfigLogLevel {
     Trace,
 }
 
+#[derive(Default, Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigTagCase {
+    #[default]
+    Lowercase,
+    Preserve,
+}
+
+#[derive(Default, Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigLogRotation {
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
 impl From<Level> for ConfigLogLevel {
     fn from(value: Level) -> Self {
         match value {
@@ -137,3 +974,361 @@ impl From<ConfigLogLevel> for Level {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_map_size() {
+        let config = Config::from(ConfigParsed::default());
+        assert_eq!(*config.search_map_size(), 1024 * 1024 * 1024);
+
+        let config =
+            Config::from(toml::from_str::<ConfigParsed>(r#"search_map_size = "2GiB""#).unwrap());
+        assert_eq!(*config.search_map_size(), 2 * 1024 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_item_counts_must_be_positive() {
+        let config = Config::from(ConfigParsed::default());
+        assert_eq!(*config.feed_item_count(), 20);
+        assert_eq!(*config.listing_page_size(), 50);
+
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>("feed_item_count = 5\nlisting_page_size = 10").unwrap(),
+        );
+        assert_eq!(*config.feed_item_count(), 5);
+        assert_eq!(*config.listing_page_size(), 10);
+
+        for setting in ["feed_item_count = 0", "listing_page_size = 0"] {
+            let error = toml::from_str::<ConfigParsed>(setting).err().unwrap();
+            assert!(error.to_string().contains("must be at least 1"), "{error}");
+        }
+    }
+
+    #[test]
+    fn test_ranking_rules() {
+        let config = Config::from(ConfigParsed::default());
+        assert_eq!(*config.ranking_rules(), milli::default_criteria());
+
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>(
+                r#"ranking_rules = ["exactness", "words", "modified:desc"]"#,
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            *config.ranking_rules(),
+            [
+                Criterion::Exactness,
+                Criterion::Words,
+                Criterion::Desc("modified".into())
+            ]
+        );
+
+        let error = toml::from_str::<ConfigParsed>(r#"ranking_rules = ["wrods"]"#)
+            .err()
+            .unwrap();
+        assert!(
+            error
+                .to_string()
+                .contains("`wrods` ranking rule is invalid")
+        );
+
+        let error = toml::from_str::<ConfigParsed>(r#"ranking_rules = ["modifed:desc"]"#)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("unknown field"));
+    }
+
+    #[test]
+    fn test_search_document_fields() {
+        let config = Config::from(ConfigParsed::default());
+        assert_eq!(config.search_primary_key(), "id");
+        assert_eq!(
+            *config.search_searchable_fields(),
+            ["title", "description", "markdown", "tags"]
+        );
+
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>(
+                "search_primary_key = \"url\"\nsearch_searchable_fields = [\"title\", \"author\"]",
+            )
+            .unwrap(),
+        );
+        assert_eq!(config.search_primary_key(), "url");
+        assert_eq!(*config.search_searchable_fields(), ["title", "author"]);
+
+        for invalid in [
+            "search_primary_key = \"\"",
+            "search_primary_key = \"title\"",
+            "search_searchable_fields = []",
+            "search_searchable_fields = [\"titel\"]",
+        ] {
+            assert!(
+                toml::from_str::<ConfigParsed>(invalid).is_err(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_allowed_html() {
+        let config = Config::from(ConfigParsed::default());
+        assert!(config.allowed_html().tags().is_empty());
+
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>(
+                "[allowed_html]\ntags = [\"details\", \"summary\"]\nattributes = { details = [\"open\"] }",
+            )
+            .unwrap(),
+        );
+        assert!(config.allowed_html().tags().contains("details"));
+        assert!(config.allowed_html().attributes()["details"].contains("open"));
+
+        for invalid in [
+            "[allowed_html]\ntags = [\"Script\"]",
+            "[allowed_html]\nattributes = { span = [\"class\"] }",
+        ] {
+            assert!(
+                toml::from_str::<ConfigParsed>(invalid).is_err(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_collections() {
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>(
+                "data_path = \"/srv/site\"\n[[collections]]\nname = \"docs\"\npath = \"docs\"\nprefix = \"/docs/\"",
+            )
+            .unwrap(),
+        );
+        let collection = &config.collections()[0];
+        assert_eq!(collection.prefix(), "docs");
+        assert_eq!(
+            config.collection_path(collection),
+            PathBuf::from("/srv/site/docs")
+        );
+
+        for invalid in [
+            "[[collections]]\nname = \"docs\"\npath = \"docs\"\nprefix = \"/\"",
+            "[[collections]]\nname = \"docs\"\npath = \"a\"\nprefix = \"a\"\n[[collections]]\nname = \"docs\"\npath = \"b\"\nprefix = \"b\"",
+            "[[collections]]\nname = \"a\"\npath = \"a\"\nprefix = \"docs\"\n[[collections]]\nname = \"b\"\npath = \"b\"\nprefix = \"docs\"",
+        ] {
+            assert!(
+                toml::from_str::<ConfigParsed>(invalid).is_err(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_reindex_interval() {
+        let interval = |toml: &str| {
+            *Config::from(toml::from_str::<ConfigParsed>(toml).unwrap()).search_reindex_interval()
+        };
+        assert_eq!(interval(""), Some(Duration::from_secs(3600)));
+        assert_eq!(
+            interval(r#"search_reindex_interval = "30m""#),
+            Some(Duration::from_secs(1800))
+        );
+        assert_eq!(interval(r#"search_reindex_interval = "never""#), None);
+        assert_eq!(interval(r#"search_reindex_interval = "0""#), None);
+    }
+
+    #[test]
+    fn test_view_transitions() {
+        let config = Config::from(ConfigParsed::default());
+        assert!(config.view_transitions());
+        assert_eq!(
+            *config.view_transition_duration(),
+            Duration::from_millis(50)
+        );
+        assert_eq!(config.view_transition_timing(), "ease-in-out");
+
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>(
+                "view_transition_duration = \"120ms\"\nview_transition_timing = \"cubic-bezier(0.4, 0, 0.2, 1)\"",
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            *config.view_transition_duration(),
+            Duration::from_millis(120)
+        );
+        assert_eq!(
+            config.view_transition_timing(),
+            "cubic-bezier(0.4, 0, 0.2, 1)"
+        );
+
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>("view_transition_timing = \"linear}</style>\"").unwrap(),
+        );
+        assert_eq!(config.view_transition_timing(), "ease-in-out");
+    }
+
+    #[test]
+    fn test_log_files() {
+        let config = Config::from(ConfigParsed::default());
+        assert_eq!(config.log_file_name(), "website.log");
+        assert_eq!(*config.log_rotation(), ConfigLogRotation::Daily);
+        assert_eq!(*config.log_max_files(), None);
+
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>(
+                "log_file_name = \"site.log\"\nlog_rotation = \"hourly\"\nlog_max_files = 48",
+            )
+            .unwrap(),
+        );
+        assert_eq!(config.log_file_name(), "site.log");
+        assert_eq!(*config.log_rotation(), ConfigLogRotation::Hourly);
+        assert_eq!(*config.log_max_files(), Some(48));
+
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>("log_file_name = \" \"\nlog_max_files = 0").unwrap(),
+        );
+        assert_eq!(config.log_file_name(), "website.log");
+        assert_eq!(*config.log_max_files(), None);
+    }
+
+    #[test]
+    fn test_overrides_replace_config_file_settings() {
+        let mut parsed = toml::from_str::<ConfigParsed>("data_path = \"/srv/site\"").unwrap();
+        parsed.apply(ConfigOverrides::default());
+        assert_eq!(parsed.data_path, Some(PathBuf::from("/srv/site")));
+
+        parsed.apply(ConfigOverrides {
+            data_path: Some(PathBuf::from("/tmp/site")),
+        });
+        let config = Config::from(parsed);
+        assert_eq!(config.data_path(), &PathBuf::from("/tmp/site"));
+        assert_eq!(config.pages_path(), PathBuf::from("/tmp/site/pages"));
+    }
+
+    #[test]
+    fn test_directory_index() {
+        let directory_index = |toml: &str| {
+            Config::from(toml::from_str::<ConfigParsed>(toml).unwrap())
+                .directory_index()
+                .clone()
+        };
+        assert_eq!(directory_index(""), "index.md");
+        assert_eq!(
+            directory_index("directory_index = \"README.md\""),
+            "README.md"
+        );
+        assert_eq!(directory_index("directory_index = \"../x.md\""), "index.md");
+        assert_eq!(
+            directory_index("directory_index = \"index.html\""),
+            "index.md"
+        );
+    }
+
+    #[test]
+    fn test_default_language() {
+        let default_language = |toml: &str| {
+            Config::from(toml::from_str::<ConfigParsed>(toml).unwrap())
+                .default_language()
+                .clone()
+        };
+        assert_eq!(default_language(""), "en");
+        assert_eq!(default_language("default_language = \" sv \""), "sv");
+        assert_eq!(default_language("default_language = \"pt-BR\""), "pt-BR");
+        assert_eq!(default_language("default_language = \"swedish\""), "en");
+    }
+
+    #[test]
+    fn test_gone_paths_are_normalized() {
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>(r#"gone_paths = ["/old/page/", " blog/draft"]"#)
+                .unwrap(),
+        );
+        assert_eq!(
+            *config.gone_paths(),
+            HashSet::from(["old/page".to_string(), "blog/draft".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_og_image() {
+        let config = Config::from(ConfigParsed::default());
+        assert!(config.og_image().is_none());
+
+        let config = Config::from(toml::from_str::<ConfigParsed>("[og_image]").unwrap());
+        let og_image = config.og_image().as_ref().unwrap();
+        assert_eq!(*og_image.background(), [0x1f, 0x29, 0x37]);
+        assert_eq!(*og_image.lines(), default_og_lines());
+
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>(
+                "og_image = { background = \"#FFF\", accent = \"#336699\", lines = [\"title\", \"author\"] }",
+            )
+            .unwrap(),
+        );
+        let og_image = config.og_image().as_ref().unwrap();
+        assert_eq!(*og_image.background(), [0xff, 0xff, 0xff]);
+        assert_eq!(*og_image.accent(), [0x33, 0x66, 0x99]);
+        assert_eq!(
+            *og_image.lines(),
+            vec![ConfigOgLine::Title, ConfigOgLine::Author]
+        );
+
+        for color in ["red", "#12345", "#12345g", "336699"] {
+            assert!(
+                toml::from_str::<ConfigParsed>(&format!(
+                    "og_image = {{ background = \"{color}\" }}"
+                ))
+                .is_err(),
+                "{color}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_sitemap_shard_size_limits() {
+        let config = Config::from(ConfigParsed::default());
+        assert_eq!(*config.sitemap_shard_size(), 10_000);
+
+        let config =
+            Config::from(toml::from_str::<ConfigParsed>("sitemap_shard_size = 500").unwrap());
+        assert_eq!(*config.sitemap_shard_size(), 500);
+
+        for size in [0, 40_001] {
+            let error = toml::from_str::<ConfigParsed>(&format!("sitemap_shard_size = {size}"))
+                .err()
+                .unwrap();
+            assert!(
+                error.to_string().contains("must be between 1 and 40000"),
+                "{error}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_compression_levels() {
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>(
+                r#"
+                compression_encodings = ["br", "gzip"]
+                compression_levels = { br = 4, gzip = "best" }
+                "#,
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            *config.compression_encodings(),
+            [ConfigEncoding::Br, ConfigEncoding::Gzip]
+        );
+        assert_eq!(
+            config.compression_levels()[&ConfigEncoding::Br],
+            ConfigCompressionLevel::Precise(4)
+        );
+        assert_eq!(
+            config.compression_levels()[&ConfigEncoding::Gzip],
+            ConfigCompressionLevel::Named(ConfigCompressionLevelName::Best)
+        );
+    }
+}
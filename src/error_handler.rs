@@ -1,9 +1,46 @@
+use std::any::Any;
+
 use axum::{
-    http::{StatusCode, header},
+    Json,
+    extract::{FromRequestParts, Query},
+    http::{StatusCode, header, request::Parts},
     response::{IntoResponse, Response},
 };
+use serde::de::DeserializeOwned;
+
+use crate::{
+    assets::ASSET_MANAGER,
+    config::load_config,
+    web::{HTML_CONTENT_TYPE, escape_html, is_html, is_json, prefers_json},
+};
 
-use crate::{assets::ASSET_MANAGER, config::load_config};
+/// Query parameters like [`Query`], but malformed ones are rejected with the
+/// error page, or a JSON error for clients preferring JSON, naming the
+/// offending parameter instead of a plain text response.
+pub struct ValidQuery<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for ValidQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Query::<T>::from_request_parts(parts, state).await {
+            Ok(Query(params)) => Ok(Self(params)),
+            Err(rejection) => {
+                let status = rejection.status();
+                let message = rejection.body_text();
+                Err(if prefers_json(&parts.headers) {
+                    (status, Json(serde_json::json!({ "error": message }))).into_response()
+                } else {
+                    render_error_page(status, Some(&message))
+                })
+            }
+        }
+    }
+}
 
 pub async fn error_handler(
     request: axum::extract::Request,
@@ -22,21 +59,49 @@ pub async fn error_handler(
             );
         }
 
+        // Handlers that render their own error page or JSON error know best
+        // what to show.
+        if response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .is_some_and(|content_type| is_html(content_type) || is_json(content_type))
+        {
+            return Ok(response);
+        }
+
         let status = response.status();
-        let html = render_error_page(status);
+        let html = render_error_page(status, None);
         return Ok(html.into_response());
     }
 
     Ok(response)
 }
 
-fn render_error_page(status: StatusCode) -> Response {
+/// Turns a panic in a handler into a branded 500 page instead of a dropped
+/// connection. The backtrace is logged by the panic hook set up with logging.
+pub fn handle_panic(payload: Box<dyn Any + Send + 'static>) -> Response {
+    let message = payload
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| payload.downcast_ref::<&str>().copied())
+        .unwrap_or("unknown panic payload");
+    tracing::error!("💥 Handler panicked: {message}");
+
+    render_error_page(StatusCode::INTERNAL_SERVER_ERROR, None)
+}
+
+/// Renders the error page for `status`, explaining the error with `message`
+/// when given.
+pub fn render_error_page(status: StatusCode, message: Option<&str>) -> Response {
     let config = load_config();
     let title = format!(
         "{} {}",
         status.as_str(),
         status.canonical_reason().unwrap_or("Error")
     );
+    let message = message
+        .map(|message| format!(r#"<p class="error-message">{}</p>"#, escape_html(message)))
+        .unwrap_or_default();
     let html = format!(
         r#"<!DOCTYPE html>
 <html>
@@ -49,8 +114,8 @@ fn render_error_page(status: StatusCode) -> Response {
     </head>
     <body>
         <main class="error-page error-page--{}">
-            <h1>{}</h1>
-            <p><a href="/">To start page</a></p>
+            <h1>{}</h1>{}
+            <p><a href="{}">To start page</a></p>
         </main>
     </body>
 </html>"#,
@@ -58,12 +123,159 @@ fn render_error_page(status: StatusCode) -> Response {
         config.title(),
         ASSET_MANAGER.hashed_route("styles.css").unwrap_or_default(),
         status.as_str(),
-        title
+        title,
+        message,
+        config.route("/")
     );
 
     Response::builder()
         .status(status)
-        .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+        .header(header::CONTENT_TYPE, HTML_CONTENT_TYPE)
         .body(html.into())
         .unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, body::Body, extract::Request, middleware, routing::get};
+    use tower::ServiceExt;
+    use tower_http::catch_panic::CatchPanicLayer;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_error_page_replaces_bare_errors_only() {
+        let app = Router::new()
+            .route("/bare", get(async || StatusCode::NOT_FOUND))
+            .route(
+                "/html",
+                get(async || {
+                    (
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        [(header::CONTENT_TYPE, HTML_CONTENT_TYPE)],
+                        "<p>Custom</p>",
+                    )
+                }),
+            )
+            .layer(middleware::from_fn(error_handler));
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/bare").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("error-page--404"));
+
+        let response = app
+            .oneshot(Request::builder().uri("/html").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"<p>Custom</p>");
+    }
+
+    #[tokio::test]
+    async fn test_malformed_query_names_parameter() {
+        #[derive(serde::Deserialize)]
+        struct Params {
+            page: Option<usize>,
+        }
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(async |ValidQuery(params): ValidQuery<Params>| {
+                    params.page.unwrap_or_default().to_string()
+                }),
+            )
+            .layer(middleware::from_fn(error_handler));
+
+        let response = app
+            .clone()
+            .oneshot(Request::get("/?page=abc").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("error-page--400"));
+        assert!(body.contains(r#"<p class="error-message">"#));
+        assert!(body.contains("page: invalid digit"));
+
+        let response = app
+            .oneshot(
+                Request::get("/?page=abc")
+                    .header(header::ACCEPT, "application/json")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(
+            error["error"]
+                .as_str()
+                .unwrap()
+                .contains("page: invalid digit")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_json_errors_are_kept() {
+        let app = Router::new()
+            .route(
+                "/",
+                get(async || {
+                    (
+                        StatusCode::NOT_FOUND,
+                        axum::Json(serde_json::json!({ "error": "Page not found" })),
+                    )
+                }),
+            )
+            .layer(middleware::from_fn(error_handler));
+
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_panic_becomes_error_page() {
+        let app = Router::new()
+            .route(
+                "/",
+                get(async || -> &'static str { panic!("malformed content") }),
+            )
+            .layer(CatchPanicLayer::custom(handle_panic))
+            .layer(middleware::from_fn(error_handler));
+
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("error-page--500"));
+    }
+}
@@ -0,0 +1,461 @@
+use axum::{
+    Json, Router,
+    extract::Path,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use rayon::iter::ParallelIterator;
+use serde::Serialize;
+use time::{
+    Date, OffsetDateTime,
+    format_description::well_known::{Rfc2822, Rfc3339},
+    macros::date,
+};
+use ulid::Ulid;
+
+use crate::{
+    assets::ASSET_MANAGER,
+    config::{Config, load_config},
+    listing::tag_route,
+    markdown::url_authority,
+    page::{Page, normalize_tag},
+    search::EXCERPT_WORDS,
+    web::escape_html,
+};
+
+/// XSLT stylesheet in the assets directory rendering feeds in browsers.
+const FEED_STYLESHEET: &str = "feed.xsl";
+/// Styles of the page `feed.xsl` renders.
+const FEED_CSS: &str = "feed.css";
+/// Date in the tag URIs of pages whose id carries no time of its own. Fixed,
+/// as the ids of feed entries must never change.
+const TAG_URI_DATE: Date = date!(2024 - 01 - 01);
+
+pub fn feed_routes() -> Router {
+    Router::new()
+        .route("/feed.json", get(json_feed_handler))
+        .route("/feed.xml", get(rss_feed_handler))
+        .route("/atom.xml", get(atom_feed_handler))
+        .route("/tags/{tag}/feed.xml", get(tag_feed_handler))
+}
+
+/// The most recently modified published pages, newest first.
+pub fn recent_pages(pages: impl ParallelIterator<Item = Page>, limit: usize) -> Vec<Page> {
+    let mut pages: Vec<Page> = pages.filter(|page| !page.draft).collect();
+    pages.sort_by(|a, b| b.modified.cmp(&a.modified).then_with(|| a.url.cmp(&b.url)));
+    pages.truncate(limit);
+    pages
+}
+
+/// The most recent published pages, read off the async runtime.
+async fn feed_pages(config: &Config) -> Vec<Page> {
+    let limit = *config.feed_item_count();
+    Page::read_blocking(move || recent_pages(Page::all(), limit)).await
+}
+
+async fn json_feed_handler() -> Response {
+    let config = load_config();
+    let pages = feed_pages(&config).await;
+
+    (
+        [(header::CONTENT_TYPE, "application/feed+json")],
+        Json(JsonFeed::new(&pages, &config)),
+    )
+        .into_response()
+}
+
+async fn rss_feed_handler() -> Response {
+    let config = load_config();
+    let pages = feed_pages(&config).await;
+
+    rss_response(render_rss(&pages, None, &config))
+}
+
+async fn atom_feed_handler() -> Response {
+    let config = load_config();
+    let pages = feed_pages(&config).await;
+
+    (
+        [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        render_atom(&pages, &config),
+    )
+        .into_response()
+}
+
+/// The RSS feed of the pages tagged `tag`, normalized the way page tags are.
+async fn tag_feed_handler(Path(tag): Path<String>) -> Result<Response, StatusCode> {
+    let config = load_config();
+    let tag = normalize_tag(&tag, *config.tag_case()).ok_or(StatusCode::NOT_FOUND)?;
+    let limit = *config.feed_item_count();
+    let (tag, pages) = Page::read_blocking(move || {
+        let pages = recent_pages(Page::all().filter(|page| page.tags.contains(&tag)), limit);
+        (tag, pages)
+    })
+    .await;
+
+    if pages.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(rss_response(render_rss(&pages, Some(&tag), &config)))
+}
+
+fn rss_response(xml: String) -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/rss+xml; charset=utf-8")],
+        xml,
+    )
+        .into_response()
+}
+
+/// Renders an [RSS 2.0](https://www.rssboard.org/rss-specification) channel
+/// of `pages`, titled after `tag` for the feeds of a single tag.
+fn render_rss(pages: &[Page], tag: Option<&str>, config: &Config) -> String {
+    let (title, feed_path) = match tag {
+        Some(tag) => (
+            format!("{} – {tag}", config.title()),
+            format!("{}/feed.xml", tag_route(tag)),
+        ),
+        None => (config.title().clone(), "/feed.xml".to_string()),
+    };
+
+    let mut xml = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+{}<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom">
+    <channel>
+        <title>{}</title>
+        <link>{}</link>
+        <description>{}</description>
+        <atom:link href="{}" rel="self" type="application/rss+xml"/>
+"#,
+        feed_stylesheet(),
+        escape_html(&title),
+        escape_html(&config.absolute_url("/")),
+        escape_html(&title),
+        escape_html(&config.absolute_url(&feed_path)),
+    );
+
+    for page in pages {
+        let url = config.absolute_url(&format!("/{}", page.url.to_string_lossy()));
+        xml.push_str(&format!(
+            r#"        <item>
+            <title>{}</title>
+            <link>{}</link>
+            <guid isPermaLink="false">{}</guid>
+            <pubDate>{}</pubDate>
+            <description>{}</description>
+        </item>
+"#,
+            escape_html(page.title.as_deref().unwrap_or_default()),
+            escape_html(&url),
+            page.id,
+            page.modified.format(&Rfc2822).unwrap_or_default(),
+            escape_html(&page.excerpt(EXCERPT_WORDS)),
+        ));
+    }
+
+    xml.push_str("    </channel>\n</rss>\n");
+    xml
+}
+
+/// Renders an [Atom 1.0](https://www.rfc-editor.org/rfc/rfc4287) feed of
+/// `pages`. The feed is updated when its most recently updated entry was.
+fn render_atom(pages: &[Page], config: &Config) -> String {
+    let updated = pages
+        .iter()
+        .map(|page| page.modified)
+        .max()
+        .unwrap_or_else(OffsetDateTime::now_utc);
+
+    let mut xml = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+{}<feed xmlns="http://www.w3.org/2005/Atom">
+    <id>{}</id>
+    <title>{}</title>
+    <updated>{}</updated>
+    <author><name>{}</name></author>
+    <link href="{}"/>
+    <link href="{}" rel="self" type="application/atom+xml"/>
+"#,
+        feed_stylesheet(),
+        escape_html(&config.absolute_url("/")),
+        escape_html(config.title()),
+        updated.format(&Rfc3339).unwrap_or_default(),
+        escape_html(config.title()),
+        escape_html(&config.absolute_url("/")),
+        escape_html(&config.absolute_url("/atom.xml")),
+    );
+
+    for page in pages {
+        let url = config.absolute_url(&format!("/{}", page.url.to_string_lossy()));
+        let author = page
+            .author
+            .as_ref()
+            .map(|author| {
+                format!(
+                    "            <author><name>{}</name></author>\n",
+                    escape_html(author)
+                )
+            })
+            .unwrap_or_default();
+        xml.push_str(&format!(
+            r#"        <entry>
+            <id>{}</id>
+            <title>{}</title>
+            <link href="{}"/>
+            <updated>{}</updated>
+{author}            <summary>{}</summary>
+            <content type="html">{}</content>
+        </entry>
+"#,
+            tag_uri(page, config),
+            escape_html(page.title.as_deref().unwrap_or_default()),
+            escape_html(&url),
+            page.modified.format(&Rfc3339).unwrap_or_default(),
+            escape_html(&page.excerpt(EXCERPT_WORDS)),
+            escape_html(&page.html),
+        ));
+    }
+
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// A [tag URI](https://www.rfc-editor.org/rfc/rfc4151) identifying `page` by
+/// its id, minted under the site host on the day the ULID was generated.
+/// Ids derived from the path carry no time, nor do ULIDs past the year 9999,
+/// so those are minted on [`TAG_URI_DATE`] rather than on any date of the
+/// page, which could change along with it.
+fn tag_uri(page: &Page, config: &Config) -> String {
+    let host = url_authority(config.base_url())
+        .and_then(|authority| authority.split(':').next().map(str::to_string))
+        .unwrap_or("localhost".into());
+    let id = page.id;
+    let date = Some(id.timestamp_ms())
+        .filter(|ms| page.explicit_id && *ms > 0)
+        .and_then(|ms| OffsetDateTime::from_unix_timestamp_nanos(i128::from(ms) * 1_000_000).ok())
+        .map_or(TAG_URI_DATE, |generated| generated.date());
+
+    format!(
+        "tag:{host},{}-{:02}-{:02}:{id}",
+        date.year(),
+        u8::from(date.month()),
+        date.day()
+    )
+}
+
+/// Processing instructions styling a feed with the bundled `feed.xsl`, so it
+/// reads as a page when opened in a browser rather than as raw XML. The
+/// second names the CSS the transformed page links to, which the static
+/// stylesheet can not know the hashed route of.
+fn feed_stylesheet() -> String {
+    ASSET_MANAGER
+        .hashed_route(FEED_STYLESHEET)
+        .map(|route| {
+            format!(
+                "<?xml-stylesheet type=\"text/xsl\" href=\"{}\"?>\n<?feed-stylesheet {}?>\n",
+                escape_html(&route),
+                escape_html(&ASSET_MANAGER.hashed_route(FEED_CSS).unwrap_or_default())
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// A [JSON Feed 1.1](https://www.jsonfeed.org/version/1.1/) document.
+#[derive(Serialize)]
+struct JsonFeed {
+    version: &'static str,
+    title: String,
+    home_page_url: String,
+    feed_url: String,
+    items: Vec<JsonFeedItem>,
+}
+
+#[derive(Serialize)]
+struct JsonFeedItem {
+    id: Ulid,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    content_html: String,
+    #[serde(with = "time::serde::rfc3339")]
+    date_modified: OffsetDateTime,
+}
+
+impl JsonFeed {
+    fn new(pages: &[Page], config: &Config) -> Self {
+        Self {
+            version: "https://jsonfeed.org/version/1.1",
+            title: config.title().clone(),
+            home_page_url: config.absolute_url("/"),
+            feed_url: config.absolute_url("/feed.json"),
+            items: pages
+                .iter()
+                .map(|page| JsonFeedItem {
+                    id: page.id,
+                    url: config.absolute_url(&format!("/{}", page.url.to_string_lossy())),
+                    title: page.title.clone(),
+                    content_html: page.html.clone(),
+                    date_modified: page.modified,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::iter::IntoParallelIterator;
+
+    use super::*;
+    use crate::config::ConfigParsed;
+
+    fn page(path: &str, frontmatter: &str, modified: i64) -> Page {
+        Page::parse(
+            path.into(),
+            &format!("---\n{frontmatter}\n---\n# Page"),
+            OffsetDateTime::from_unix_timestamp(modified).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_recent_pages() {
+        let pages = vec![
+            page("old", "title: Old", 1),
+            page("draft", "draft: true", 4),
+            page("new", "title: New", 3),
+            page("middle", "title: Middle", 2),
+        ];
+
+        let recent = recent_pages(pages.into_par_iter(), 2);
+        let urls: Vec<_> = recent
+            .iter()
+            .map(|page| page.url.to_str().unwrap())
+            .collect();
+        assert_eq!(urls, ["new", "middle"]);
+    }
+
+    #[test]
+    fn test_json_feed() {
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>(r#"base_url = "https://example.com""#).unwrap(),
+        );
+        let pages = [page("notes/first", "title: First", 0)];
+
+        let feed = serde_json::to_value(JsonFeed::new(&pages, &config)).unwrap();
+        assert_eq!(feed["version"], "https://jsonfeed.org/version/1.1");
+        assert_eq!(feed["feed_url"], "https://example.com/feed.json");
+        let item = &feed["items"][0];
+        assert_eq!(item["id"], pages[0].id.to_string());
+        assert_eq!(item["url"], "https://example.com/notes/first");
+        assert_eq!(item["title"], "First");
+        assert_eq!(item["date_modified"], "1970-01-01T00:00:00Z");
+        assert!(
+            item["content_html"]
+                .as_str()
+                .unwrap()
+                .contains("<h1>Page</h1>")
+        );
+    }
+
+    #[test]
+    fn test_rss_feed() {
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>(
+                "title = \"Notes & more\"\nbase_url = \"https://example.com\"",
+            )
+            .unwrap(),
+        );
+        let pages = [page(
+            "notes/first",
+            "title: First\ndescription: About <things>",
+            0,
+        )];
+
+        let xml = render_rss(&pages, None, &config);
+        let stylesheet = ASSET_MANAGER.hashed_route(FEED_STYLESHEET).unwrap();
+        let css = ASSET_MANAGER.hashed_route(FEED_CSS).unwrap();
+        assert!(xml.starts_with(&format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<?xml-stylesheet type=\"text/xsl\" href=\"{stylesheet}\"?>\n<?feed-stylesheet {css}?>\n<rss "
+        )));
+        assert!(xml.contains("<title>Notes &amp; more</title>"));
+        assert!(xml.contains(r#"<atom:link href="https://example.com/feed.xml""#));
+        assert!(xml.contains("<link>https://example.com/notes/first</link>"));
+        assert!(xml.contains(&format!(
+            r#"<guid isPermaLink="false">{}</guid>"#,
+            pages[0].id
+        )));
+        assert!(xml.contains("<pubDate>Thu, 01 Jan 1970 00:00:00 +0000</pubDate>"));
+        assert!(xml.contains("<description>About &lt;things&gt;</description>"));
+
+        let xml = render_rss(&pages, Some("rust"), &config);
+        assert!(xml.contains("<title>Notes &amp; more – rust</title>"));
+        assert!(xml.contains(r#"href="https://example.com/tags/rust/feed.xml""#));
+    }
+
+    #[test]
+    fn test_atom_feed() {
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>(
+                "title = \"Notes & more\"\nbase_url = \"https://example.com:8080\"",
+            )
+            .unwrap(),
+        );
+        let pages = [
+            page(
+                "notes/first",
+                "id: 01ARZ3NDEKTSV4RRFFQ69G5FAV\ntitle: First\nauthor: Ada",
+                0,
+            ),
+            page("notes/second", "title: Second", 86400),
+        ];
+
+        let xml = render_atom(&pages, &config);
+        assert!(xml.contains(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#));
+        assert!(xml.contains("    <updated>1970-01-02T00:00:00Z</updated>"));
+        assert!(xml.contains(
+            r#"<link href="https://example.com:8080/atom.xml" rel="self" type="application/atom+xml"/>"#
+        ));
+        assert!(xml.contains("<id>tag:example.com,2016-07-30:01ARZ3NDEKTSV4RRFFQ69G5FAV</id>"));
+        assert!(xml.contains(r#"<link href="https://example.com:8080/notes/first"/>"#));
+        assert!(xml.contains("<updated>1970-01-01T00:00:00Z</updated>"));
+        assert!(xml.contains("<author><name>Ada</name></author>"));
+        assert!(xml.contains(r#"<content type="html">&lt;h1&gt;Page&lt;/h1&gt;"#));
+        assert!(xml.ends_with("</feed>\n"));
+    }
+
+    #[test]
+    fn test_tag_uri_dates() {
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>("base_url = \"https://example.com\"").unwrap(),
+        );
+        let id = Ulid::from_parts(86_400_000, 1);
+        let explicit = page("notes/explicit", &format!("id: {id}"), 0);
+        assert_eq!(
+            tag_uri(&explicit, &config),
+            format!("tag:example.com,1970-01-02:{id}")
+        );
+
+        let derived = page("notes/derived", "title: Derived\ndate: 2024-05-01", 86400);
+        let uri = tag_uri(&derived, &config);
+        assert!(uri.starts_with("tag:example.com,2024-01-01:"));
+        let edited = page(
+            "notes/derived",
+            "title: Edited\ndate: 2025-06-01",
+            86400 * 400,
+        );
+        assert_eq!(tag_uri(&edited, &config), uri);
+
+        let far_future = page(
+            "notes/far",
+            "id: 7ZZZZZZZZZZZZZZZZZZZZZZZZZ\ndate: 2024-05-01",
+            0,
+        );
+        assert_eq!(
+            tag_uri(&far_future, &config),
+            "tag:example.com,2024-01-01:7ZZZZZZZZZZZZZZZZZZZZZZZZZ"
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::SystemTime,
+};
+
+use lazy_static::lazy_static;
+
+use crate::config::load_config;
+
+/// Name of the file in the data directory listing URL paths of removed pages,
+/// one per line, answered with `410 Gone`.
+pub const GONE_FILE: &str = "gone.txt";
+
+/// Paths read from a gone file along with the modification time it was read
+/// at.
+type CachedGone = (Option<SystemTime>, Arc<HashSet<String>>);
+
+lazy_static! {
+    static ref GONE: RwLock<HashMap<PathBuf, CachedGone>> = RwLock::new(HashMap::new());
+}
+
+/// Whether the page at the URL `path`, without the base path, was removed for
+/// good, being listed in `gone_paths` in the config or in the gone file.
+pub fn is_gone(path: &str) -> bool {
+    let config = load_config();
+    let path = normalize_gone_path(path);
+    config.gone_paths().contains(&path)
+        || gone_file_paths(&config.data_path().join(GONE_FILE)).contains(&path)
+}
+
+/// A URL path without surrounding whitespace and slashes, the form paths are
+/// compared in.
+pub fn normalize_gone_path(path: &str) -> String {
+    path.trim().trim_matches('/').to_string()
+}
+
+/// Reads the paths in the gone file at `path`, reusing the ones read before
+/// while its modification time is unchanged. Blank lines and lines starting
+/// with `#` are skipped. Without a file no path is gone.
+fn gone_file_paths(path: &Path) -> Arc<HashSet<String>> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+    if let Some((cached_modified, paths)) = GONE.read().unwrap_or_else(|e| e.into_inner()).get(path)
+    {
+        if *cached_modified == modified {
+            return paths.clone();
+        }
+    }
+
+    let paths = match modified.map(|_| fs::read_to_string(path)) {
+        Some(Ok(content)) => content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(normalize_gone_path)
+            .collect(),
+        Some(Err(error)) => {
+            tracing::warn!("⚠️ Unable to read {}: {}", path.to_string_lossy(), error);
+            HashSet::new()
+        }
+        None => HashSet::new(),
+    };
+    let paths = Arc::new(paths);
+    GONE.write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(path.to_path_buf(), (modified, paths.clone()));
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gone_file_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join(GONE_FILE);
+        assert!(gone_file_paths(&file).is_empty());
+
+        fs::write(
+            &file,
+            "# Removed in the 2024 cleanup\n/old/page\n\n  blog/draft/  \n",
+        )
+        .unwrap();
+        let paths = gone_file_paths(&file);
+        assert_eq!(
+            *paths,
+            HashSet::from(["old/page".to_string(), "blog/draft".to_string()])
+        );
+        assert!(!paths.contains("# Removed in the 2024 cleanup"));
+    }
+}
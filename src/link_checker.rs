@@ -0,0 +1,139 @@
+use std::path::{Path, PathBuf};
+
+use rayon::iter::ParallelIterator;
+use scraper::{Html, Selector};
+
+use crate::{
+    config::{Config, load_config},
+    page::Page,
+};
+
+/// Routes served by the program itself rather than from pages.
+const NON_PAGE_ROUTES: [&str; 16] = [
+    "/admin/",
+    "/archive",
+    "/assets/",
+    "/atom.xml",
+    "/authors/",
+    "/favicon.ico",
+    "/feed.json",
+    "/feed.xml",
+    "/llms.txt",
+    "/manifest.webmanifest",
+    "/og/",
+    "/pages.json",
+    "/search",
+    "/sitemap-",
+    "/sitemap.xml",
+    "/tags",
+];
+
+#[derive(Debug)]
+pub struct BrokenLink {
+    pub page: PathBuf,
+    pub href: String,
+}
+
+/// Finds internal links in all pages that do not resolve to an existing page.
+pub fn check_links() -> Vec<BrokenLink> {
+    let config = load_config();
+    let mut broken: Vec<BrokenLink> = Page::all()
+        .flat_map_iter(|page| {
+            internal_links(&page.html)
+                .into_iter()
+                .filter(|href| !resolves(href, &page.url, &config))
+                .map(|href| BrokenLink {
+                    page: page.path.clone(),
+                    href,
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    broken.sort_by(|a, b| a.page.cmp(&b.page).then_with(|| a.href.cmp(&b.href)));
+    broken
+}
+
+/// Extracts link targets that point within the site, without query or fragment.
+fn internal_links(html: &str) -> Vec<String> {
+    let selector = Selector::parse("a[href]").unwrap();
+    Html::parse_fragment(html)
+        .select(&selector)
+        .filter_map(|link| link.value().attr("href"))
+        .filter(|href| !href.starts_with("//") && !href.contains(':'))
+        .map(|href| {
+            href.split(['?', '#'])
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        })
+        .filter(|href| !href.is_empty())
+        .collect()
+}
+
+/// Whether `path` is served by one of the [`NON_PAGE_ROUTES`]. Routes ending
+/// in `/` or `-` cover every path starting with them, the others only
+/// themselves and paths continuing them with a `/` or `.`, so pages like
+/// `/tagsoup` are still checked.
+fn is_non_page_route(path: &str) -> bool {
+    NON_PAGE_ROUTES.iter().any(|route| {
+        path.strip_prefix(route).is_some_and(|rest| {
+            route.ends_with(['/', '-']) || rest.is_empty() || rest.starts_with(['/', '.'])
+        })
+    })
+}
+
+fn resolves(href: &str, page_url: &Path, config: &Config) -> bool {
+    let path = if let Some(absolute) = href.strip_prefix('/') {
+        let absolute = format!("/{absolute}");
+        let path = absolute
+            .strip_prefix(config.base_path().as_str())
+            .unwrap_or(&absolute)
+            .to_string();
+        if is_non_page_route(&path) {
+            return true;
+        }
+        path
+    } else {
+        let dir = page_url.parent().unwrap_or(Path::new(""));
+        format!("/{}", dir.join(href).to_string_lossy())
+    };
+
+    Page::get_full_path(path).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_internal_links() {
+        let html = r##"<p>
+            <a href="/about">About</a>
+            <a href="guide/setup#install">Setup</a>
+            <a href="#top">Top</a>
+            <a href="https://example.com">External</a>
+            <a href="//cdn.example.com/lib.js">Protocol relative</a>
+            <a href="mailto:me@example.com">Mail</a>
+        </p>"##;
+        assert_eq!(internal_links(html), ["/about", "guide/setup"]);
+    }
+
+    #[test]
+    fn test_non_page_routes() {
+        for path in [
+            "/archive",
+            "/tags",
+            "/tags/rust",
+            "/tags.json",
+            "/search",
+            "/assets/styles.css",
+            "/sitemap-2.xml",
+        ] {
+            assert!(is_non_page_route(path), "{path}");
+        }
+        for path in ["/archive-notes", "/tagsoup", "/searching", "/about"] {
+            assert!(!is_non_page_route(path), "{path}");
+        }
+    }
+}
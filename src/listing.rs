@@ -0,0 +1,613 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet},
+};
+
+use axum::{
+    Json, Router,
+    extract::Path,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use percent_encoding::{AsciiSet, CONTROLS, utf8_percent_encode};
+use rayon::iter::ParallelIterator;
+use serde::{Deserialize, Serialize};
+use time::{Month, macros::format_description};
+
+use crate::{
+    config::load_config,
+    error_handler::ValidQuery,
+    page::{Page, normalize_tag},
+    search::EXCERPT_WORDS,
+    web::{HTML_CONTENT_TYPE, escape_html, render_layout},
+};
+
+pub fn listing_routes() -> Router {
+    Router::new()
+        .route("/archive", get(archive_handler))
+        .route("/authors/{name}", get(author_handler))
+        .route("/tags", get(tags_handler))
+        .route("/tags.json", get(tags_json_handler))
+        .route("/tags/{tag}", get(tag_handler))
+}
+
+/// Characters escaped in a tag or author name used as a path segment, besides
+/// those outside ASCII, so tags like `c#` and `ci/cd` link to their own
+/// listing.
+const TAG_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'\\')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// Path of the listing of the pages tagged `tag`, without the base path.
+pub fn tag_route(tag: &str) -> String {
+    format!("/tags/{}", utf8_percent_encode(tag, TAG_SEGMENT))
+}
+
+/// Path of the listing of the pages by `author`, without the base path.
+pub fn author_route(author: &str) -> String {
+    format!("/authors/{}", utf8_percent_encode(author, TAG_SEGMENT))
+}
+
+async fn archive_handler(ValidQuery(params): ValidQuery<ListingParams>) -> Response {
+    let pages: Vec<Page> = Page::read_blocking(move || {
+        Page::all()
+            .filter(|page| !page.draft && params.includes(page))
+            .collect()
+    })
+    .await;
+    let html = render_layout("Archive", "", "", &archive_html(pages));
+
+    ([(header::CONTENT_TYPE, HTML_CONTENT_TYPE)], html).into_response()
+}
+
+/// Lists the dated pages by year and month, newest first. Undated pages are
+/// left out.
+fn archive_html(pages: Vec<Page>) -> String {
+    let mut months: BTreeMap<Reverse<(i32, Month)>, Vec<Page>> = BTreeMap::new();
+    for page in pages {
+        if let Some(date) = page.date {
+            months
+                .entry(Reverse((date.year(), date.month())))
+                .or_default()
+                .push(page);
+        }
+    }
+
+    let mut years_html = String::new();
+    let mut current_year = None;
+    for (Reverse((year, month)), mut pages) in months {
+        pages.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| a.reading_order(b)));
+
+        if current_year != Some(year) {
+            if current_year.is_some() {
+                years_html.push_str(
+                    r#"
+                    </ul>
+                </li>"#,
+                );
+            }
+            years_html.push_str(&format!(
+                r#"
+                <li>
+                    <h2>{year}</h2>
+                    <ul>"#
+            ));
+            current_year = Some(year);
+        }
+
+        let items_html: String = pages
+            .iter()
+            .map(|page| {
+                let date = page.date.and_then(|date| {
+                    date.format(format_description!("[year]-[month]-[day]"))
+                        .ok()
+                });
+                format!(
+                    r#"
+                                <li><time datetime="{0}">{0}</time> <a href="{1}">{2}</a></li>"#,
+                    date.unwrap_or_default(),
+                    escape_html(&page.href()),
+                    escape_html(page.title.as_deref().unwrap_or_default()),
+                )
+            })
+            .collect();
+        years_html.push_str(&format!(
+            r#"
+                        <li>
+                            <h3>{month}</h3>
+                            <ul class="listing">{items_html}
+                            </ul>
+                        </li>"#
+        ));
+    }
+    if current_year.is_some() {
+        years_html.push_str(
+            r#"
+                    </ul>
+                </li>"#,
+        );
+    }
+
+    format!(
+        r#"<h1>Archive</h1>
+            <ul class="archive">{years_html}
+            </ul>"#
+    )
+}
+
+#[derive(Clone, Deserialize)]
+struct ListingParams {
+    /// One based page number.
+    page: Option<usize>,
+    /// Name of a collection to limit the listing to.
+    collection: Option<String>,
+}
+
+impl ListingParams {
+    fn includes(&self, page: &Page) -> bool {
+        self.collection.is_none() || page.collection == self.collection
+    }
+}
+
+async fn author_handler(
+    Path(name): Path<String>,
+    ValidQuery(params): ValidQuery<ListingParams>,
+) -> Result<Response, StatusCode> {
+    let (filter, author) = (params.clone(), name.clone());
+    let pages: Vec<Page> = Page::read_blocking(move || {
+        Page::all()
+            .filter(|page| {
+                !page.draft
+                    && filter.includes(page)
+                    && page
+                        .author
+                        .as_ref()
+                        .is_some_and(|page_author| page_author.eq_ignore_ascii_case(&author))
+            })
+            .collect()
+    })
+    .await;
+
+    listing_response(&format!("Pages by {name}"), pages, &params)
+}
+
+/// The published pages tagged `tag`, normalized the way page tags are.
+async fn tag_handler(
+    Path(tag): Path<String>,
+    ValidQuery(params): ValidQuery<ListingParams>,
+) -> Result<Response, StatusCode> {
+    let normalized = normalize_tag(&tag, *load_config().tag_case()).ok_or(StatusCode::NOT_FOUND)?;
+    let filter = params.clone();
+    let pages: Vec<Page> = Page::read_blocking(move || {
+        Page::all()
+            .filter(|page| !page.draft && filter.includes(page) && page.tags.contains(&normalized))
+            .collect()
+    })
+    .await;
+
+    listing_response(&format!("Pages tagged {tag}"), pages, &params)
+}
+
+/// Renders the requested page of `pages`, newest first by date and then by
+/// modification time, or `404 Not Found` when there are none.
+fn listing_response(
+    title: &str,
+    mut pages: Vec<Page>,
+    params: &ListingParams,
+) -> Result<Response, StatusCode> {
+    if pages.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    pages.sort_by(|a, b| {
+        b.date
+            .cmp(&a.date)
+            .then_with(|| b.modified.cmp(&a.modified))
+    });
+
+    let listing_page = ListingPage::new(
+        &pages,
+        params.page.unwrap_or(1),
+        *load_config().listing_page_size(),
+    )
+    .ok_or(StatusCode::NOT_FOUND)?
+    .in_collection(params.collection.as_deref());
+    Ok(render_listing(title, &listing_page))
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct TagCount {
+    tag: String,
+    count: usize,
+}
+
+/// Number of published pages per tag, by tag. Tags are normalized the way
+/// tag feeds and listings match them, so unless the case is preserved `Rust`
+/// and `rust`, as well as `Ünicode` and `ünicode`, count as one.
+fn tag_counts(pages: impl ParallelIterator<Item = Page>) -> Vec<TagCount> {
+    let case = *load_config().tag_case();
+    let page_tags: Vec<BTreeSet<String>> = pages
+        .filter(|page| !page.draft)
+        .map(|page| {
+            page.tags
+                .iter()
+                .filter_map(|tag| normalize_tag(tag, case))
+                .collect()
+        })
+        .collect();
+
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for tag in page_tags.into_iter().flatten() {
+        *counts.entry(tag).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .map(|(tag, count)| TagCount { tag, count })
+        .collect()
+}
+
+/// The tag counts of the pages included by `params`, read off the async
+/// runtime.
+async fn listed_tag_counts(params: ListingParams) -> Vec<TagCount> {
+    Page::read_blocking(move || tag_counts(Page::all().filter(|page| params.includes(page)))).await
+}
+
+async fn tags_json_handler(ValidQuery(params): ValidQuery<ListingParams>) -> Response {
+    Json(listed_tag_counts(params).await).into_response()
+}
+
+async fn tags_handler(ValidQuery(params): ValidQuery<ListingParams>) -> Response {
+    let tags = listed_tag_counts(params).await;
+    let html = render_layout("Tags", "", "", &tag_cloud_html(&tags));
+
+    ([(header::CONTENT_TYPE, HTML_CONTENT_TYPE)], html).into_response()
+}
+
+/// Lists the tags alphabetically, each classed `weight-1` to `weight-5` by
+/// how often it is used compared to the most used tag, for styling as a tag
+/// cloud.
+fn tag_cloud_html(tags: &[TagCount]) -> String {
+    let config = load_config();
+    let max = tags.iter().map(|tag| tag.count).max().unwrap_or(1);
+    let items_html: String = tags
+        .iter()
+        .map(|TagCount { tag, count }| {
+            let weight = 1 + (count - 1) * 4 / max.saturating_sub(1).max(1);
+            format!(
+                r#"
+                <li class="weight-{weight}"><a href="{}" data-count="{count}">{}</a></li>"#,
+                escape_html(&config.route(&tag_route(tag))),
+                escape_html(tag),
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<h1>Tags</h1>
+            <ul class="tag-cloud">{items_html}
+            </ul>"#
+    )
+}
+
+/// One page worth of a listing.
+pub struct ListingPage<'a> {
+    pages: &'a [Page],
+    number: usize,
+    has_next: bool,
+    collection: Option<&'a str>,
+}
+
+impl<'a> ListingPage<'a> {
+    /// Picks page `number`, counting from one, or `None` when it is past the
+    /// end. The first page always exists.
+    pub fn new(pages: &'a [Page], number: usize, size: usize) -> Option<Self> {
+        let start = number.checked_sub(1)?.checked_mul(size)?;
+        if start > 0 && start >= pages.len() {
+            return None;
+        }
+        let end = start.saturating_add(size).min(pages.len());
+
+        Some(Self {
+            pages: &pages[start..end],
+            number,
+            has_next: end < pages.len(),
+            collection: None,
+        })
+    }
+
+    /// Keeps the listing limited to `collection` when paging.
+    pub fn in_collection(self, collection: Option<&'a str>) -> Self {
+        Self { collection, ..self }
+    }
+
+    fn pagination_html(&self) -> String {
+        let collection = self
+            .collection
+            .map(|collection| format!("&amp;collection={}", escape_html(collection)))
+            .unwrap_or_default();
+        let previous = if self.number > 1 {
+            format!(
+                r#"<a rel="prev" href="?page={}{collection}">Newer</a>"#,
+                self.number - 1
+            )
+        } else {
+            String::new()
+        };
+        let next = if self.has_next {
+            format!(
+                r#"<a rel="next" href="?page={}{collection}">Older</a>"#,
+                self.number + 1
+            )
+        } else {
+            String::new()
+        };
+
+        if previous.is_empty() && next.is_empty() {
+            String::new()
+        } else {
+            format!(
+                r#"
+            <nav class="pagination">{previous}{next}</nav>"#
+            )
+        }
+    }
+}
+
+/// Renders a titled list of links to pages with their excerpts, in the given
+/// order.
+pub fn render_listing(title: &str, listing_page: &ListingPage) -> Response {
+    let mut items_html = String::new();
+    for page in listing_page.pages {
+        items_html.push_str(&format!(
+            r#"
+                <li><a href="{}">{}</a><p class="excerpt">{}</p></li>"#,
+            escape_html(&page.href()),
+            escape_html(page.title.as_deref().unwrap_or_default()),
+            escape_html(&page.excerpt(EXCERPT_WORDS)),
+        ));
+    }
+
+    let content = format!(
+        r#"<h1>{}</h1>
+            <ul class="listing">{}
+            </ul>{}"#,
+        escape_html(title),
+        items_html,
+        listing_page.pagination_html()
+    );
+    let html = render_layout(title, "", "", &content);
+
+    ([(header::CONTENT_TYPE, HTML_CONTENT_TYPE)], html).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use axum::{body::Body, http::Request};
+    use rayon::iter::IntoParallelIterator;
+    use time::OffsetDateTime;
+    use tower::ServiceExt;
+    use ulid::Ulid;
+
+    use super::*;
+
+    fn pages(count: usize) -> Vec<Page> {
+        (0..count)
+            .map(|index| {
+                Page::parse(
+                    PathBuf::from(format!("/pages/{index}.md")),
+                    &format!("# Page {index}"),
+                    OffsetDateTime::UNIX_EPOCH,
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_archive_groups_dated_pages_newest_first() {
+        let page = |name: &str, date: Option<&str>| {
+            let frontmatter = date.map(|date| format!("date: {date}")).unwrap_or_default();
+            Page::parse(
+                PathBuf::from(format!("/pages/{name}.md")),
+                &format!("---\ntitle: {name}\n{frontmatter}\n---\nBody"),
+                OffsetDateTime::UNIX_EPOCH,
+            )
+            .unwrap()
+        };
+        let html = archive_html(vec![
+            page("Old", Some("2023-12-24")),
+            page("About", None),
+            page("Early", Some("2024-06-01")),
+            page("Late", Some("2024-06-30")),
+            page("Spring", Some("2024-03-10")),
+        ]);
+
+        let position = |text: &str| {
+            html.find(text)
+                .unwrap_or_else(|| panic!("{text} in {html}"))
+        };
+        assert!(position("<h2>2024</h2>") < position("<h3>June</h3>"));
+        assert!(position("<h3>June</h3>") < position(">Late<"));
+        assert!(position(">Late<") < position(">Early<"));
+        assert!(position(">Early<") < position("<h3>March</h3>"));
+        assert!(position("<h3>March</h3>") < position("<h2>2023</h2>"));
+        assert!(position("<h2>2023</h2>") < position(">Old<"));
+        assert!(!html.contains("About"));
+        assert_eq!(html.matches("<ul").count(), html.matches("</ul>").count());
+        assert_eq!(html.matches("<li").count(), html.matches("</li>").count());
+    }
+
+    #[test]
+    fn test_tag_counts_fold_case() {
+        let page = |name: &str, frontmatter: &str| {
+            Page::parse(
+                PathBuf::from(format!("/pages/{name}.md")),
+                &format!("---\n{frontmatter}\n---\nBody"),
+                OffsetDateTime::UNIX_EPOCH,
+            )
+            .unwrap()
+        };
+        // Tags as read with their case preserved.
+        let mut unicode = page("e", "title: Unicode");
+        unicode.tags = ["Ünicode".to_string(), "ünicode".to_string()].into();
+        let pages = vec![
+            page("a", "tags: [Rust, rust, web]"),
+            page("b", "tags: [rust, \" \"]"),
+            page("c", "tags: [web]\ndraft: true"),
+            page("d", "title: Untagged"),
+            unicode,
+        ];
+
+        let tags = tag_counts(pages.into_par_iter());
+        assert_eq!(
+            tags,
+            vec![
+                TagCount {
+                    tag: "rust".into(),
+                    count: 2
+                },
+                TagCount {
+                    tag: "web".into(),
+                    count: 1
+                },
+                TagCount {
+                    tag: "ünicode".into(),
+                    count: 1
+                },
+            ]
+        );
+
+        let html = tag_cloud_html(&tags);
+        assert!(
+            html.contains(
+                r#"<li class="weight-5"><a href="/tags/rust" data-count="2">rust</a></li>"#
+            )
+        );
+        assert!(
+            html.contains(
+                r#"<li class="weight-1"><a href="/tags/web" data-count="1">web</a></li>"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_tag_cloud_links_encode_tags() {
+        let tags: Vec<TagCount> = ["c#", "ci/cd", "q&a?", "ünicode"]
+            .into_iter()
+            .map(|tag| TagCount {
+                tag: tag.into(),
+                count: 1,
+            })
+            .collect();
+        let html = tag_cloud_html(&tags);
+
+        assert!(html.contains(r#"href="/tags/c%23""#));
+        assert!(html.contains(r#"href="/tags/ci%2Fcd""#));
+        assert!(html.contains(r#"href="/tags/q&amp;a%3F""#));
+        assert!(html.contains(r#"href="/tags/%C3%BCnicode""#));
+        assert!(html.contains(">q&amp;a?</a>"));
+    }
+
+    #[tokio::test]
+    async fn test_tag_listings_match_normalized_tags() {
+        let pages_path = load_config().pages_path();
+        fs::create_dir_all(&pages_path).unwrap();
+        let id = Ulid::new();
+        let file = pages_path.join(format!("tagged-{id}.md"));
+        fs::write(
+            &file,
+            format!("---\ntags: [Ünicode-{id}, ci/cd-{id}]\n---\nBody"),
+        )
+        .unwrap();
+
+        for tag in [format!("ÜNICODE-{id}"), format!("ci/cd-{id}")] {
+            let request = Request::builder()
+                .uri(tag_route(&tag))
+                .body(Body::empty())
+                .unwrap();
+            let response = listing_routes().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{tag}");
+        }
+
+        fs::remove_file(file).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_author_listings_link_encoded_and_by_date() {
+        let pages_path = load_config().pages_path();
+        fs::create_dir_all(&pages_path).unwrap();
+        let id = Ulid::new();
+        let author = format!("Ada #{id}/Lovelace");
+        assert_eq!(
+            author_route(&author),
+            format!("/authors/Ada%20%23{id}%2FLovelace")
+        );
+
+        let files = [("Summer", "2024-06-01"), ("Winter", "2024-01-01")].map(|(title, date)| {
+            let file = pages_path.join(format!("{title}-{id}.md"));
+            fs::write(
+                &file,
+                format!("---\ntitle: {title}\nauthor: \"{author}\"\ndate: {date}\n---\nBody"),
+            )
+            .unwrap();
+            file
+        });
+
+        let request = Request::builder()
+            .uri(author_route(&author))
+            .body(Body::empty())
+            .unwrap();
+        let response = listing_routes().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.find(">Summer<").unwrap() < html.find(">Winter<").unwrap());
+
+        for file in files {
+            fs::remove_file(file).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_listing_pages() {
+        let pages = pages(5);
+
+        let first = ListingPage::new(&pages, 1, 2).unwrap();
+        assert_eq!(first.pages.len(), 2);
+        assert!(first.pagination_html().contains(r#"href="?page=2""#));
+        assert!(!first.pagination_html().contains("prev"));
+
+        let last = ListingPage::new(&pages, 3, 2).unwrap();
+        assert_eq!(last.pages.len(), 1);
+        assert!(
+            last.pagination_html()
+                .contains(r#"rel="prev" href="?page=2""#)
+        );
+        assert!(!last.pagination_html().contains("next"));
+
+        assert!(ListingPage::new(&pages, 4, 2).is_none());
+        assert!(ListingPage::new(&pages, 0, 2).is_none());
+        assert_eq!(
+            ListingPage::new(&pages, 1, 10).unwrap().pagination_html(),
+            ""
+        );
+        assert!(ListingPage::new(&[], 1, 10).is_some());
+    }
+}
@@ -0,0 +1,64 @@
+use axum::{
+    Router,
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use rayon::iter::ParallelIterator;
+
+use crate::{config::load_config, page::Page};
+
+/// Serves the site content as plain text for LLM and RAG ingestion.
+pub fn llms_routes() -> Router {
+    Router::new().route("/llms.txt", get(llms_handler))
+}
+
+async fn llms_handler() -> Response {
+    let mut pages: Vec<Page> =
+        Page::read_blocking(|| Page::all().filter(|page| !page.draft).collect()).await;
+    pages.sort_by(|a, b| a.url.cmp(&b.url));
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        render_llms_txt(load_config().title(), &pages),
+    )
+        .into_response()
+}
+
+fn render_llms_txt(site_title: &str, pages: &[Page]) -> String {
+    let mut output = format!("# {site_title}\n");
+    for page in pages {
+        output.push_str(&format!(
+            "\n## {}\n\nURL: {}\n\n{}\n",
+            page.title.as_deref().unwrap_or_default(),
+            page.href(),
+            page.plain_text()
+        ));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use time::OffsetDateTime;
+
+    use super::*;
+
+    #[test]
+    fn test_llms_txt_contains_plain_text_body() {
+        let page = Page::parse(
+            PathBuf::from("/pages/about.md"),
+            "---\ntitle: About\n---\nWe *build* [websites](/work).",
+            OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap();
+
+        let output = render_llms_txt("Site", &[page]);
+        assert!(output.starts_with("# Site\n"));
+        assert!(output.contains("## About"));
+        assert!(output.contains("We build websites."));
+        assert!(!output.contains('<'));
+    }
+}
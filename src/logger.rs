@@ -1,6 +1,7 @@
-use crate::config::Config;
+use crate::config::{Config, ConfigLogRotation};
 use anyhow::Result;
 use tracing::level_filters::LevelFilter;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::{filter::EnvFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt};
 
 pub fn init_logging(config: &Config) -> Result<()> {
@@ -12,14 +13,22 @@ pub fn init_logging(config: &Config) -> Result<()> {
         .with_level(true)
         .with_ansi(atty::is(atty::Stream::Stdout));
 
+    let mut file_appender = RollingFileAppender::builder()
+        .rotation(match config.log_rotation() {
+            ConfigLogRotation::Hourly => Rotation::HOURLY,
+            ConfigLogRotation::Daily => Rotation::DAILY,
+            ConfigLogRotation::Never => Rotation::NEVER,
+        })
+        .filename_prefix(config.log_file_name());
+    if let Some(max_files) = *config.log_max_files() {
+        file_appender = file_appender.max_log_files(max_files);
+    }
+
     let file_log = fmt::layer()
         .with_target(true)
         .with_level(true)
         .with_ansi(false)
-        .with_writer(tracing_appender::rolling::daily(
-            config.log_path(),
-            "website.log",
-        ));
+        .with_writer(file_appender.build(config.log_path())?);
 
     let log_level = (*config.log_level()).into();
     let level_filter = LevelFilter::from_level(log_level).into();
@@ -34,6 +43,14 @@ pub fn init_logging(config: &Config) -> Result<()> {
         .with(file_log)
         .init();
 
+    std::panic::set_hook(Box::new(|info| {
+        tracing::error!(
+            "💥 {}\n{}",
+            info,
+            std::backtrace::Backtrace::force_capture()
+        );
+    }));
+
     tracing::info!("📜 Logger setup at level: {}", log_level);
 
     Ok(())
@@ -1,23 +1,85 @@
 use anyhow::Result;
-use config::load_config;
+use assets::check_assets;
+use authoring::{fix_ids, new_page};
+use cli::{Cli, Command};
+use config::{ConfigOverrides, init_config};
+use link_checker::check_links;
 use logger::init_logging;
+use og_image::spawn_og_image_pruning;
 use search::spawn_search_indexer;
 use web::start_server;
 
+mod access_log;
+mod admin;
 mod assets;
+#[cfg(debug_assertions)]
+mod assets_build;
+mod authoring;
+mod cli;
 mod config;
 mod error_handler;
+mod feed;
+mod gone;
+mod link_checker;
+mod listing;
+mod llms;
 mod logger;
+mod markdown;
+mod navigation;
+mod og_image;
 mod page;
+mod page_index;
+mod response_cache;
 mod search;
+mod section;
 mod security;
+mod sitemap;
 mod web;
+mod webmanifest;
+mod websiteignore;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let config = load_config();
+    let cli = Cli::parse_args();
+    let config = init_config(ConfigOverrides {
+        data_path: cli.data_path,
+    });
     init_logging(&config)?;
-    let (search_index, _debouncer, _watcher) = spawn_search_indexer(&config).await?;
-    start_server(&config, search_index).await?;
+
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => {
+            check_assets(&config)?;
+            #[cfg(debug_assertions)]
+            let _asset_watcher = assets::watch_assets()
+                .inspect_err(|e| tracing::warn!("⚠️ Not watching assets for changes: {}", e))
+                .ok();
+            spawn_og_image_pruning(&config);
+            let (search_index, _debouncer, _watcher) = spawn_search_indexer(&config).await?;
+            start_server(&config, search_index).await?;
+        }
+        Command::CheckLinks => {
+            let broken = check_links();
+            for link in &broken {
+                println!("{}: {}", link.page.to_string_lossy(), link.href);
+            }
+            if !broken.is_empty() {
+                eprintln!("💥 Found {} broken links", broken.len());
+                std::process::exit(1);
+            }
+            println!("✅ No broken links found");
+        }
+        Command::FixIds => {
+            let changed = fix_ids()?;
+            for path in &changed {
+                println!("{}", path.to_string_lossy());
+            }
+            println!("✅ Added ids to {} pages", changed.len());
+        }
+        Command::New { path, title, tags } => {
+            let file_path = new_page(&path, title, tags)?;
+            println!("{}", file_path.to_string_lossy());
+        }
+    }
+
     Ok(())
 }
@@ -0,0 +1,554 @@
+use std::collections::{HashMap, HashSet};
+
+use ammonia::Builder;
+use latex2mathml::{DisplayStyle, latex_to_mathml};
+use lazy_static::lazy_static;
+use pulldown_cmark::{
+    BlockQuoteKind, CowStr, Event, HeadingLevel, Options, Parser, Tag, TagEnd, html,
+};
+use regex::{Captures, Regex};
+
+use crate::{
+    config::{Config, ConfigAllowedHtml},
+    web::escape_html,
+};
+
+/// MathML elements produced by the math renderer.
+const MATH_TAGS: [&str; 22] = [
+    "math",
+    "semantics",
+    "annotation",
+    "mrow",
+    "mi",
+    "mn",
+    "mo",
+    "ms",
+    "mtext",
+    "mspace",
+    "msub",
+    "msup",
+    "msubsup",
+    "munder",
+    "mover",
+    "munderover",
+    "mfrac",
+    "msqrt",
+    "mroot",
+    "mtable",
+    "mtr",
+    "mtd",
+];
+
+const MATH_ATTRIBUTES: [&str; 12] = [
+    "display",
+    "xmlns",
+    "mathvariant",
+    "stretchy",
+    "fence",
+    "separator",
+    "accent",
+    "lspace",
+    "rspace",
+    "linethickness",
+    "columnalign",
+    "encoding",
+];
+
+lazy_static! {
+    /// Opening of a link in sanitized HTML, which always quotes attributes
+    /// with double quotes, capturing the `href`.
+    static ref LINK_START: Regex = Regex::new(r#"<a ((?:[^>]*? )?href="([^"]*)")"#).unwrap();
+}
+
+/// Kinds of `> [!KIND]` admonitions, as used in their class names.
+const ADMONITION_KINDS: [&str; 5] = ["note", "tip", "important", "warning", "caution"];
+
+/// Renders markdown to sanitized HTML according to the configured options.
+pub fn render_markdown(markdown: &str, config: &Config) -> String {
+    let mut options =
+        Options::ENABLE_GFM | Options::ENABLE_DEFINITION_LIST | Options::ENABLE_TASKLISTS;
+    if *config.render_math() {
+        options.insert(Options::ENABLE_MATH);
+    }
+    if *config.smart_typography() {
+        options.insert(Options::ENABLE_SMART_PUNCTUATION);
+    }
+
+    let offset = *config.heading_offset();
+    let parser = Parser::new_ext(markdown, options).map(|event| match event {
+        Event::Start(Tag::Heading {
+            level,
+            id,
+            classes,
+            attrs,
+        }) if offset > 0 => Event::Start(Tag::Heading {
+            level: offset_heading(level, offset),
+            id,
+            classes,
+            attrs,
+        }),
+        Event::End(TagEnd::Heading(level)) if offset > 0 => {
+            Event::End(TagEnd::Heading(offset_heading(level, offset)))
+        }
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: with_base_path(dest_url, config),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: with_base_path(dest_url, config),
+            title,
+            id,
+        }),
+        Event::Start(Tag::BlockQuote(Some(kind))) => Event::Html(admonition_start(kind)),
+        Event::End(TagEnd::BlockQuote(Some(_))) => Event::Html("</aside>\n".into()),
+        Event::InlineMath(latex) => Event::InlineHtml(render_math(&latex, DisplayStyle::Inline)),
+        Event::DisplayMath(latex) => Event::Html(render_math(&latex, DisplayStyle::Block)),
+        event => event,
+    });
+
+    let mut html = String::new();
+    html::push_html(&mut html, wrap_figures(parser.collect()).into_iter());
+    let html = sanitizer(config).clean(&html).to_string();
+    protect_external_links(&html, config).trim().to_string()
+}
+
+/// The heading level `offset` levels below `level`, at most `<h6>`.
+fn offset_heading(level: HeadingLevel, offset: u8) -> HeadingLevel {
+    HeadingLevel::try_from((level as usize + offset as usize).min(6)).unwrap_or(HeadingLevel::H6)
+}
+
+/// Adds `rel="noopener noreferrer"`, and `target="_blank"` when configured,
+/// to links leading to other hosts than the configured `base_url`. Internal
+/// links are left as they are.
+fn protect_external_links(html: &str, config: &Config) -> String {
+    let site = url_authority(config.base_url());
+    let target = if *config.external_links_new_tab() {
+        r#" target="_blank""#
+    } else {
+        ""
+    };
+
+    LINK_START
+        .replace_all(html, |captures: &Captures| {
+            let external =
+                url_authority(&captures[2]).is_some_and(|host| Some(&host) != site.as_ref());
+            if external {
+                format!(r#"<a rel="noopener noreferrer"{target} {}"#, &captures[1])
+            } else {
+                captures[0].to_string()
+            }
+        })
+        .into_owned()
+}
+
+/// Host and port of an absolute or protocol relative http(s) URL, lowercased.
+pub fn url_authority(url: &str) -> Option<String> {
+    let url = url.to_ascii_lowercase();
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .or_else(|| url.strip_prefix("//"))?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    (!host.is_empty()).then(|| host.to_string())
+}
+
+/// Extracts the readable text of markdown, keeping paragraphs apart.
+pub fn markdown_to_text(markdown: &str) -> String {
+    extract_text(markdown, true)
+}
+
+/// Like [`markdown_to_text`], but without the text of headings, for excerpts
+/// shown below the page title.
+pub fn markdown_to_body_text(markdown: &str) -> String {
+    extract_text(markdown, false)
+}
+
+fn extract_text(markdown: &str, headings: bool) -> String {
+    let mut text = String::new();
+    let mut in_heading = false;
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Start(Tag::Heading { .. }) if !headings => in_heading = true,
+            Event::End(TagEnd::Heading(_)) if !headings => in_heading = false,
+            _ if in_heading => (),
+            Event::Text(content) | Event::Code(content) => text.push_str(&content),
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            Event::End(
+                TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::Item | TagEnd::CodeBlock,
+            ) => text.push_str("\n\n"),
+            _ => (),
+        }
+    }
+    text.trim().to_string()
+}
+
+fn sanitizer(config: &Config) -> Builder<'_> {
+    let mut builder = Builder::default();
+    builder.link_rel(None);
+    allow_configured_html(
+        &mut builder,
+        config.allowed_html(),
+        HashMap::from([
+            (
+                "aside",
+                HashSet::from_iter(std::iter::once("admonition").chain(ADMONITION_KINDS)),
+            ),
+            ("p", HashSet::from(["admonition-title"])),
+        ]),
+    );
+    // Task list checkboxes. Whatever the markup, inputs can only be disabled
+    // checkboxes, so pages can not hold working form fields.
+    builder
+        .add_tags(["input"])
+        .add_tag_attributes("input", ["checked"])
+        .set_tag_attribute_value("input", "type", "checkbox")
+        .set_tag_attribute_value("input", "disabled", "");
+    if *config.lazy_images() {
+        builder
+            .set_tag_attribute_value("img", "loading", "lazy")
+            .set_tag_attribute_value("img", "decoding", "async");
+    }
+    if *config.render_math() {
+        builder
+            .add_tags(MATH_TAGS)
+            .add_generic_attributes(MATH_ATTRIBUTES);
+    }
+    builder
+}
+
+/// Lets the extra HTML allowed in the config through `builder`, with the
+/// configured classes added to `classes`.
+pub fn allow_configured_html<'a>(
+    builder: &mut Builder<'a>,
+    allowed: &'a ConfigAllowedHtml,
+    mut classes: HashMap<&'a str, HashSet<&'a str>>,
+) {
+    builder.add_tags(allowed.tags().iter().map(String::as_str));
+    for (tag, attributes) in allowed.attributes() {
+        builder.add_tag_attributes(tag.as_str(), attributes.iter().map(String::as_str));
+    }
+    for (tag, names) in allowed.classes() {
+        classes
+            .entry(tag.as_str())
+            .or_default()
+            .extend(names.iter().map(String::as_str));
+    }
+    builder.allowed_classes(classes);
+}
+
+/// Turns paragraphs holding nothing but an image into figures, captioned with
+/// the alt text of the image.
+fn wrap_figures(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut output = Vec::with_capacity(events.len());
+    let mut index = 0;
+
+    while index < events.len() {
+        let Some(end) = standalone_image_end(&events, index) else {
+            output.push(events[index].clone());
+            index += 1;
+            continue;
+        };
+
+        let image = &events[index + 1..end];
+        let alt: String = image
+            .iter()
+            .filter_map(|event| match event {
+                Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+                _ => None,
+            })
+            .collect();
+        output.push(Event::Html("<figure>".into()));
+        output.extend(image.iter().cloned());
+        if !alt.trim().is_empty() {
+            output.push(Event::Html(
+                format!("<figcaption>{}</figcaption>", escape_html(alt.trim())).into(),
+            ));
+        }
+        output.push(Event::Html("</figure>\n".into()));
+        index = end + 1;
+    }
+
+    output
+}
+
+/// Index of the end of the paragraph opened at `start`, if its only content
+/// is a single image.
+fn standalone_image_end(events: &[Event<'_>], start: usize) -> Option<usize> {
+    if !matches!(events.get(start)?, Event::Start(Tag::Paragraph))
+        || !matches!(events.get(start + 1)?, Event::Start(Tag::Image { .. }))
+    {
+        return None;
+    }
+
+    let image_end = start
+        + 1
+        + events[start + 1..]
+            .iter()
+            .position(|event| matches!(event, Event::End(TagEnd::Image)))?;
+    matches!(events.get(image_end + 1)?, Event::End(TagEnd::Paragraph)).then_some(image_end + 1)
+}
+
+/// Opens a `> [!NOTE]` style blockquote as an aside titled after its kind.
+fn admonition_start(kind: BlockQuoteKind) -> CowStr<'static> {
+    let (class, title) = match kind {
+        BlockQuoteKind::Note => ("note", "Note"),
+        BlockQuoteKind::Tip => ("tip", "Tip"),
+        BlockQuoteKind::Important => ("important", "Important"),
+        BlockQuoteKind::Warning => ("warning", "Warning"),
+        BlockQuoteKind::Caution => ("caution", "Caution"),
+    };
+    format!("<aside class=\"admonition {class}\">\n<p class=\"admonition-title\">{title}</p>\n")
+        .into()
+}
+
+/// Renders LaTeX to MathML, falling back on the source as code if it is invalid.
+fn render_math(latex: &str, display: DisplayStyle) -> CowStr<'static> {
+    match latex_to_mathml(latex, display) {
+        Ok(mathml) => mathml.into(),
+        Err(error) => {
+            tracing::warn!("⚠️ Unable to render math {latex:?}: {error}");
+            format!(r#"<code class="math">{}</code>"#, escape_html(latex)).into()
+        }
+    }
+}
+
+/// Prefixes site absolute links with the configured base path.
+fn with_base_path<'a>(url: CowStr<'a>, config: &Config) -> CowStr<'a> {
+    if url.starts_with('/') && !url.starts_with("//") && !config.base_path().is_empty() {
+        config.route(&url).into()
+    } else {
+        url
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigParsed;
+
+    fn config(toml: &str) -> Config {
+        Config::from(toml::from_str::<ConfigParsed>(toml).unwrap())
+    }
+
+    #[test]
+    fn test_markdown_to_text() {
+        let text = markdown_to_text("# Title\n\nSome *emphasis* and `code`.\n\n<b>raw</b>");
+        assert_eq!(text, "Title\n\nSome emphasis and code.\n\nraw");
+    }
+
+    #[test]
+    fn test_markdown_to_body_text() {
+        let text = markdown_to_body_text("# Title\n\nSome *emphasis*.\n\n## `Sub` heading\n\nMore");
+        assert_eq!(text, "Some emphasis.\n\nMore");
+    }
+
+    #[test]
+    fn test_math_rendering() {
+        let html = render_markdown("Inline $x^2$ math", &config("render_math = true"));
+        assert!(html.contains("<math"), "{html}");
+        assert!(!html.contains('$'), "{html}");
+    }
+
+    #[test]
+    fn test_admonition_rendering() {
+        let html = render_markdown("> [!WARNING]\n> Mind the *gap*", &config(""));
+        assert_eq!(
+            html,
+            "<aside class=\"admonition warning\">\n<p class=\"admonition-title\">Warning</p>\n\
+             <p>Mind the <em>gap</em></p>\n</aside>"
+        );
+
+        let html = render_markdown("> Plain quote", &config(""));
+        assert_eq!(html, "<blockquote>\n<p>Plain quote</p>\n</blockquote>");
+    }
+
+    #[test]
+    fn test_definition_list_rendering() {
+        let html = render_markdown("Term\n: Definition", &config(""));
+        assert!(html.starts_with("<dl>"), "{html}");
+        assert!(html.contains("<dt>Term</dt>"), "{html}");
+        assert!(html.contains("<dd>Definition</dd>"), "{html}");
+    }
+
+    #[test]
+    fn test_task_list_rendering() {
+        // The attributes set by the sanitizer come in no particular order.
+        let inputs = |html: &str| -> Vec<Vec<String>> {
+            html.split("<input ")
+                .skip(1)
+                .map(|rest| {
+                    let mut attributes: Vec<String> = rest[..rest.find('>').unwrap()]
+                        .split_whitespace()
+                        .map(String::from)
+                        .collect();
+                    attributes.sort();
+                    attributes
+                })
+                .collect()
+        };
+
+        let html = render_markdown("- [x] Done\n- [ ] To do\n- Plain", &config(""));
+        assert_eq!(
+            inputs(&html),
+            vec![
+                vec![r#"checked="""#, r#"disabled="""#, r#"type="checkbox""#],
+                vec![r#"disabled="""#, r#"type="checkbox""#],
+            ],
+            "{html}"
+        );
+        assert!(html.contains("\nDone</li>"), "{html}");
+        assert!(html.contains("<li>Plain</li>"), "{html}");
+
+        let html = render_markdown(
+            r#"<input type="text" name="secret" value="x" onfocus="steal()">"#,
+            &config(""),
+        );
+        assert_eq!(
+            inputs(&html),
+            vec![vec![r#"disabled="""#, r#"type="checkbox""#]],
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn test_unknown_classes_are_stripped() {
+        let html = render_markdown(r#"<aside class="admonition evil">x</aside>"#, &config(""));
+        assert_eq!(html, r#"<aside class="admonition">x</aside>"#);
+    }
+
+    #[test]
+    fn test_external_links_are_protected() {
+        let markdown = "[In](/about) [Site](https://Example.com/x) [Out](https://rust-lang.org) \
+                        [Relative](docs) [Mail](mailto:a@b.c)";
+        let html = render_markdown(markdown, &config(r#"base_url = "https://example.com""#));
+        assert_eq!(
+            html,
+            "<p><a href=\"/about\">In</a> <a href=\"https://Example.com/x\">Site</a> \
+             <a rel=\"noopener noreferrer\" href=\"https://rust-lang.org\">Out</a> \
+             <a href=\"docs\">Relative</a> <a href=\"mailto:a@b.c\">Mail</a></p>"
+        );
+
+        let html = render_markdown(
+            r#"<a title="x" href="//rust-lang.org">Out</a>"#,
+            &config("external_links_new_tab = true"),
+        );
+        assert_eq!(
+            html,
+            r#"<p><a rel="noopener noreferrer" target="_blank" title="x" href="//rust-lang.org">Out</a></p>"#
+        );
+    }
+
+    #[test]
+    fn test_rel_and_target_in_html_are_stripped() {
+        let html = render_markdown(
+            r#"<a href="/about" rel="opener" target="_top">In</a>"#,
+            &config(""),
+        );
+        assert_eq!(html, r#"<p><a href="/about">In</a></p>"#);
+    }
+
+    #[test]
+    fn test_standalone_images_become_figures() {
+        let html = render_markdown("![A *cat* & dog](/cat.png)", &config("lazy_images = false"));
+        assert_eq!(
+            html,
+            "<figure><img src=\"/cat.png\" alt=\"A cat &amp; dog\">\
+             <figcaption>A cat &amp; dog</figcaption></figure>"
+        );
+
+        let html = render_markdown(
+            "See ![a cat](/cat.png) here",
+            &config("lazy_images = false"),
+        );
+        assert_eq!(html, "<p>See <img src=\"/cat.png\" alt=\"a cat\"> here</p>");
+
+        let html = render_markdown("![](/cat.png)", &config("lazy_images = false"));
+        assert_eq!(html, "<figure><img src=\"/cat.png\" alt=\"\"></figure>");
+    }
+
+    #[test]
+    fn test_images_load_lazily() {
+        let html = render_markdown("See ![a cat](/cat.png) here", &config(""));
+        assert!(html.contains(r#"loading="lazy""#), "{html}");
+        assert!(html.contains(r#"decoding="async""#), "{html}");
+
+        let html = render_markdown(
+            "See ![a cat](/cat.png) here",
+            &config("lazy_images = false"),
+        );
+        assert_eq!(html, r#"<p>See <img src="/cat.png" alt="a cat"> here</p>"#);
+    }
+
+    #[test]
+    fn test_smart_typography() {
+        let markdown = r#""Wait," she said -- 'it's 9--5' --- and then... `"code"`"#;
+        let html = render_markdown(markdown, &config("smart_typography = true"));
+        assert_eq!(
+            html,
+            r#"<p>“Wait,” she said – ‘it’s 9–5’ — and then… <code>"code"</code></p>"#
+        );
+
+        let html = render_markdown(markdown, &config(""));
+        assert_eq!(
+            html,
+            r#"<p>"Wait," she said -- 'it's 9--5' --- and then... <code>"code"</code></p>"#
+        );
+    }
+
+    #[test]
+    fn test_heading_offset() {
+        let markdown = "# Title\n\n## Part\n\n###### Detail";
+        assert_eq!(
+            render_markdown(markdown, &config("")),
+            "<h1>Title</h1>\n<h2>Part</h2>\n<h6>Detail</h6>"
+        );
+        assert_eq!(
+            render_markdown(markdown, &config("heading_offset = 1")),
+            "<h2>Title</h2>\n<h3>Part</h3>\n<h6>Detail</h6>"
+        );
+    }
+
+    #[test]
+    fn test_configured_html_is_allowed() {
+        let markdown = "<details open><summary>More</summary><video controls></video></details>\n\n<span class=\"note other\">Hi</span>";
+
+        let html = render_markdown(markdown, &config(""));
+        assert!(html.contains("<details><summary>"), "{html}");
+        assert!(!html.contains("<video"), "{html}");
+        assert!(!html.contains("class="), "{html}");
+
+        let html = render_markdown(
+            markdown,
+            &config(
+                "[allowed_html]\ntags = [\"video\"]\nattributes = { details = [\"open\"], video = [\"controls\"] }\nclasses = { span = [\"note\"] }",
+            ),
+        );
+        assert!(
+            html.contains(
+                r#"<details open=""><summary>More</summary><video controls=""></video></details>"#
+            ),
+            "{html}"
+        );
+        assert!(html.contains(r#"<span class="note">Hi</span>"#), "{html}");
+    }
+
+    #[test]
+    fn test_math_disabled_by_default() {
+        let html = render_markdown("Inline $x^2$ math", &config(""));
+        assert_eq!(html, "<p>Inline $x^2$ math</p>");
+    }
+}
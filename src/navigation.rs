@@ -0,0 +1,224 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::SystemTime,
+};
+
+use lazy_static::lazy_static;
+use serde::Serialize;
+
+use crate::page::Page;
+
+lazy_static! {
+    /// Published pages per directory, in reading order, along with the
+    /// modification times of their files when they were read.
+    static ref SIBLINGS: RwLock<HashMap<PathBuf, CachedSiblings>> = RwLock::new(HashMap::new());
+}
+
+/// Markdown files of a directory along with their modification times.
+type FileTimes = Vec<(PathBuf, Option<SystemTime>)>;
+
+type CachedSiblings = (FileTimes, Vec<Sibling>);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct PageLink {
+    pub url: String,
+    pub title: Option<String>,
+}
+
+impl From<&Page> for PageLink {
+    fn from(page: &Page) -> Self {
+        Self {
+            url: page.href(),
+            title: page.title.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Adjacent {
+    pub previous: Option<PageLink>,
+    pub next: Option<PageLink>,
+}
+
+/// A page in the directory of the page being viewed, as much of it as is
+/// needed to link to it.
+#[derive(Clone)]
+struct Sibling {
+    path: PathBuf,
+    link: PageLink,
+}
+
+impl From<&Page> for Sibling {
+    fn from(page: &Page) -> Self {
+        Self {
+            path: page.path.clone(),
+            link: PageLink::from(page),
+        }
+    }
+}
+
+/// Finds the pages before and after `page` among the pages in its directory.
+pub fn adjacent_pages(page: &Page) -> Adjacent {
+    // Pages in a directory share their section, so either all of them are
+    // in the navigation or none are.
+    if !page.section.in_navigation() {
+        return Adjacent::default();
+    }
+
+    let siblings = page.path.parent().map(sibling_pages).unwrap_or_default();
+    adjacent_in(&siblings, &page.path)
+}
+
+/// The published pages directly in `dir`, in reading order. They are only
+/// read again once a markdown file in `dir` is added, removed or modified.
+fn sibling_pages(dir: &Path) -> Vec<Sibling> {
+    let files: FileTimes = markdown_files(dir)
+        .into_iter()
+        .map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            (path, modified)
+        })
+        .collect();
+
+    if let Some((cached_files, siblings)) =
+        SIBLINGS.read().unwrap_or_else(|e| e.into_inner()).get(dir)
+    {
+        if *cached_files == files {
+            return siblings.clone();
+        }
+    }
+
+    let mut pages: Vec<Page> = files
+        .iter()
+        .filter_map(|(path, _)| Page::read(path).ok())
+        .filter(|page| !page.draft)
+        .collect();
+    pages.sort_by(Page::reading_order);
+
+    let siblings: Vec<Sibling> = pages.iter().map(Sibling::from).collect();
+    SIBLINGS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(dir.to_path_buf(), (files, siblings.clone()));
+    siblings
+}
+
+/// The visible markdown files directly in `dir`, sorted by path.
+fn markdown_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path.extension() == Some("md".as_ref())
+                && !path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+fn adjacent_in(siblings: &[Sibling], path: &Path) -> Adjacent {
+    let Some(position) = siblings.iter().position(|sibling| sibling.path == path) else {
+        return Adjacent::default();
+    };
+
+    Adjacent {
+        previous: position
+            .checked_sub(1)
+            .and_then(|previous| siblings.get(previous))
+            .map(|sibling| sibling.link.clone()),
+        next: siblings
+            .get(position + 1)
+            .map(|sibling| sibling.link.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use time::OffsetDateTime;
+    use ulid::Ulid;
+
+    use super::*;
+    use crate::config::load_config;
+
+    fn page(name: &str, frontmatter: &str) -> Page {
+        Page::parse(
+            PathBuf::from(format!("/docs/{name}.md")),
+            &format!("---\n{frontmatter}\n---\nBody"),
+            OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_adjacent_pages_by_weight_then_title() {
+        let mut pages = [
+            page("c", "title: C"),
+            page("b", "title: B\nweight: 2"),
+            page("a", "title: A"),
+            page("d", "title: D\nweight: 1"),
+        ];
+        pages.sort_by(Page::reading_order);
+
+        let titles: Vec<_> = pages.iter().map(|p| p.title.clone().unwrap()).collect();
+        assert_eq!(titles, ["D", "B", "A", "C"]);
+
+        let siblings: Vec<Sibling> = pages.iter().map(Sibling::from).collect();
+
+        let first = adjacent_in(&siblings, &pages[0].path);
+        assert_eq!(first.previous, None);
+        assert_eq!(first.next.unwrap().title, Some("B".into()));
+
+        let middle = adjacent_in(&siblings, &pages[1].path);
+        assert_eq!(middle.previous.unwrap().title, Some("D".into()));
+        assert_eq!(middle.next.unwrap().title, Some("A".into()));
+
+        let last = adjacent_in(&siblings, &pages[3].path);
+        assert_eq!(last.previous.unwrap().title, Some("A".into()));
+        assert_eq!(last.next, None);
+    }
+
+    #[test]
+    fn test_siblings_are_read_again_when_files_change() {
+        let dir = load_config()
+            .pages_path()
+            .join(format!("nav-{}", Ulid::new()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.md"), "---\ntitle: A\n---\nBody").unwrap();
+        fs::write(dir.join("b.md"), "---\ntitle: B\n---\nBody").unwrap();
+        let first = Page::read(dir.join("a.md")).unwrap();
+
+        let next = adjacent_pages(&first).next.unwrap();
+        assert_eq!(next.title, Some("B".into()));
+
+        // Set apart from the first write, as some filesystems only keep
+        // modification times to the second.
+        fs::write(dir.join("b.md"), "---\ntitle: Renamed\n---\nBody").unwrap();
+        let file = fs::File::options()
+            .write(true)
+            .open(dir.join("b.md"))
+            .unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        let next = adjacent_pages(&first).next.unwrap();
+        assert_eq!(next.title, Some("Renamed".into()));
+
+        fs::write(dir.join("aa.md"), "---\ntitle: AA\n---\nBody").unwrap();
+        let next = adjacent_pages(&first).next.unwrap();
+        assert_eq!(next.title, Some("AA".into()));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
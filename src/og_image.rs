@@ -0,0 +1,510 @@
+use std::{collections::HashSet, fs, io, path::Path};
+
+use ab_glyph::{Font, FontRef, PxScaleFont, ScaleFont, point};
+
+use axum::{
+    Router,
+    extract::Path as UrlPath,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use lazy_static::lazy_static;
+use rayon::iter::ParallelIterator;
+use tiny_skia::{Color, Mask, Paint, Pixmap, Rect, Transform};
+use ulid::Ulid;
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::{
+    config::{Config, ConfigOgImage, ConfigOgLine, load_config},
+    page::Page,
+    search::sorted_tags,
+    web::{page_cache_control, with_last_modified},
+};
+
+/// Size of the preview images, the size most sites showing link previews
+/// expect.
+pub const OG_IMAGE_WIDTH: u32 = 1200;
+pub const OG_IMAGE_HEIGHT: u32 = 630;
+const PADDING: f32 = 80.0;
+const BAR_WIDTH: f32 = 24.0;
+/// Space between the blocks of text, in pixels.
+const BLOCK_GAP: f32 = 32.0;
+/// File in the image directory recording the settings the cached images
+/// were rendered with.
+const SETTINGS_FILE: &str = "settings.json";
+
+/// Serves generated Open Graph preview images of pages, when enabled with
+/// `og_image` in the config.
+pub fn og_image_routes() -> Router {
+    Router::new().route("/og/{*path}", get(og_image_handler))
+}
+
+/// Path of the preview image of `page`, without the base path.
+pub fn og_image_route(page: &Page) -> String {
+    format!("/og/{}.png", page.url.to_string_lossy())
+}
+
+async fn og_image_handler(UrlPath(path): UrlPath<String>) -> Result<Response, StatusCode> {
+    let config = load_config();
+    let og_image = config.og_image().clone().ok_or(StatusCode::NOT_FOUND)?;
+    let url = path
+        .strip_suffix(".png")
+        .ok_or(StatusCode::NOT_FOUND)?
+        .to_string();
+
+    let (page, png) = tokio::task::spawn_blocking(move || page_image(&config, &og_image, url))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)??;
+
+    let mut response = ([(header::CONTENT_TYPE, "image/png")], png).into_response();
+    if let Some(cache_control) = page_cache_control(&load_config(), page.draft) {
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, cache_control);
+    }
+    Ok(with_last_modified(response, page.modified))
+}
+
+/// Reads the page at `url` along with its preview image, rendering the image
+/// unless a card with the same text, by its [`card_hash`], is cached on disk.
+/// Images of other settings are pruned on start.
+fn page_image(
+    config: &Config,
+    og_image: &ConfigOgImage,
+    url: String,
+) -> Result<(Page, Vec<u8>), StatusCode> {
+    let page = Page::read(url).map_err(|_| StatusCode::NOT_FOUND)?;
+
+    let file = config
+        .og_image_path()
+        .join(format!("{}.png", card_hash(&page)));
+    if let Ok(png) = fs::read(&file) {
+        return Ok((page, png));
+    }
+
+    let png = render_card(&page, config.title(), og_image);
+    if let Err(error) = write_atomically(&file, &png) {
+        tracing::warn!(
+            "⚠️ Unable to cache preview image {}: {}",
+            file.to_string_lossy(),
+            error
+        );
+    }
+    Ok((page, png))
+}
+
+/// Removes the cached preview images no page has the card hash of any more, or all of them when the card settings changed since they were
+/// rendered, in the background.
+pub fn spawn_og_image_pruning(config: &Config) {
+    let Some(og_image) = config.og_image().clone() else {
+        return;
+    };
+    let directory = config.og_image_path();
+    let settings = card_settings(config, &og_image);
+    tokio::task::spawn_blocking(move || {
+        let hashes = Page::all().map(|page| card_hash(&page)).collect();
+        match prune(&directory, &settings, &hashes) {
+            Ok(0) => {}
+            Ok(removed) => tracing::info!("🖼️ Pruned {removed} outdated preview images"),
+            Err(error) => tracing::warn!(
+                "⚠️ Unable to prune preview images in {}: {}",
+                directory.to_string_lossy(),
+                error
+            ),
+        }
+    });
+}
+
+/// Hash of the page text a card can show, including tags and other defaults
+/// from the section of the page, as 16 lowercase hexadecimal digits.
+fn card_hash(page: &Page) -> String {
+    let text = serde_json::json!({
+        "title": page.title,
+        "description": page.description,
+        "tags": sorted_tags(&page.tags),
+        "author": page.author,
+    });
+    format!("{:016x}", xxh3_64(text.to_string().as_bytes()))
+}
+
+/// Everything besides the page that the look of a card depends on.
+fn card_settings(config: &Config, og_image: &ConfigOgImage) -> String {
+    serde_json::json!({ "site_title": config.title(), "og_image": og_image }).to_string()
+}
+
+/// Removes the images in `directory` not named by one of `hashes`, or all
+/// of them when they were rendered with other `settings`, returning how many
+/// were removed.
+fn prune(directory: &Path, settings: &str, hashes: &HashSet<String>) -> io::Result<usize> {
+    let settings_file = directory.join(SETTINGS_FILE);
+    let unchanged = fs::read_to_string(&settings_file).is_ok_and(|stored| stored == settings);
+    let mut removed = 0;
+    match fs::read_dir(directory) {
+        Ok(entries) => {
+            for entry in entries {
+                let path = entry?.path();
+                if path.extension().is_none_or(|extension| extension != "png") {
+                    continue;
+                }
+                let current = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .is_some_and(|stem| hashes.contains(stem));
+                if !(unchanged && current) {
+                    fs::remove_file(&path)?;
+                    removed += 1;
+                }
+            }
+        }
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => return Err(error),
+    }
+    if !unchanged {
+        write_atomically(&settings_file, settings.as_bytes())?;
+    }
+    Ok(removed)
+}
+
+/// Writes through a temporary file, so concurrent requests never read a
+/// partly written image.
+fn write_atomically(file: &Path, content: &[u8]) -> io::Result<()> {
+    if let Some(directory) = file.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    let temporary = file.with_extension(format!("{}.tmp", Ulid::new()));
+    fs::write(&temporary, content)?;
+    fs::rename(&temporary, file)
+}
+
+// Typefaces of the cards, bundled so that titles in any script covered by
+// DejaVu Sans render the same on every server.
+lazy_static! {
+    static ref REGULAR: FontRef<'static> =
+        FontRef::try_from_slice(include_bytes!("../fonts/DejaVuSans.ttf"))
+            .expect("the bundled font is valid");
+    static ref BOLD: FontRef<'static> =
+        FontRef::try_from_slice(include_bytes!("../fonts/DejaVuSans-Bold.ttf"))
+            .expect("the bundled font is valid");
+}
+
+/// Renders the preview card of `page` as a PNG image.
+fn render_card(page: &Page, site_title: &str, og_image: &ConfigOgImage) -> Vec<u8> {
+    draw_card(page, site_title, og_image)
+        .encode_png()
+        .expect("encoding to memory does not fail")
+}
+
+fn draw_card(page: &Page, site_title: &str, og_image: &ConfigOgImage) -> Pixmap {
+    let mut pixmap = Pixmap::new(OG_IMAGE_WIDTH, OG_IMAGE_HEIGHT).expect("the card size is valid");
+    let [red, green, blue] = *og_image.background();
+    pixmap.fill(Color::from_rgba8(red, green, blue, 255));
+    if let Some(bar) = Rect::from_xywh(0.0, 0.0, BAR_WIDTH, OG_IMAGE_HEIGHT as f32) {
+        pixmap.fill_rect(bar, &paint(*og_image.accent()), Transform::identity(), None);
+    }
+
+    let left = BAR_WIDTH + PADDING;
+    let max_width = OG_IMAGE_WIDTH as f32 - left - PADDING;
+    let bottom = OG_IMAGE_HEIGHT as f32 - PADDING;
+    let mut top = PADDING;
+    for block in og_image.lines() {
+        let (text, font, size, color, max_lines) = match block {
+            ConfigOgLine::SiteTitle => (site_title.to_string(), &*BOLD, 36.0, og_image.accent(), 1),
+            ConfigOgLine::Title => (
+                page.title.clone().unwrap_or_default(),
+                &*BOLD,
+                64.0,
+                og_image.foreground(),
+                3,
+            ),
+            ConfigOgLine::Description => (
+                page.description.clone().unwrap_or_default(),
+                &*REGULAR,
+                34.0,
+                og_image.foreground(),
+                3,
+            ),
+            ConfigOgLine::Tags => (
+                sorted_tags(&page.tags)
+                    .iter()
+                    .map(|tag| format!("#{tag}"))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                &*REGULAR,
+                30.0,
+                og_image.accent(),
+                2,
+            ),
+            ConfigOgLine::Author => (
+                page.author
+                    .as_ref()
+                    .map(|author| format!("by {author}"))
+                    .unwrap_or_default(),
+                &*REGULAR,
+                30.0,
+                og_image.foreground(),
+                1,
+            ),
+        };
+        if text.trim().is_empty() {
+            continue;
+        }
+
+        let font = font.as_scaled(size);
+        let line_height = font.height() + font.line_gap();
+        // The glyphs of a block are drawn as coverage into a mask, which is
+        // then filled with the block color in one go.
+        let mut mask = Mask::new(OG_IMAGE_WIDTH, OG_IMAGE_HEIGHT).expect("the card size is valid");
+        for line in wrap(&text, |line| text_width(&font, line), max_width, max_lines) {
+            if top + line_height > bottom {
+                break;
+            }
+            draw_text(&mut mask, &font, left, top + font.ascent(), &line);
+            top += line_height;
+        }
+        if let Some(card) = Rect::from_xywh(0.0, 0.0, OG_IMAGE_WIDTH as f32, OG_IMAGE_HEIGHT as f32)
+        {
+            pixmap.fill_rect(card, &paint(*color), Transform::identity(), Some(&mask));
+        }
+        top += BLOCK_GAP;
+    }
+
+    pixmap
+}
+
+fn paint(color: [u8; 3]) -> Paint<'static> {
+    let mut paint = Paint::default();
+    let [red, green, blue] = color;
+    paint.set_color_rgba8(red, green, blue, 255);
+    paint
+}
+
+/// Width of `text` set in `font`, in pixels.
+fn text_width(font: &PxScaleFont<&FontRef>, text: &str) -> f32 {
+    let mut width = 0.0;
+    let mut previous = None;
+    for c in text.chars() {
+        let id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            width += font.kern(previous, id);
+        }
+        width += font.h_advance(id);
+        previous = Some(id);
+    }
+    width
+}
+
+/// Adds the coverage of the glyphs of `text` to `mask`, starting at `x` on
+/// the baseline at `baseline`.
+fn draw_text(mask: &mut Mask, font: &PxScaleFont<&FontRef>, x: f32, baseline: f32, text: &str) {
+    let width = mask.width() as i64;
+    let height = mask.height() as i64;
+    let data = mask.data_mut();
+    let mut caret = x;
+    let mut previous = None;
+    for c in text.chars() {
+        let id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            caret += font.kern(previous, id);
+        }
+        let glyph = id.with_scale_and_position(font.scale(), point(caret, baseline));
+        caret += font.h_advance(id);
+        previous = Some(id);
+
+        let Some(outline) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outline.px_bounds();
+        outline.draw(|glyph_x, glyph_y, coverage| {
+            let x = bounds.min.x as i64 + glyph_x as i64;
+            let y = bounds.min.y as i64 + glyph_y as i64;
+            if (0..width).contains(&x) && (0..height).contains(&y) {
+                let alpha = &mut data[(y * width + x) as usize];
+                *alpha = (*alpha).max((coverage.clamp(0.0, 1.0) * 255.0) as u8);
+            }
+        });
+    }
+}
+
+/// Breaks `text` into at most `max_lines` lines no wider than `max_width` as
+/// measured by `width`, at spaces where possible, ending with an ellipsis
+/// when it does not fit.
+fn wrap(text: &str, width: impl Fn(&str) -> f32, max_width: f32, max_lines: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut word = word.to_string();
+        while !word.is_empty() {
+            let candidate = if line.is_empty() {
+                word.clone()
+            } else {
+                format!("{line} {word}")
+            };
+            if width(&candidate) <= max_width {
+                line = candidate;
+                break;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+                continue;
+            }
+            // A word wider than a line on its own is broken where it stops
+            // fitting, keeping at least one character per line.
+            let first = word.chars().next().map_or(word.len(), char::len_utf8);
+            let split = word
+                .char_indices()
+                .map(|(index, _)| index)
+                .skip(1)
+                .take_while(|index| width(&word[..*index]) <= max_width)
+                .last()
+                .unwrap_or(first);
+            let rest = word.split_off(split);
+            lines.push(word);
+            word = rest;
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        if let Some(last) = lines.last_mut() {
+            let mut kept = last.trim_end().to_string();
+            while !kept.is_empty() && width(&format!("{kept}…")) > max_width {
+                kept.pop();
+                kept.truncate(kept.trim_end().len());
+            }
+            *last = format!("{kept}…");
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use ab_glyph::GlyphId;
+    use time::OffsetDateTime;
+
+    use super::*;
+    use crate::section::SectionConfig;
+
+    #[test]
+    fn test_wrap() {
+        let chars = |line: &str| line.chars().count() as f32;
+        assert_eq!(wrap("A short title", chars, 20.0, 3), vec!["A short title"]);
+        assert_eq!(
+            wrap("Getting started with the website", chars, 12.0, 3),
+            vec!["Getting", "started with", "the website"]
+        );
+        assert_eq!(
+            wrap("Supercalifragilistic", chars, 8.0, 3),
+            vec!["Supercal", "ifragili", "stic"]
+        );
+        assert_eq!(
+            wrap("one two three four five six", chars, 9.0, 2),
+            vec!["one two", "three…"]
+        );
+        assert_eq!(wrap("Ωμέγα", chars, 2.0, 2), vec!["Ωμ", "έ…"]);
+        assert!(wrap("  ", chars, 10.0, 2).is_empty());
+    }
+
+    #[test]
+    fn test_fonts_cover_non_latin_text() {
+        for c in ['é', 'Ω', 'ж', '€', '—'] {
+            assert_ne!(REGULAR.glyph_id(c), GlyphId(0), "{c}");
+            assert_ne!(BOLD.glyph_id(c), GlyphId(0), "{c}");
+        }
+    }
+
+    #[test]
+    fn test_render_card() {
+        let page = Page::parse(
+            PathBuf::from("guides/start.md"),
+            "---\ntitle: A rather long title that needs wrapping over several lines to fit the card\ntags: [rust]\n---\nBody",
+            OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap();
+        let og_image: ConfigOgImage = toml::from_str("").unwrap();
+        let pixmap = draw_card(&page, "Welcome", &og_image);
+        assert_eq!((pixmap.width(), pixmap.height()), (1200, 630));
+
+        let rgb = |x, y| {
+            let pixel = pixmap.pixel(x, y).unwrap();
+            [pixel.red(), pixel.green(), pixel.blue()]
+        };
+        // The accent bar runs along the left edge, with text to the right of
+        // it and nothing in the padding.
+        assert_eq!(rgb(0, 0), *og_image.accent());
+        assert_eq!(rgb(30, 0), *og_image.background());
+        assert!((104..1120).any(|x| (80..300).any(|y| rgb(x, y) != *og_image.background())));
+        assert!((0..1200).all(|x| rgb(x, 629) == *og_image.background() || x < 24));
+
+        let png = render_card(&page, "Welcome", &og_image);
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    }
+
+    #[test]
+    fn test_prune() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["kept.png", "gone.png", "notes.txt"] {
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+        let hashes = HashSet::from(["kept".to_string()]);
+
+        // Images cached before the settings were recorded are all outdated.
+        assert_eq!(prune(dir.path(), "a", &hashes).unwrap(), 2);
+        fs::write(dir.path().join("kept.png"), "").unwrap();
+        fs::write(dir.path().join("gone.png"), "").unwrap();
+        assert_eq!(prune(dir.path(), "a", &hashes).unwrap(), 1);
+        assert!(dir.path().join("kept.png").exists());
+        assert!(!dir.path().join("gone.png").exists());
+        assert!(dir.path().join("notes.txt").exists());
+
+        assert_eq!(prune(dir.path(), "b", &hashes).unwrap(), 1);
+        assert!(!dir.path().join("kept.png").exists());
+        assert_eq!(
+            fs::read_to_string(dir.path().join(SETTINGS_FILE)).unwrap(),
+            "b"
+        );
+        assert_eq!(prune(&dir.path().join("missing"), "b", &hashes).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_card_hash_follows_section_tags() {
+        let page = |path: &str, section: &str| {
+            Page::parse_in_section(
+                PathBuf::from(path),
+                "---\ntitle: Start\n---\nBody",
+                OffsetDateTime::UNIX_EPOCH,
+                toml::from_str::<SectionConfig>(section).unwrap(),
+            )
+            .unwrap()
+        };
+
+        let blog = card_hash(&page("/pages/blog/start.md", "tags = [\"blog\"]"));
+        assert_eq!(blog.len(), 16);
+        assert_eq!(
+            blog,
+            card_hash(&page("/pages/news/start.md", "tags = [\"blog\"]"))
+        );
+        assert_ne!(
+            blog,
+            card_hash(&page("/pages/docs/start.md", "tags = [\"docs\"]"))
+        );
+        assert_ne!(blog, card_hash(&page("/pages/start.md", "")));
+    }
+
+    #[test]
+    fn test_og_image_route() {
+        let page = Page::parse(
+            PathBuf::from("guides/start.md"),
+            "# Start",
+            OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap();
+        assert_eq!(og_image_route(&page), "/og/guides/start.png");
+    }
+}
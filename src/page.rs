@@ -1,35 +1,126 @@
 use std::{
-    collections::HashSet,
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
     fs,
     path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+    time::SystemTime,
 };
 
 use jwalk::WalkDir;
-use pulldown_cmark::{Parser, html};
-use rayon::iter::{ParallelBridge, ParallelIterator};
+use lazy_static::lazy_static;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use scraper::{Html, Selector};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
 use thiserror::Error;
-use time::OffsetDateTime;
+use time::{Date, OffsetDateTime, macros::format_description};
 use ulid::Ulid;
 use xxhash_rust::xxh3::xxh3_128;
 
-use crate::config::load_config;
+use crate::{
+    config::{ConfigTagCase, load_config},
+    markdown,
+    section::SectionConfig,
+    websiteignore::is_ignored,
+};
+
+/// Reading speed assumed for [`Page::reading_minutes`].
+const WORDS_PER_MINUTE: usize = 200;
+
+lazy_static! {
+    /// Files of the pages with a `slug`, by the URL path it gives them, as
+    /// those can not be derived back from the URL.
+    static ref SLUGS: RwLock<HashMap<PathBuf, PathBuf>> = RwLock::new(HashMap::new());
+    /// The other way around, the URL paths of the pages with a `slug` by
+    /// their file, to redirect the URL path of the file to.
+    static ref SLUGGED_FILES: RwLock<HashMap<PathBuf, PathBuf>> = RwLock::new(HashMap::new());
+    /// The page roots, canonicalized once rather than on every page read.
+    static ref PAGE_ROOTS: RwLock<Option<Vec<PageRoot>>> = RwLock::new(None);
+    /// Stems of the markdown files in page directories, looked up for the
+    /// translations of a page on every request.
+    static ref MARKDOWN_STEMS: RwLock<HashMap<PathBuf, CachedStems>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Markdown file stems in a directory along with the modification time of the
+/// directory they were listed at, `None` for missing directories.
+type CachedStems = (Option<SystemTime>, Vec<String>);
 
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub struct Frontmatter {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<Ulid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
+    /// Shown after the title in the browser tab in place of the site title,
+    /// left out altogether when empty.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title_suffix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub tags: Option<HashSet<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// Short summary of the page, shown as the search excerpt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Summary of the page in feeds and listings, replacing its opening
+    /// words. The content before a `<!--more-->` line is used when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<String>,
+    /// Publication date, e.g. `2024-01-31`, a time of day is ignored.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_option_date",
+        serialize_with = "serialize_option_date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub date: Option<Date>,
+    /// Position among sibling pages, lower comes first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight: Option<i64>,
+    /// Robots meta directives, e.g. `noindex, nofollow`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub robots: Option<String>,
+    /// Drafts are still served by URL but left out of navigation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub draft: Option<bool>,
+    /// URL path within the pages directory or collection, e.g.
+    /// `guides/start`, replacing the one derived from the file path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slug: Option<String>,
+    /// Any fields not known above, passed through as is for client side use.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Debug)]
 pub struct Page {
     pub id: Ulid,
+    /// Whether the id was set in the frontmatter rather than derived.
+    pub explicit_id: bool,
     pub title: Option<String>,
+    /// Document title suffix from the frontmatter or the section, the site
+    /// title when `None`.
+    pub title_suffix: Option<String>,
     pub modified: OffsetDateTime,
     pub url: PathBuf,
     pub tags: HashSet<String>,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    /// Summary chosen by the author, as plain text, from the frontmatter or
+    /// the content before a `<!--more-->` line.
+    pub excerpt: Option<String>,
+    pub date: Option<Date>,
+    pub weight: Option<i64>,
+    pub robots: Option<String>,
+    pub draft: bool,
+    pub extra: Map<String, Value>,
+    /// Name of the configured collection the page belongs to, if any.
+    pub collection: Option<String>,
+    /// Settings from the `_section.toml` files above the page.
+    pub section: SectionConfig,
+    pub path: PathBuf,
     pub markdown: String,
     pub html: String,
 }
@@ -40,44 +131,139 @@ pub enum PageError {
     Io(#[from] std::io::Error),
     #[error("Toml error: {0}")]
     TomlSerialize(#[from] toml::ser::Error),
+    #[error("Toml error: {0}")]
+    TomlDeserialize(#[from] toml::de::Error),
     #[error("YAML error: {0}")]
     YAMLDeserialize(#[from] serde_yaml::Error),
+    #[error("File is {size} bytes, more than the limit of {limit} bytes")]
+    TooLarge { size: u64, limit: u64 },
+    #[error("File is not valid UTF-8")]
+    NotUtf8,
 }
 
 impl Page {
     pub fn read(path: impl Into<PathBuf>) -> Result<Self, PageError> {
+        let (path, content, modified) = Self::read_source(path)?;
+        let section = SectionConfig::for_page(&path);
+        Self::parse_in_section(path, &content, modified, section)
+    }
+
+    /// Reads the file behind a page URL or path, without parsing it, returning
+    /// the resolved path, content and modification time.
+    pub fn read_source(
+        path: impl Into<PathBuf>,
+    ) -> Result<(PathBuf, String, OffsetDateTime), PageError> {
         let path: PathBuf = path.into();
         let path = if path.extension().map(|p| p.to_str()) == Some(Some("md")) {
-            path
+            Self::get_page_file(&path)?
         } else {
             Self::get_full_path(path)?
         };
 
-        let content = fs::read_to_string(&path)?;
+        let content = read_content(&path, *load_config().max_page_bytes())?;
         let modified = fs::metadata(&path)?.modified()?;
 
-        let (frontmatter, markdown) = Self::split_frontmatter(&content)?;
+        Ok((path, content, OffsetDateTime::from(modified)))
+    }
+
+    /// Builds a page from the content of the markdown file at `path`.
+    pub fn parse(
+        path: PathBuf,
+        content: &str,
+        modified: OffsetDateTime,
+    ) -> Result<Self, PageError> {
+        Self::parse_in_section(path, content, modified, SectionConfig::default())
+    }
+
+    /// Like [`Page::parse`], with defaults from the section the page is in.
+    pub fn parse_in_section(
+        path: PathBuf,
+        content: &str,
+        modified: OffsetDateTime,
+        section: SectionConfig,
+    ) -> Result<Self, PageError> {
+        Self::parse_with(path, content, modified, section, true)
+    }
+
+    /// Like [`Page::parse_in_section`], leaving `html` empty. The markdown is
+    /// only rendered when the title has to be taken from its headings.
+    pub fn parse_metadata(
+        path: PathBuf,
+        content: &str,
+        modified: OffsetDateTime,
+        section: SectionConfig,
+    ) -> Result<Self, PageError> {
+        Self::parse_with(path, content, modified, section, false)
+    }
 
-        let html = Self::render_markdown(&markdown)?;
-        let url = Self::path_to_url(&path);
+    fn parse_with(
+        path: PathBuf,
+        content: &str,
+        modified: OffsetDateTime,
+        section: SectionConfig,
+        render_body: bool,
+    ) -> Result<Self, PageError> {
+        let (frontmatter, markdown) = Self::split_frontmatter(content)?;
+
+        let html = if render_body || frontmatter.title.is_none() {
+            Self::render_markdown(&markdown)?
+        } else {
+            String::new()
+        };
+        let root = PageRoot::for_path(&path);
+        let url = match frontmatter
+            .slug
+            .as_deref()
+            .and_then(|slug| normalize_slug(slug, &path))
+        {
+            Some(slug) => Self::slug_to_url(&slug, root.as_ref()),
+            None => Self::path_to_url(&path, root.as_ref()),
+        };
 
         let title = if frontmatter.title.is_some() {
             frontmatter.title
         } else {
             let document = Html::parse_document(&html);
             Self::extract_header_title(&document)
-        };
+        }
+        .and_then(|title| normalize_text(&title));
 
         Ok(Self {
-            title: title.clone(),
+            title,
+            title_suffix: frontmatter
+                .title_suffix
+                .or_else(|| section.title_suffix.clone()),
+            explicit_id: frontmatter.id.is_some(),
             id: frontmatter
                 .id
-                .unwrap_or_else(|| ulid_from_string(&title.unwrap_or_default())),
-            modified: OffsetDateTime::from(modified),
+                .unwrap_or_else(|| ulid_from_string(&url.to_string_lossy())),
+            modified,
             url,
-            tags: frontmatter.tags.unwrap_or_default(),
+            tags: frontmatter
+                .tags
+                .or_else(|| section.tags.clone())
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|tag| normalize_tag(tag, *load_config().tag_case()))
+                .collect(),
+            author: frontmatter.author,
+            description: frontmatter
+                .description
+                .filter(|description| !description.trim().is_empty()),
+            excerpt: frontmatter
+                .excerpt
+                .and_then(|excerpt| normalize_text(&excerpt))
+                .or_else(|| excerpt_before_more(&markdown)),
+            date: frontmatter.date,
+            weight: frontmatter.weight,
+            robots: frontmatter.robots,
+            draft: frontmatter.draft.unwrap_or(false),
+            extra: frontmatter.extra,
+            collection: root.and_then(|root| root.collection),
+            section,
+            path,
             markdown,
-            html,
+            html: if render_body { html } else { String::new() },
         })
     }
 
@@ -86,11 +272,22 @@ impl Page {
         let frontmatter = toml::to_string(&Frontmatter {
             id: Some(Ulid::new()),
             title: self.title.clone(),
+            title_suffix: self.title_suffix.clone(),
             tags: Some(self.tags.clone()),
+            author: self.author.clone(),
+            description: self.description.clone(),
+            excerpt: None,
+            date: self.date,
+            weight: self.weight,
+            robots: self.robots.clone(),
+            draft: Some(self.draft),
+            // Written at its URL path, the file needs no slug.
+            slug: None,
+            extra: self.extra.clone(),
         })?;
 
         let content = format!(
-            "---\n{}\n---\n{}",
+            "+++\n{}\n+++\n{}",
             ammonia::clean(&frontmatter),
             ammonia::clean(&self.markdown)
         );
@@ -98,26 +295,236 @@ impl Page {
         Ok(())
     }
 
-    pub fn all() -> impl ParallelIterator<Item = Self> {
-        let pages_root = load_config().pages_path();
-        WalkDir::new(pages_root)
-            .skip_hidden(true)
-            .follow_links(true)
-            .into_iter()
-            .par_bridge()
-            .filter_map(|dir_entry_result| {
-                let dir_entry = dir_entry_result.ok()?;
-                let path = dir_entry.path().canonicalize().ok()?;
+    /// Link to the page, including any configured base path. Directory
+    /// index pages link to their directory, e.g. `/docs/` for `docs/index.md`.
+    pub fn href(&self) -> String {
+        let config = load_config();
+        config.route(&self.link_path(config.directory_index()))
+    }
 
-                if !path.is_file() || path.extension() != Some("md".as_ref()) {
-                    return None;
+    /// Absolute URL path of the page, ending in `/` for the
+    /// `directory_index` file of a directory.
+    fn link_path(&self, directory_index: &str) -> String {
+        let is_directory_index = self
+            .path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy() == directory_index)
+            && self.url.file_stem() == Path::new(directory_index).file_stem();
+        match self.url.parent() {
+            Some(directory) if is_directory_index => {
+                let directory = directory.to_string_lossy();
+                if directory.is_empty() {
+                    "/".to_string()
+                } else {
+                    format!("/{directory}/")
                 }
+            }
+            _ => format!("/{}", self.url.to_string_lossy()),
+        }
+    }
 
-                Page::read(path).ok()
+    /// Whether search engines may index the page according to its robots
+    /// directives.
+    pub fn is_indexable(&self) -> bool {
+        !self.robots.as_ref().is_some_and(|robots| {
+            robots.split(',').any(|directive| {
+                matches!(
+                    directive.trim().to_ascii_lowercase().as_str(),
+                    "noindex" | "none"
+                )
             })
+        })
+    }
+
+    /// The page content as plain text, without markup.
+    pub fn plain_text(&self) -> String {
+        markdown::markdown_to_text(&self.markdown)
+    }
+
+    /// Minutes it takes to read the page, rounded up and at least one.
+    pub fn reading_minutes(&self) -> usize {
+        self.plain_text()
+            .split_whitespace()
+            .count()
+            .div_ceil(WORDS_PER_MINUTE)
+            .max(1)
     }
 
-    fn get_full_path(url_path: impl Into<PathBuf>) -> Result<PathBuf, PageError> {
+    /// The excerpt chosen by the author in full, or otherwise the first
+    /// `words` words of the description or content, as plain text ending in
+    /// an ellipsis when shortened.
+    pub fn excerpt(&self, words: usize) -> String {
+        if let Some(excerpt) = &self.excerpt {
+            return excerpt.clone();
+        }
+        let text = self
+            .description
+            .clone()
+            .unwrap_or_else(|| self.plain_text());
+        let mut rest = text.split_whitespace();
+        let mut excerpt = rest.by_ref().take(words).collect::<Vec<_>>().join(" ");
+        if rest.next().is_some() {
+            excerpt.push('…');
+        }
+        excerpt
+    }
+
+    /// Orders pages for reading, by `weight` with lower first and pages
+    /// without one last, then by title. Use it wherever pages are listed in
+    /// an order authors pick.
+    pub fn reading_order(&self, other: &Self) -> Ordering {
+        match (self.weight, other.weight) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+        .then_with(|| self.title.cmp(&other.title))
+    }
+
+    pub fn all() -> impl ParallelIterator<Item = Self> {
+        Self::read_all()
+            .filter_map(|(path, page)| page.map_err(|error| log_unreadable(&path, &error)).ok())
+    }
+
+    /// Like [`Page::all`], but also yields the files that could not be read.
+    pub fn read_all() -> impl ParallelIterator<Item = (PathBuf, Result<Self, PageError>)> {
+        page_files()
+            .into_par_iter()
+            .map(|path| (path.clone(), Page::read(path)))
+    }
+
+    /// Runs `read` on the blocking thread pool, so handlers going through
+    /// every page file do not hold up the async runtime while reading them.
+    pub async fn read_blocking<T: Send + 'static>(read: impl FnOnce() -> T + Send + 'static) -> T {
+        tokio::task::spawn_blocking(read)
+            .await
+            .unwrap_or_else(|error| std::panic::resume_unwind(error.into_panic()))
+    }
+
+    /// Rebuilds the lookup of pages by the URL their `slug` gives them from
+    /// the frontmatter of all pages. When several pages claim the same URL,
+    /// the first by file path keeps it.
+    pub fn index_slugs() {
+        let mut claims: Vec<(PathBuf, PathBuf)> = page_files()
+            .into_par_iter()
+            .filter_map(|path| {
+                let content = read_content(&path, *load_config().max_page_bytes()).ok()?;
+                let (frontmatter, _) = Self::split_frontmatter(&content).ok()?;
+                let slug = normalize_slug(frontmatter.slug.as_deref()?, &path)?;
+                let url = Self::slug_to_url(&slug, PageRoot::for_path(&path).as_ref());
+                Some((url, path))
+            })
+            .collect();
+        claims.sort_by(|a, b| a.1.cmp(&b.1));
+
+        let mut slugs: HashMap<PathBuf, PathBuf> = HashMap::with_capacity(claims.len());
+        for (url, path) in claims {
+            if let Some(first) = slugs.get(&url) {
+                tracing::warn!(
+                    "⚠️ Slug /{} of {} is already used by {}, ignoring it",
+                    url.to_string_lossy(),
+                    path.to_string_lossy(),
+                    first.to_string_lossy()
+                );
+            } else {
+                slugs.insert(url, path);
+            }
+        }
+
+        *SLUGGED_FILES.write().unwrap_or_else(|e| e.into_inner()) = slugs
+            .iter()
+            .map(|(url, path)| (path.clone(), url.clone()))
+            .collect();
+        *SLUGS.write().unwrap_or_else(|e| e.into_inner()) = slugs;
+    }
+
+    /// The URL path a page is served at instead of `url_path`, when that is
+    /// the path of a file whose `slug` gives it another URL.
+    pub fn slug_redirect(url_path: &str) -> Option<PathBuf> {
+        let slug_url = PathBuf::from(url_path.trim_matches('/'));
+        if SLUGS
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains_key(&slug_url)
+        {
+            return None;
+        }
+
+        let file_path = Self::get_file_path(url_path).ok()?;
+        SLUGGED_FILES
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&file_path)
+            .cloned()
+    }
+
+    pub fn get_full_path(url_path: impl Into<PathBuf>) -> Result<PathBuf, PageError> {
+        let url_path: PathBuf = url_path.into();
+        let slug_url = PathBuf::from(url_path.to_string_lossy().trim_matches('/'));
+        if let Some(path) = SLUGS
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&slug_url)
+        {
+            return Ok(path.clone());
+        }
+
+        Self::get_file_path(&url_path.to_string_lossy())
+    }
+
+    /// The file at `url_path` within its page root, ignoring slugs.
+    fn get_file_path(url_path: &str) -> Result<PathBuf, PageError> {
+        Self::get_file_path_in(PageRoot::all(), url_path)
+    }
+
+    fn get_file_path_in(roots: Vec<PageRoot>, url_path: &str) -> Result<PathBuf, PageError> {
+        let (root, url_path) = PageRoot::route(roots.clone(), url_path);
+        let path = Self::url_to_file_path(url_path);
+
+        let file_path = fs::canonicalize(root.path.join(&path))?;
+
+        // Files of a collection inside the pages directory are only served
+        // below the prefix of the collection.
+        if PageRoot::containing(&roots, &file_path) != Some(&root) {
+            return Err(PageError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Path is outside data root",
+            )));
+        }
+
+        if is_ignored(&root.path, &file_path, false) {
+            return Err(PageError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Path is ignored",
+            )));
+        }
+
+        Ok(file_path)
+    }
+
+    /// The canonical path of the markdown file at `path`, as long as it is
+    /// a page in one of the page roots.
+    fn get_page_file(path: &Path) -> Result<PathBuf, PageError> {
+        let file_path = fs::canonicalize(path)?;
+        let roots = PageRoot::all();
+        match PageRoot::containing(&roots, &file_path) {
+            Some(root) if !is_ignored(&root.path, &file_path, false) => Ok(file_path),
+            _ => Err(PageError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Path is not a page",
+            ))),
+        }
+    }
+
+    /// Maps a URL path to the markdown file path relative to the pages root.
+    pub fn url_to_file_path(url_path: impl Into<PathBuf>) -> String {
+        Self::url_to_file_path_in(url_path, load_config().directory_index())
+    }
+
+    /// Like [`Page::url_to_file_path`], with paths ending in `/` mapped to
+    /// the `directory_index` file in that directory.
+    fn url_to_file_path_in(url_path: impl Into<PathBuf>, directory_index: &str) -> String {
         let path: PathBuf = url_path.into();
         let mut path = path.to_string_lossy().to_string();
 
@@ -126,47 +533,107 @@ impl Page {
         }
 
         if path.ends_with("/") {
-            path.push_str("index.md");
+            path.push_str(directory_index);
         } else if !path.ends_with(".md") {
             path.push_str(".md");
         }
-        path = path
-            .strip_prefix("/")
+
+        path.strip_prefix("/")
             .map(|p| p.into())
-            .unwrap_or(path.clone());
+            .unwrap_or(path.clone())
+    }
 
-        let config = load_config();
-        let pages_root = config.pages_path();
-        let file_path = fs::canonicalize(pages_root.join(&path))?;
+    /// The language versions of the page at the URL path `url_path`, sorted
+    /// by language. Translations are named after the page file with a
+    /// language tag before the extension, so `about.sv.md` translates
+    /// `about.md`, which is in `default_language`. Files are only taken as
+    /// translations next to that untranslated page, so that names like
+    /// `node.js.md` stay pages of their own. Empty when the page has no
+    /// translations.
+    pub fn translations(url_path: &str, default_language: &str) -> Vec<Translation> {
+        let (root, url_path) = PageRoot::for_url(url_path);
+        let file = root.path.join(Self::url_to_file_path(url_path));
+        Self::translations_in(&root, &file, default_language)
+    }
 
-        if !file_path.starts_with(&pages_root) {
-            return Err(PageError::Io(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "Path is outside data root",
-            )));
+    /// Like [`Page::translations`], for the page file `file` below `root`,
+    /// whether or not the file itself exists.
+    fn translations_in(root: &PageRoot, file: &Path, default_language: &str) -> Vec<Translation> {
+        let (Some(dir), Some(stem)) = (file.parent(), file.file_stem()) else {
+            return Vec::new();
+        };
+        let stem = stem.to_string_lossy();
+        let name = split_language(&stem).map_or(&*stem, |(name, _)| name);
+
+        let stems = markdown_stems(dir);
+        if !stems.iter().any(|stem| stem == name) {
+            return Vec::new();
         }
+        let mut translations: Vec<Translation> = stems
+            .iter()
+            .filter_map(|stem| {
+                let language = match split_language(stem) {
+                    Some((stem_name, language)) if stem_name == name => language,
+                    None if stem == name => default_language,
+                    _ => return None,
+                };
+                let path = dir.join(format!("{stem}.md"));
+                (!is_ignored(&root.path, &path, false)).then(|| Translation {
+                    language: language.to_string(),
+                    url: Self::path_to_url(&path, Some(root))
+                        .to_string_lossy()
+                        .into_owned(),
+                })
+            })
+            .collect();
 
-        Ok(file_path)
+        if translations
+            .iter()
+            .all(|translation| translation.language == default_language)
+        {
+            return Vec::new();
+        }
+        translations.sort_by(|a, b| a.language.cmp(&b.language));
+        translations
     }
 
+    /// Picks the first `h1`, otherwise the first heading of any level and only
+    /// then the first paragraph. Elements inside code blocks are ignored.
     fn extract_header_title(document: &Html) -> Option<String> {
-        let selector = Selector::parse("h1,h2,h3,h4,h5,h6,p").unwrap();
-        document
-            .select(&selector)
-            .next()
-            .map(|h1| h1.text().collect::<String>())
-            .map(|s| s.trim().to_string())
+        ["h1", "h1,h2,h3,h4,h5,h6", "p"]
+            .into_iter()
+            .find_map(|selector| {
+                let selector = Selector::parse(selector).unwrap();
+                document
+                    .select(&selector)
+                    .filter(|element| {
+                        !element.ancestors().any(|node| {
+                            node.value()
+                                .as_element()
+                                .is_some_and(|element| element.name() == "pre")
+                        })
+                    })
+                    .find_map(|element| normalize_text(&element.text().collect::<String>()))
+            })
     }
 
-    fn split_frontmatter(content: &str) -> Result<(Frontmatter, String), PageError> {
+    /// Splits a page into frontmatter and markdown body.
+    ///
+    /// Frontmatter is recognized when the first line, ignoring a leading UTF-8
+    /// byte order mark and trailing whitespace, is exactly `---` (YAML) or
+    /// `+++` (TOML). It ends at the next line that, ignoring trailing
+    /// whitespace, is the same fence. Anything else is all body.
+    pub fn split_frontmatter(content: &str) -> Result<(Frontmatter, String), PageError> {
+        let content = content.strip_prefix('\u{feff}').unwrap_or(content);
         let mut lines = content.lines();
-        if lines.next() != Some("---") {
-            return Ok((Frontmatter::default(), content.to_string()));
-        }
+        let fence = match lines.next().map(str::trim_end) {
+            Some(fence @ ("---" | "+++")) => fence,
+            _ => return Ok((Frontmatter::default(), content.to_string())),
+        };
 
         let mut frontmatter = String::new();
         for line in lines.by_ref() {
-            if line == "---" {
+            if line.trim_end() == fence {
                 break;
             }
             frontmatter.push_str(line);
@@ -182,28 +649,377 @@ impl Page {
             .trim()
             .to_string();
 
-        let frontmatter: Frontmatter = serde_yaml::from_str(&frontmatter)?;
+        let frontmatter: Frontmatter = if frontmatter.trim().is_empty() {
+            Frontmatter::default()
+        } else if fence == "+++" {
+            toml::from_str(&frontmatter)?
+        } else {
+            serde_yaml::from_str(&frontmatter)?
+        };
 
         Ok((frontmatter, markdown))
     }
 
     fn render_markdown(markdown: &str) -> Result<String, PageError> {
-        let parser = Parser::new(markdown);
-        let mut html = String::new();
-        html::push_html(&mut html, parser);
-        let html = ammonia::clean(&html);
-        Ok(html.trim().to_string())
+        Ok(markdown::render_markdown(markdown, &load_config()))
+    }
+
+    fn slug_to_url(slug: &str, root: Option<&PageRoot>) -> PathBuf {
+        match root {
+            Some(root) => Path::new(&root.prefix).join(slug),
+            None => PathBuf::from(slug),
+        }
+    }
+
+    fn path_to_url(path: &Path, root: Option<&PageRoot>) -> PathBuf {
+        match root {
+            Some(root) => Path::new(&root.prefix)
+                .join(path.strip_prefix(&root.path).unwrap_or(path))
+                .with_extension(""),
+            None => path.with_extension(""),
+        }
+    }
+}
+
+/// A language version of a page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Translation {
+    /// Language tag as written in the file name, e.g. `sv` or `pt-BR`.
+    pub language: String,
+    /// URL path of the version, without a leading slash.
+    pub url: String,
+}
+
+impl Translation {
+    /// URL path of the page without a language, served in the language the
+    /// visitor prefers.
+    pub fn negotiated_url(&self) -> String {
+        match self.url.rsplit_once('/') {
+            Some((dir, stem)) => {
+                format!(
+                    "{dir}/{}",
+                    split_language(stem).map_or(stem, |(name, _)| name)
+                )
+            }
+            None => split_language(&self.url)
+                .map_or(&*self.url, |(name, _)| name)
+                .to_string(),
+        }
+    }
+}
+
+/// A directory of pages along with the URL prefix it is served below, empty
+/// for the pages directory and the prefix of the collection otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRoot {
+    /// Canonical path of the directory.
+    pub path: PathBuf,
+    pub prefix: String,
+    /// Name of the collection, `None` for the pages directory.
+    pub collection: Option<String>,
+}
+
+impl PageRoot {
+    /// The pages directory followed by the configured collections, resolved
+    /// once all of their directories exist.
+    pub fn all() -> Vec<Self> {
+        if let Some(roots) = &*PAGE_ROOTS.read().unwrap_or_else(|e| e.into_inner()) {
+            return roots.clone();
+        }
+
+        match Self::resolve(|path| path.canonicalize().ok()) {
+            Some(roots) => {
+                *PAGE_ROOTS.write().unwrap_or_else(|e| e.into_inner()) = Some(roots.clone());
+                roots
+            }
+            // Missing directories can not be canonicalized yet, so their
+            // paths are used as configured until they are created.
+            None => Self::resolve(|path| Some(path.canonicalize().unwrap_or(path)))
+                .expect("every path is kept"),
+        }
     }
 
-    fn path_to_url(path: &Path) -> PathBuf {
+    fn resolve(canonical: impl Fn(PathBuf) -> Option<PathBuf>) -> Option<Vec<Self>> {
         let config = load_config();
-        path.strip_prefix(config.pages_path())
-            .unwrap_or(path)
-            .with_extension("")
-            .to_path_buf()
+        std::iter::once(canonical(config.pages_path()).map(|path| Self {
+            path,
+            prefix: String::new(),
+            collection: None,
+        }))
+        .chain(config.collections().iter().map(|collection| {
+            canonical(config.collection_path(collection)).map(|path| Self {
+                path,
+                prefix: collection.prefix().clone(),
+                collection: Some(collection.name().clone()),
+            })
+        }))
+        .collect()
+    }
+
+    /// The root holding the file at `path`.
+    pub fn for_path(path: &Path) -> Option<Self> {
+        Self::containing(&Self::all(), path).cloned()
+    }
+
+    /// The innermost of `roots` holding `path`, as collections may be placed
+    /// inside the pages directory.
+    pub fn containing<'a>(roots: &'a [Self], path: &Path) -> Option<&'a Self> {
+        roots
+            .iter()
+            .filter(|root| path.starts_with(&root.path))
+            .max_by_key(|root| root.path.components().count())
+    }
+
+    /// The root serving the URL path `url`, along with the rest of the URL
+    /// path within it.
+    pub fn for_url(url: &str) -> (Self, String) {
+        Self::route(Self::all(), url)
+    }
+
+    fn route(roots: Vec<Self>, url: &str) -> (Self, String) {
+        let url = url.trim_start_matches('/');
+        let mut roots = roots.into_iter();
+        let pages = roots.next().expect("the pages directory is always a root");
+        roots
+            .find_map(|root| {
+                let rest = url.strip_prefix(root.prefix.as_str())?;
+                let rest = match rest.strip_prefix('/') {
+                    Some(rest) => format!("/{rest}"),
+                    None if rest.is_empty() => "/".to_string(),
+                    None => return None,
+                };
+                Some((root, rest))
+            })
+            .unwrap_or((pages, format!("/{url}")))
+    }
+}
+
+/// The markdown files of all page roots, each listed under the innermost root
+/// holding it.
+///
+/// The walk runs on the rayon pool itself, so the files are listed up front
+/// rather than bridged into a parallel iterator, whose workers would leave
+/// the walk without threads on single core machines.
+fn page_files() -> Vec<PathBuf> {
+    let roots = PageRoot::all();
+    roots
+        .iter()
+        .flat_map(|root| {
+            // A collection inside another root only belongs to itself.
+            markdown_files(root.path.clone()).filter(|path| {
+                PageRoot::containing(&roots, path).is_some_and(|owner| owner.path == root.path)
+            })
+        })
+        .collect()
+}
+
+/// A slug without surrounding slashes, or `None` with a warning when it is
+/// empty or has `.` or `..` segments.
+fn normalize_slug(slug: &str, path: &Path) -> Option<String> {
+    let slug = slug.trim().trim_matches('/');
+    let valid = slug
+        .split('/')
+        .all(|segment| !matches!(segment, "" | "." | ".."));
+    if !valid {
+        tracing::warn!(
+            "⚠️ Ignoring invalid slug {:?} in {}",
+            slug,
+            path.to_string_lossy()
+        );
+        return None;
+    }
+    Some(slug.to_string())
+}
+
+/// A date as written in YAML, as a string, or TOML, as a native date.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DateInput {
+    Text(String),
+    Toml(toml::value::Datetime),
+}
+
+fn deserialize_option_date<'de, D>(deserializer: D) -> Result<Option<Date>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = match Option::<DateInput>::deserialize(deserializer)? {
+        Some(DateInput::Text(text)) => text,
+        Some(DateInput::Toml(datetime)) => datetime.to_string(),
+        None => return Ok(None),
+    };
+    parse_date(&text)
+        .map(Some)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid date {text:?}")))
+}
+
+fn serialize_option_date<S>(date: &Option<Date>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match date.and_then(|date| {
+        date.format(format_description!("[year]-[month]-[day]"))
+            .ok()
+    }) {
+        Some(date) => serializer.serialize_str(&date),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Parses the `YYYY-MM-DD` date at the start of a date or date-time.
+fn parse_date(text: &str) -> Option<Date> {
+    let text = text.trim();
+    let date = &text[..text.find(['T', 't', ' ']).unwrap_or(text.len())];
+    Date::parse(date, format_description!("[year]-[month]-[day]")).ok()
+}
+
+/// Reads a page file, refusing files above `max_bytes` or that are not UTF-8.
+fn read_content(path: &Path, max_bytes: u64) -> Result<String, PageError> {
+    let size = fs::metadata(path)?.len();
+    if size > max_bytes {
+        return Err(PageError::TooLarge {
+            size,
+            limit: max_bytes,
+        });
+    }
+
+    String::from_utf8(fs::read(path)?).map_err(|_| PageError::NotUtf8)
+}
+
+/// Logs why a page file is left out, oversized files being an expected
+/// configuration choice rather than an error.
+pub fn log_unreadable(path: &Path, error: &PageError) {
+    match error {
+        PageError::TooLarge { .. } => {
+            tracing::warn!("⚠️ Skipping {}: {}", path.to_string_lossy(), error)
+        }
+        _ => tracing::error!("💥 Skipping {}: {}", path.to_string_lossy(), error),
+    }
+}
+
+/// Canonical paths of the markdown files below `root`, which should itself be
+/// canonical. Symlinks are followed, but each directory is only read once so
+/// cycles and repeated links end, and anything resolving outside `root` is
+/// skipped.
+fn markdown_files(root: PathBuf) -> impl Iterator<Item = PathBuf> {
+    let visited = Arc::new(Mutex::new(HashSet::new()));
+    let walk_root = root.clone();
+
+    WalkDir::new(&root)
+        .skip_hidden(true)
+        .follow_links(true)
+        .process_read_dir(move |_, _, _, children| {
+            for child in children.iter_mut().flatten() {
+                if child.read_children_path.is_none() {
+                    continue;
+                }
+                let is_new_dir = child.path().canonicalize().is_ok_and(|dir| {
+                    dir.starts_with(&walk_root)
+                        && !is_ignored(&walk_root, &dir, true)
+                        && visited
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .insert(dir)
+                });
+                if !is_new_dir {
+                    child.read_children_path = None;
+                }
+            }
+        })
+        .into_iter()
+        .filter_map(move |dir_entry_result| {
+            let path = dir_entry_result.ok()?.path().canonicalize().ok()?;
+            (path.starts_with(&root)
+                && path.is_file()
+                && path.extension() == Some("md".as_ref())
+                && !is_ignored(&root, &path, false))
+            .then_some(path)
+        })
+}
+
+/// Stems of the markdown files in `dir`, listed again only once the directory
+/// was modified, as adding, removing or renaming files does.
+fn markdown_stems(dir: &Path) -> Vec<String> {
+    let modified = fs::metadata(dir).and_then(|m| m.modified()).ok();
+    if let Some((cached_modified, stems)) = MARKDOWN_STEMS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(dir)
+    {
+        if *cached_modified == modified {
+            return stems.clone();
+        }
     }
+
+    let stems: Vec<String> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension() != Some("md".as_ref()) || !path.is_file() {
+                return None;
+            }
+            Some(path.file_stem()?.to_string_lossy().into_owned())
+        })
+        .collect();
+    MARKDOWN_STEMS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(dir.to_path_buf(), (modified, stems.clone()));
+    stems
+}
+
+/// Splits a file stem like `about.sv` into the page name and the language tag
+/// it ends with, `None` when it does not end with one.
+pub fn split_language(stem: &str) -> Option<(&str, &str)> {
+    stem.rsplit_once('.')
+        .filter(|(name, language)| !name.is_empty() && is_language_tag(language))
+}
+
+/// Whether `tag` is a two letter ISO 639-1 language code, optionally followed
+/// by a region or other subtags, such as `sv`, `pt-BR` or `zh-Hant`.
+pub fn is_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    subtags.next().is_some_and(|language| {
+        language.len() == 2 && language.bytes().all(|b| b.is_ascii_alphabetic())
+    }) && subtags.all(|subtag| {
+        (2..=8).contains(&subtag.len()) && subtag.bytes().all(|b| b.is_ascii_alphanumeric())
+    })
+}
+
+/// Trims text, such as a title, and collapses internal whitespace, including
+/// newlines, to single spaces. Blank text becomes `None`.
+fn normalize_text(text: &str) -> Option<String> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+/// Trims and collapses the whitespace in a tag, lowercasing it unless the
+/// case is preserved. Blank tags become `None`.
+pub fn normalize_tag(tag: &str, case: ConfigTagCase) -> Option<String> {
+    normalize_text(tag).map(|tag| match case {
+        ConfigTagCase::Lowercase => tag.to_lowercase(),
+        ConfigTagCase::Preserve => tag,
+    })
 }
 
+/// The content before a `<!--more-->` line as plain text without its
+/// headings, `None` without such a line or when nothing else comes before it.
+fn excerpt_before_more(markdown: &str) -> Option<String> {
+    let mut before = String::new();
+    for line in markdown.lines() {
+        let marker: String = line.split_whitespace().collect();
+        if marker.eq_ignore_ascii_case("<!--more-->") {
+            return normalize_text(&markdown::markdown_to_body_text(&before));
+        }
+        before.push_str(line);
+        before.push('\n');
+    }
+    None
+}
+
+/// Derives a stable id, used for pages without a frontmatter `id` from their
+/// URL path, which unlike the title is unique per file.
 fn ulid_from_string(input: &str) -> Ulid {
     let hash = xxh3_128(input.as_bytes());
     Ulid::from_parts(0, hash)
@@ -213,6 +1029,94 @@ fn ulid_from_string(input: &str) -> Ulid {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_derived_ids_follow_the_path() {
+        let page = |path: &str, content: &str| {
+            Page::parse(PathBuf::from(path), content, OffsetDateTime::UNIX_EPOCH).unwrap()
+        };
+
+        let first = page("/pages/a/intro.md", "# Intro");
+        let second = page("/pages/b/intro.md", "# Intro");
+        assert!(!first.explicit_id);
+        assert_ne!(first.id, second.id);
+        assert_ne!(page("/pages/a.md", "").id, page("/pages/b.md", "").id);
+        assert_eq!(first.id, page("/pages/a/intro.md", "# Renamed").id);
+
+        let id = Ulid::new();
+        let explicit = page("/pages/a/intro.md", &format!("---\nid: {id}\n---\n# Intro"));
+        assert!(explicit.explicit_id);
+        assert_eq!(explicit.id, id);
+    }
+
+    #[test]
+    fn test_page_roots_route_by_prefix() {
+        let root = |path: &str, prefix: &str| PageRoot {
+            path: path.into(),
+            prefix: prefix.into(),
+            collection: (!prefix.is_empty()).then(|| prefix.to_string()),
+        };
+        let roots = vec![
+            root("/srv/pages", ""),
+            root("/srv/docs", "docs"),
+            root("/srv/pages/blog", "blog"),
+        ];
+
+        let route = |url: &str| {
+            let (root, rest) = PageRoot::route(roots.clone(), url);
+            (root.prefix, rest)
+        };
+        assert_eq!(route("/docs/setup"), ("docs".into(), "/setup".into()));
+        assert_eq!(route("/docs"), ("docs".into(), "/".into()));
+        assert_eq!(route("/docs/"), ("docs".into(), "/".into()));
+        assert_eq!(route("/docsets/a"), ("".into(), "/docsets/a".into()));
+        assert_eq!(route("/"), ("".into(), "/".into()));
+
+        let owner = |path: &str| {
+            PageRoot::containing(&roots, Path::new(path)).map(|root| root.prefix.clone())
+        };
+        assert_eq!(owner("/srv/pages/blog/post.md"), Some("blog".into()));
+        assert_eq!(owner("/srv/pages/about.md"), Some("".into()));
+        assert_eq!(owner("/elsewhere/a.md"), None);
+
+        assert_eq!(
+            Page::path_to_url(Path::new("/srv/docs/setup/intro.md"), Some(&roots[1])),
+            PathBuf::from("docs/setup/intro")
+        );
+        assert_eq!(
+            Page::path_to_url(Path::new("/srv/pages/about.md"), Some(&roots[0])),
+            PathBuf::from("about")
+        );
+    }
+
+    #[test]
+    fn test_files_are_only_served_from_their_own_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let pages = dir.path().canonicalize().unwrap().join("pages");
+        fs::create_dir_all(pages.join("journal")).unwrap();
+        fs::write(pages.join("about.md"), "# About").unwrap();
+        fs::write(pages.join("journal/entry.md"), "# Entry").unwrap();
+        let roots = vec![
+            PageRoot {
+                path: pages.clone(),
+                prefix: String::new(),
+                collection: None,
+            },
+            PageRoot {
+                path: pages.join("journal"),
+                prefix: "blog".into(),
+                collection: Some("blog".into()),
+            },
+        ];
+
+        let file = |url: &str| Page::get_file_path_in(roots.clone(), url).ok();
+        assert_eq!(file("/about"), Some(pages.join("about.md")));
+        assert_eq!(file("/blog/entry"), Some(pages.join("journal/entry.md")));
+        assert_eq!(file("/journal/entry"), None);
+
+        // Markdown files are only read by path from within the page roots.
+        assert!(Page::read_source(pages.join("about.md")).is_err());
+    }
+
     #[test]
     fn test_frontmatter_parsing() {
         let content = r#"---
@@ -233,10 +1137,391 @@ Some other text
         assert_eq!(md.trim(), "# Content\n\nSome other text");
     }
 
+    #[test]
+    fn test_frontmatter_extra_fields() {
+        let content = r#"---
+title: "Test Page"
+layout: "wide"
+cover:
+  src: "/cover.png"
+---
+# Content
+"#;
+
+        let (fm, _) = Page::split_frontmatter(content).unwrap();
+        assert_eq!(fm.title, Some("Test Page".into()));
+        assert_eq!(fm.extra.get("layout"), Some(&Value::from("wide")));
+        assert_eq!(
+            fm.extra.get("cover").and_then(|cover| cover.get("src")),
+            Some(&Value::from("/cover.png"))
+        );
+        assert!(!fm.extra.contains_key("title"));
+    }
+
+    #[test]
+    fn test_frontmatter_dates() {
+        let date = |content: &str| Page::split_frontmatter(content).map(|(fm, _)| fm.date);
+        let expected = Some(time::macros::date!(2024 - 01 - 31));
+
+        assert_eq!(date("---\ndate: 2024-01-31\n---\n").unwrap(), expected);
+        assert_eq!(
+            date("---\ndate: \"2024-01-31T10:00:00Z\"\n---\n").unwrap(),
+            expected
+        );
+        assert_eq!(date("+++\ndate = 2024-01-31\n+++\n").unwrap(), expected);
+        assert_eq!(
+            date("+++\ndate = 2024-01-31T10:00:00+02:00\n+++\n").unwrap(),
+            expected
+        );
+        assert_eq!(date("---\ntitle: Undated\n---\n").unwrap(), None);
+        assert!(date("---\ndate: 31/01/2024\n---\n").is_err());
+
+        let frontmatter = Frontmatter {
+            date: expected,
+            ..Default::default()
+        };
+        assert_eq!(
+            toml::to_string(&frontmatter).unwrap().trim(),
+            r#"date = "2024-01-31""#
+        );
+    }
+
+    #[test]
+    fn test_slug_overrides_url() {
+        let page = |path: &str, slug: &str| {
+            Page::parse(
+                path.into(),
+                &format!("---\nslug: {slug}\n---\n# Page"),
+                OffsetDateTime::UNIX_EPOCH,
+            )
+            .unwrap()
+        };
+
+        let original = page("/pages/2024-draft-name.md", "/guides/start/");
+        let renamed = page("/pages/getting-started.md", "guides/start");
+        assert_eq!(original.url, PathBuf::from("guides/start"));
+        assert_eq!(original.id, renamed.id);
+
+        for invalid in ["../outside", "guides//start", "\"/\""] {
+            let page = page("/pages/named.md", invalid);
+            assert_eq!(page.url, PathBuf::from("/pages/named"), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_excerpt_from_marker_and_frontmatter() {
+        let page = |content: &str| {
+            Page::parse(
+                PathBuf::from("/pages/post.md"),
+                content,
+                OffsetDateTime::UNIX_EPOCH,
+            )
+            .unwrap()
+        };
+
+        let marked = page("# Post\n\nThe *short* version.\n\n<!-- more -->\n\nThe long version.");
+        assert_eq!(marked.excerpt.as_deref(), Some("The short version."));
+        assert_eq!(marked.excerpt(2), "The short version.");
+        assert!(!marked.html.contains("more"));
+
+        let explicit =
+            page("---\nexcerpt: |\n  Chosen\n  words\n---\nFirst part\n<!--more-->\nRest");
+        assert_eq!(explicit.excerpt.as_deref(), Some("Chosen words"));
+
+        let plain = page("---\ndescription: Described\n---\nNo marker here");
+        assert_eq!(plain.excerpt, None);
+        assert_eq!(plain.excerpt(20), "Described");
+        assert_eq!(page("<!--more-->\nRest").excerpt, None);
+    }
+
+    #[test]
+    fn test_tags_are_normalized() {
+        let page = Page::parse(
+            PathBuf::from("/pages/post.md"),
+            "---\ntags: [Rust, \" rust \", \"Getting\\tStarted \", \"getting  started\", \" \", Ünicode]\n---\nBody",
+            OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap();
+        assert_eq!(
+            page.tags,
+            HashSet::from(["rust".into(), "getting started".into(), "ünicode".into()])
+        );
+
+        assert_eq!(
+            normalize_tag("  Getting \n Started ", ConfigTagCase::Preserve),
+            Some("Getting Started".into())
+        );
+        assert_eq!(normalize_tag("  ", ConfigTagCase::Lowercase), None);
+    }
+
+    #[test]
+    fn test_custom_directory_index() {
+        assert_eq!(Page::url_to_file_path_in("/", "index.md"), "index.md");
+        assert_eq!(
+            Page::url_to_file_path_in("docs/", "README.md"),
+            "docs/README.md"
+        );
+        assert_eq!(Page::url_to_file_path_in("/", "_index.md"), "_index.md");
+        assert_eq!(
+            Page::url_to_file_path_in("docs/setup", "README.md"),
+            "docs/setup.md"
+        );
+
+        let page = |path: &str| {
+            Page::parse(PathBuf::from(path), "# Page", OffsetDateTime::UNIX_EPOCH).unwrap()
+        };
+        assert_eq!(page("docs/README.md").link_path("README.md"), "/docs/");
+        assert_eq!(page("README.md").link_path("README.md"), "/");
+        assert_eq!(page("docs/README.md").link_path("index.md"), "/docs/README");
+        assert_eq!(page("docs/index.md").link_path("index.md"), "/docs/");
+        assert_eq!(page("docs/setup.md").link_path("README.md"), "/docs/setup");
+    }
+
+    #[test]
+    fn test_metadata_parsing_skips_the_body() {
+        let parse = |content: &str| {
+            Page::parse_metadata(
+                "/pages/notes.md".into(),
+                content,
+                OffsetDateTime::UNIX_EPOCH,
+                SectionConfig::default(),
+            )
+            .unwrap()
+        };
+
+        let page = parse("---\ntitle: Notes\n---\n# Heading\n\nBody");
+        assert_eq!(page.title.as_deref(), Some("Notes"));
+        assert_eq!(page.html, "");
+        let page = parse("# Heading\n\nBody");
+        assert_eq!(page.title.as_deref(), Some("Heading"));
+        assert_eq!(page.html, "");
+
+        assert_eq!(page.reading_minutes(), 1);
+        let page = parse(&"word ".repeat(401));
+        assert_eq!(page.reading_minutes(), 3);
+    }
+
+    #[test]
+    fn test_toml_frontmatter_parsing() {
+        let content = r#"+++
+title = "Test Page"
+tags = ["rust", "axum"]
++++
+# Content
+"#;
+
+        let (fm, md) = Page::split_frontmatter(content).unwrap();
+        assert_eq!(fm.title, Some("Test Page".into()));
+        assert_eq!(
+            fm.tags.unwrap(),
+            HashSet::from(["rust".into(), "axum".into()])
+        );
+        assert_eq!(md, "# Content");
+    }
+
+    #[test]
+    fn test_empty_frontmatter_parsing() {
+        for content in ["---\n---\n# Content\n", "+++\n+++\n# Content\n"] {
+            let (fm, md) = Page::split_frontmatter(content).unwrap();
+            assert_eq!(fm.title, None);
+            assert_eq!(fm.tags, None);
+            assert_eq!(md, "# Content");
+        }
+    }
+
+    #[test]
+    fn test_bom_prefixed_frontmatter_parsing() {
+        let content = "\u{feff}---\ntitle: Test Page\n---\n# Content\n";
+        let (fm, md) = Page::split_frontmatter(content).unwrap();
+        assert_eq!(fm.title, Some("Test Page".into()));
+        assert_eq!(md, "# Content");
+    }
+
+    #[test]
+    fn test_crlf_frontmatter_parsing() {
+        let content = "---\r\ntitle: Test Page\r\ntags: [a]\r\n---\r\n# Content\r\n";
+        let (fm, md) = Page::split_frontmatter(content).unwrap();
+        assert_eq!(fm.title, Some("Test Page".into()));
+        assert_eq!(fm.tags, Some(HashSet::from(["a".into()])));
+        assert_eq!(md, "# Content");
+
+        let content = "\u{feff}+++\r\ntitle = \"Test Page\"\r\n+++\r\n# Content\r\n";
+        let (fm, md) = Page::split_frontmatter(content).unwrap();
+        assert_eq!(fm.title, Some("Test Page".into()));
+        assert_eq!(md, "# Content");
+    }
+
+    #[test]
+    fn test_trailing_space_fence_parsing() {
+        let content = "--- \ntitle: Test Page\n---\t\n# Content\n";
+        let (fm, md) = Page::split_frontmatter(content).unwrap();
+        assert_eq!(fm.title, Some("Test Page".into()));
+        assert_eq!(md, "# Content");
+    }
+
+    #[test]
+    fn test_fenceless_document_parsing() {
+        let content = "# Content\n\n---\n\nMore content\n";
+        let (fm, md) = Page::split_frontmatter(content).unwrap();
+        assert_eq!(fm.title, None);
+        assert_eq!(md, content);
+    }
+
+    #[test]
+    fn test_title_extraction_prefers_headings() {
+        let title = |html: &str| Page::extract_header_title(&Html::parse_fragment(html));
+
+        assert_eq!(
+            title("<p>Intro</p><h2>Section</h2><h1>Main\n  title</h1>").as_deref(),
+            Some("Main title")
+        );
+        assert_eq!(
+            title("<p>Intro</p><h2>Section</h2>").as_deref(),
+            Some("Section")
+        );
+        assert_eq!(
+            title("<pre><p>Code</p></pre><p></p><p>Intro</p>").as_deref(),
+            Some("Intro")
+        );
+        assert_eq!(title("<pre><code>code</code></pre>"), None);
+    }
+
     #[test]
     fn test_link_rendering() {
         let md = "[About Page](/about-page)";
         let html = Page::render_markdown(md).unwrap();
         assert_eq!(html, r#"<p><a href="/about-page">About Page</a></p>"#);
     }
+
+    #[test]
+    fn test_read_content_rejects_large_and_binary_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = dir.path().join("text.md");
+        fs::write(&text, "# Text").unwrap();
+        let binary = dir.path().join("binary.md");
+        fs::write(&binary, [0xff, 0xfe, 0x00, 0x80]).unwrap();
+
+        assert_eq!(read_content(&text, 6).unwrap(), "# Text");
+        assert!(matches!(
+            read_content(&text, 5),
+            Err(PageError::TooLarge { size: 6, limit: 5 })
+        ));
+        assert!(matches!(
+            read_content(&binary, 1024),
+            Err(PageError::NotUtf8)
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_markdown_files_skip_symlink_cycles_and_escapes() {
+        use std::os::unix::fs::symlink;
+
+        // Hidden directories are skipped, so avoid the default `.tmp` prefix.
+        let tempdir = || tempfile::Builder::new().prefix("pages").tempdir().unwrap();
+        let outside = tempdir();
+        fs::write(outside.path().join("secret.md"), "# Secret").unwrap();
+
+        let dir = tempdir();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir(root.join("sub")).unwrap();
+        fs::write(root.join("a.md"), "# A").unwrap();
+        fs::write(root.join("sub/b.md"), "# B").unwrap();
+        fs::write(root.join("sub/notes.txt"), "Not a page").unwrap();
+        symlink(&root, root.join("sub/loop")).unwrap();
+        symlink(root.join("sub"), root.join("again")).unwrap();
+        symlink(outside.path(), root.join("outside")).unwrap();
+        symlink(outside.path().join("secret.md"), root.join("secret.md")).unwrap();
+
+        let mut files: Vec<_> = markdown_files(root.clone()).collect();
+        files.sort();
+        assert_eq!(files, [root.join("a.md"), root.join("sub/b.md")]);
+    }
+
+    #[test]
+    fn test_markdown_files_respect_ignore_file() {
+        let dir = tempfile::Builder::new().prefix("pages").tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir(root.join("drafts")).unwrap();
+        fs::write(root.join("a.md"), "# A").unwrap();
+        fs::write(root.join("template.md"), "# Template").unwrap();
+        fs::write(root.join("drafts/b.md"), "# B").unwrap();
+        fs::write(root.join(".websiteignore"), "drafts/\ntemplate.md\n").unwrap();
+
+        let files: Vec<_> = markdown_files(root.clone()).collect();
+        assert_eq!(files, [root.join("a.md")]);
+    }
+
+    #[test]
+    fn test_translations_are_language_suffixed_siblings() {
+        let dir = tempfile::Builder::new().prefix("pages").tempdir().unwrap();
+        let root = PageRoot {
+            path: dir.path().canonicalize().unwrap(),
+            prefix: "docs".into(),
+            collection: Some("docs".into()),
+        };
+        fs::create_dir(root.path.join("guide")).unwrap();
+        for file in [
+            "about.md",
+            "about.sv.md",
+            "about.pt-BR.md",
+            "about.draft.md",
+            "about-us.de.md",
+            "guide/index.md",
+            "guide/index.sv.md",
+            "guide/index.fi.md",
+            "single.md",
+            "node.js.md",
+            "lonely.sv.md",
+        ] {
+            fs::write(root.path.join(file), "# Page").unwrap();
+        }
+        fs::write(root.path.join(".websiteignore"), "about.sv.md\n").unwrap();
+
+        let translations = |file: &str| {
+            Page::translations_in(&root, &root.path.join(file), "en")
+                .into_iter()
+                .map(|translation| (translation.language, translation.url))
+                .collect::<Vec<_>>()
+        };
+        let expected = [
+            ("en".to_string(), "docs/about".to_string()),
+            ("pt-BR".to_string(), "docs/about.pt-BR".to_string()),
+        ];
+        assert_eq!(translations("about.md"), expected);
+        assert_eq!(translations("about.pt-BR.md"), expected);
+        assert_eq!(
+            translations("guide/index.md"),
+            [
+                ("en".to_string(), "docs/guide/index".to_string()),
+                ("fi".to_string(), "docs/guide/index.fi".to_string()),
+                ("sv".to_string(), "docs/guide/index.sv".to_string()),
+            ]
+        );
+        assert!(translations("single.md").is_empty());
+        assert!(translations("node.js.md").is_empty());
+        assert!(translations("lonely.sv.md").is_empty());
+
+        fs::write(root.path.join("single.de.md"), "# Seite").unwrap();
+        fs::File::open(&root.path)
+            .unwrap()
+            .set_modified(SystemTime::now() + std::time::Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(translations("single.md").len(), 2);
+        let translation = |url: &str| Translation {
+            language: "sv".into(),
+            url: url.into(),
+        };
+        assert_eq!(translation("docs/about.sv").negotiated_url(), "docs/about");
+        assert_eq!(translation("about.sv").negotiated_url(), "about");
+        assert_eq!(translation("docs/about").negotiated_url(), "docs/about");
+        assert!(translations("missing.md").is_empty());
+
+        assert_eq!(split_language("about.sv"), Some(("about", "sv")));
+        assert_eq!(split_language("v1.2"), None);
+        assert_eq!(split_language(".sv"), None);
+        assert!(is_language_tag("zh-Hant"));
+        assert!(!is_language_tag("swe"));
+        assert!(!is_language_tag("en-"));
+    }
 }
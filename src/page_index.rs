@@ -0,0 +1,93 @@
+use axum::{
+    Json, Router,
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use rayon::iter::ParallelIterator;
+use serde::Serialize;
+use time::OffsetDateTime;
+use ulid::Ulid;
+
+use crate::{
+    config::load_config,
+    page::Page,
+    search::sorted_tags,
+    web::{page_cache_control, with_last_modified},
+};
+
+/// Serves a machine readable list of all pages, for custom navigation or
+/// client side search.
+pub fn page_index_routes() -> Router {
+    Router::new().route("/pages.json", get(page_index_handler))
+}
+
+async fn page_index_handler() -> Response {
+    let mut pages: Vec<Page> = Page::read_blocking(|| {
+        Page::all()
+            .filter(|page| !page.draft && page.is_indexable())
+            .collect()
+    })
+    .await;
+    pages.sort_by(|a, b| a.url.cmp(&b.url));
+
+    let modified = pages.iter().map(|page| page.modified).max();
+    let mut response = Json(page_entries(&pages)).into_response();
+    if let Some(cache_control) = page_cache_control(&load_config(), false) {
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, cache_control);
+    }
+
+    match modified {
+        Some(modified) => with_last_modified(response, modified),
+        None => response,
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct PageEntry {
+    id: Ulid,
+    url: String,
+    title: Option<String>,
+    tags: Vec<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    modified: OffsetDateTime,
+}
+
+fn page_entries(pages: &[Page]) -> Vec<PageEntry> {
+    pages
+        .iter()
+        .map(|page| PageEntry {
+            id: page.id,
+            url: page.href(),
+            title: page.title.clone(),
+            tags: sorted_tags(&page.tags),
+            modified: page.modified,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_page_entries() {
+        let page = Page::parse(
+            PathBuf::from("guides/start.md"),
+            "---\ntitle: Start\ntags: [rust, axum]\n---\nBody",
+            OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap();
+
+        let entries = serde_json::to_value(page_entries(&[page])).unwrap();
+        assert_eq!(entries[0]["url"], "/guides/start");
+        assert_eq!(entries[0]["title"], "Start");
+        assert_eq!(entries[0]["tags"], serde_json::json!(["axum", "rust"]));
+        assert_eq!(entries[0]["modified"], "1970-01-01T00:00:00Z");
+        assert!(entries[0]["id"].is_string());
+    }
+}
@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    extract::{Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use moka::future::Cache;
+
+/// Largest response body kept in the cache.
+const MAX_CACHED_BYTES: u64 = 1024 * 1024;
+
+/// Request headers the cached responses are negotiated on, along with the
+/// `Vary` header those carry. Responses differing in them are cached apart.
+const VARIED_HEADERS: [HeaderName; 2] = [header::ACCEPT, header::ACCEPT_LANGUAGE];
+
+/// Request URI along with the values of [`VARIED_HEADERS`].
+type CacheKey = (String, Vec<Option<HeaderValue>>);
+
+#[derive(Clone)]
+struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl IntoResponse for CachedResponse {
+    fn into_response(self) -> Response {
+        let mut response = Response::new(Body::from(self.body));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers;
+        response
+    }
+}
+
+/// Successful `GET` responses kept for a short while, sparing repeated
+/// renders of popular pages.
+#[derive(Clone)]
+pub struct ResponseCache(Cache<CacheKey, CachedResponse>);
+
+impl ResponseCache {
+    pub fn with_lifespan(lifespan: Duration) -> Self {
+        Self(
+            Cache::builder()
+                .max_capacity(1000)
+                .time_to_live(lifespan)
+                .build(),
+        )
+    }
+}
+
+/// Answers from the cache when it holds a response for the request, and
+/// otherwise caches the response if it is successful and small enough.
+pub async fn cache_responses(
+    State(cache): State<ResponseCache>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::GET {
+        return next.run(request).await;
+    }
+
+    let key = (
+        request.uri().to_string(),
+        VARIED_HEADERS
+            .iter()
+            .map(|name| request.headers().get(name).cloned())
+            .collect(),
+    );
+    if let Some(cached) = cache.0.get(&key).await {
+        return cached.into_response();
+    }
+
+    let response = next.run(request).await;
+    let cacheable = response.status() == StatusCode::OK
+        && response
+            .body()
+            .size_hint()
+            .exact()
+            .is_some_and(|size| size <= MAX_CACHED_BYTES);
+    if !cacheable {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let Ok(body) = axum::body::to_bytes(body, MAX_CACHED_BYTES as usize).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    cache
+        .0
+        .insert(
+            key,
+            CachedResponse {
+                status: parts.status,
+                headers: parts.headers.clone(),
+                body: body.clone(),
+            },
+        )
+        .await;
+    Response::from_parts(parts, Body::from(body))
+}
@@ -1,23 +1,37 @@
 use std::{
+    cmp::Reverse,
+    collections::HashMap,
     fs,
     io::Cursor,
     path::{Path, PathBuf},
     str::FromStr,
-    sync::Arc,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
     time::SystemTime,
 };
 
 use ammonia::Builder;
-use anyhow::Result;
-use axum::{Router, extract::Query, response::Html, routing::get};
-use heed::EnvOpenOptions;
-use lazy_static::lazy_static;
+use anyhow::{Result, anyhow, bail};
+use axum::{
+    Json, Router,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use bytesize::ByteSize;
+use heed::{CompactionOption, EnvOpenOptions};
+use hyper::{
+    StatusCode,
+    header::{self, HeaderValue},
+};
 use milli::{
-    DefaultSearchLogger, FormatOptions, GeoSortStrategy, Index, MatcherBuilder, MatchingWords,
+    DefaultSearchLogger, DocumentId, FieldDistribution, Filter, FilterableAttributesRule,
+    FormatOptions, GeoSortStrategy, Index, MatcherBuilder, MatchingWords, RoaringBitmap,
     SearchContext, TermsMatchingStrategy, TimeBudget,
     documents::{DocumentsBatchBuilder, DocumentsBatchReader},
     execute_search, filtered_universe,
-    score_details::ScoringStrategy,
+    score_details::{ScoreDetails, ScoringStrategy},
     tokenizer::TokenizerBuilder,
     update::{ClearDocuments, IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings},
 };
@@ -27,36 +41,127 @@ use pulldown_cmark::{Event, Parser};
 use rayon::iter::ParallelIterator;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use time::{
+    Date, OffsetDateTime, format_description::well_known::Rfc3339, macros::format_description,
+};
 use tokio::{
     sync::{RwLock, mpsc},
     task::JoinHandle,
     time::interval,
 };
 use ulid::Ulid;
+use xxhash_rust::xxh3::xxh3_128;
+
+use crate::{
+    config::{Config, load_config},
+    error_handler::ValidQuery,
+    markdown::allow_configured_html,
+    page::{Page, PageError, PageRoot, log_unreadable, normalize_tag},
+    web::{HTML_CONTENT_TYPE, escape_html, render_layout},
+    websiteignore::is_ignored,
+};
 
-use crate::{assets::ASSET_MANAGER, config::Config, page::Page};
-
+/// Starts indexing pages, returning the index along with the filesystem
+/// watcher and the task reindexing on changes, both `None` when watching is
+/// turned off with `watch_pages`.
 pub async fn spawn_search_indexer(
     config: &Config,
 ) -> Result<(
     Arc<RwLock<SearchIndex>>,
-    Debouncer<RecommendedWatcher, RecommendedCache>,
-    JoinHandle<()>,
+    Option<Debouncer<RecommendedWatcher, RecommendedCache>>,
+    Option<JoinHandle<()>>,
 )> {
+    tokio::task::spawn_blocking(Page::index_slugs).await?;
     let search_index = Arc::new(RwLock::new(SearchIndex::new(&config.search_path())?));
-    let search_index_watch = search_index.clone();
     let search_index_periodic = search_index.clone();
-    let duration = *config.search_reindex_interval();
+    let interval_duration = *config.search_reindex_interval();
+
+    let (debouncer, watcher) = if *config.watch_pages() {
+        let (debouncer, watcher) = watch_pages(search_index.clone())?;
+        (Some(debouncer), Some(watcher))
+    } else {
+        tracing::info!("🔎 Not watching pages for changes");
+        (None, None)
+    };
+
+    let warmed = *config.warm_index_on_start() && warm_index(&search_index).await;
+
+    match interval_duration {
+        Some(duration) => {
+            tokio::spawn(async move {
+                let mut interval = interval(duration);
+                interval.tick().await;
+                if warmed {
+                    interval.tick().await;
+                }
+
+                loop {
+                    tracing::info!("⏰ Periodic reindex triggered");
+                    if let Err(e) = search_index_periodic.read().await.reindex().await {
+                        tracing::error!("💥 Periodic reindex failed: {}", e);
+                    }
+                    if let Err(e) = search_index_periodic.write().await.swap_indexes().await {
+                        tracing::error!("💥 Swapping indexes failed: {}", e);
+                    }
+                    interval.tick().await;
+                }
+            });
+        }
+        None => {
+            tracing::info!("⏰ Periodic reindexing is turned off");
+            // The index still needs to catch up with changes made while stopped.
+            if !warmed {
+                tokio::spawn(async move {
+                    tracing::info!("⏰ Initial reindex triggered");
+                    let reindexed = search_index_periodic.read().await.reindex().await;
+                    let result = match reindexed {
+                        Ok(_) => search_index_periodic.write().await.swap_indexes().await,
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = result {
+                        tracing::error!("💥 Initial reindex failed: {}", e);
+                    }
+                });
+            }
+        }
+    }
+
+    if let Some(duration) = *config.search_compact_interval() {
+        let search_index_compact = search_index.clone();
+        tokio::spawn(async move {
+            let mut interval = interval(duration);
+            interval.tick().await;
+
+            loop {
+                interval.tick().await;
+                if let Err(e) = compact_index(&search_index_compact).await {
+                    tracing::error!("💥 Compacting the search index failed: {}", e);
+                }
+            }
+        });
+    }
+
+    Ok((search_index, debouncer, watcher))
+}
+
+/// Watches the page roots, reindexing shortly after files change.
+fn watch_pages(
+    search_index: Arc<RwLock<SearchIndex>>,
+) -> Result<(
+    Debouncer<RecommendedWatcher, RecommendedCache>,
+    JoinHandle<()>,
+)> {
+    let roots = PageRoot::all();
+    let watched_roots = roots.clone();
 
     let (sender, mut receiver) = mpsc::channel(1);
-    // let sender_inner = sender.clone();
     let mut debouncer: Debouncer<RecommendedWatcher, RecommendedCache> = new_debouncer(
         std::time::Duration::from_millis(30),
         Some(std::time::Duration::from_millis(30)),
         move |res: Result<Vec<DebouncedEvent>, _>| match res {
             Ok(events) => {
                 for event in events {
-                    if !event.kind.is_access() {
+                    if !event.kind.is_access() && !is_ignored_event(&roots, &event) {
                         if let Err(e) = sender.try_send(event) {
                             tracing::error!("💥 Failed to notify about file changes: {:?}", e);
                         }
@@ -70,20 +175,22 @@ pub async fn spawn_search_indexer(
         },
     )?;
 
-    debouncer.watch(config.pages_path(), RecursiveMode::Recursive)?;
-    tracing::info!(
-        "🔎 Watching pages for changes at path: {}",
-        config.pages_path().to_string_lossy()
-    );
+    for root in watched_roots {
+        debouncer.watch(&root.path, RecursiveMode::Recursive)?;
+        tracing::info!(
+            "🔎 Watching pages for changes at path: {}",
+            root.path.to_string_lossy()
+        );
+    }
 
     let watcher = tokio::spawn(async move {
         loop {
             if receiver.recv().await.is_some() {
                 tracing::info!("📁 Filesystem change detected, triggering reindex");
-                if let Err(e) = search_index_watch.write().await.reindex().await {
+                if let Err(e) = search_index.write().await.reindex().await {
                     tracing::error!("💥 Filesystem-triggered reindex failed: {}", e);
                 }
-                if let Err(e) = search_index_watch.write().await.swap_indexes().await {
+                if let Err(e) = search_index.write().await.swap_indexes().await {
                     tracing::error!("💥 Swapping indexes failed: {}", e);
                 }
             } else {
@@ -92,42 +199,199 @@ pub async fn spawn_search_indexer(
         }
     });
 
-    tokio::spawn(async move {
-        let mut interval = interval(duration);
-        interval.tick().await;
+    Ok((debouncer, watcher))
+}
 
-        loop {
-            tracing::info!("⏰ Periodic reindex triggered");
-            if let Err(e) = search_index_periodic.read().await.reindex().await {
-                tracing::error!("💥 Periodic reindex failed: {}", e);
-            }
-            if let Err(e) = search_index_periodic.write().await.swap_indexes().await {
-                tracing::error!("💥 Swapping indexes failed: {}", e);
-            }
-            interval.tick().await;
+/// Compacts the staging index, reclaiming the space left behind by earlier
+/// reindexes. The copy is made while searches continue, only swapping it in
+/// briefly holds the index exclusively.
+pub async fn compact_index(search_index: &RwLock<SearchIndex>) -> Result<(u64, u64)> {
+    tracing::info!("🗜️ Compacting the staging search index...");
+    let compacted = search_index.read().await.compact_staging()?;
+    let (before, after) = search_index
+        .write()
+        .await
+        .install_compacted(compacted)
+        .await?;
+    tracing::info!(
+        "🗜️ Compacted the staging search index from {} to {}",
+        ByteSize::b(before),
+        ByteSize::b(after)
+    );
+    Ok((before, after))
+}
+
+/// Size of the LMDB data file of the index at `path`.
+fn data_file_size(path: &Path) -> Result<u64> {
+    Ok(fs::metadata(path.join("data.mdb"))?.len())
+}
+
+/// Reindexes and serves the result before the server starts, returning
+/// whether it succeeded. On failure the server starts with the index as is.
+async fn warm_index(search_index: &RwLock<SearchIndex>) -> bool {
+    tracing::info!("🔥 Warming the search index, the server starts once it is done...");
+
+    let reindexed = search_index.read().await.reindex().await;
+    let result = match reindexed {
+        Ok(_) => search_index.write().await.swap_indexes().await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => {
+            tracing::info!("🔥 Search index warmed");
+            true
+        }
+        Err(e) => {
+            tracing::error!("💥 Warming the search index failed, starting anyway: {}", e);
+            false
         }
+    }
+}
+
+/// Rebuilds an unreadable active index in the background, unless a rebuild
+/// is already under way. Searches find nothing until it is done.
+fn spawn_recovery(search_index: Arc<RwLock<SearchIndex>>) {
+    tokio::spawn(async move {
+        if search_index
+            .read()
+            .await
+            .recovering
+            .swap(true, Ordering::SeqCst)
+        {
+            return;
+        }
+        if let Err(e) = recover_index(&search_index).await {
+            tracing::error!("💥 Rebuilding the search index failed: {}", e);
+        }
+        search_index
+            .read()
+            .await
+            .recovering
+            .store(false, Ordering::SeqCst);
     });
+}
 
-    Ok((search_index, debouncer, watcher))
+/// Indexes all pages into a freshly created staging index and serves it,
+/// then recreates the former active index as the new staging one.
+async fn recover_index(search_index: &RwLock<SearchIndex>) -> Result<()> {
+    search_index.write().await.recreate_staging()?;
+    search_index.read().await.reindex().await?;
+    search_index.write().await.swap_indexes().await?;
+    search_index.write().await.recreate_staging()?;
+    tracing::info!("🔎 Search index rebuilt");
+    Ok(())
+}
+
+/// Whether all paths of a filesystem event are excluded by the
+/// `.websiteignore` file of the page root they are in.
+fn is_ignored_event(roots: &[PageRoot], event: &DebouncedEvent) -> bool {
+    !event.paths.is_empty()
+        && event.paths.iter().all(|path| {
+            let path = path.canonicalize().unwrap_or_else(|_| path.clone());
+            PageRoot::containing(roots, &path)
+                .is_some_and(|root| is_ignored(&root.path, &path, path.is_dir()))
+        })
 }
 
 pub fn search_route(search_index: Arc<RwLock<SearchIndex>>) -> Router {
     let search_index = search_index;
     Router::new().route(
         "/search",
-        get(async move |Query(params): Query<SearchParams>| {
+        get(async move |ValidQuery(params): ValidQuery<SearchParams>| {
+            let mut search_query = SearchQuery::parse(&params.q);
+            search_query.collection = params.collection.clone().filter(|name| !name.is_empty());
+            match params.modified_range() {
+                Ok((after, before)) => {
+                    search_query.modified_after = after;
+                    search_query.modified_before = before;
+                }
+                Err(message) => {
+                    return render_search_error(
+                        StatusCode::BAD_REQUEST,
+                        &params.q,
+                        "Invalid search",
+                        &message,
+                    );
+                }
+            }
+            match params.crop() {
+                Ok(crop) => search_query.crop = crop,
+                Err(message) => {
+                    return render_search_error(
+                        StatusCode::BAD_REQUEST,
+                        &params.q,
+                        "Invalid search",
+                        &message,
+                    );
+                }
+            }
+            search_query.plain = params.plain();
+
+            let debug = params.debug();
             let query = params.q;
-            let hits = search_index
-                .read()
-                .await
-                .search(&query)
-                .await
-                .unwrap_or_else(|_| Vec::new());
-            render_search_results(query, hits)
+            let results = if search_query.is_empty() {
+                let collection = search_query.collection.clone();
+                let pages = Page::read_blocking(move || {
+                    Page::all()
+                        .filter(|page| collection.is_none() || page.collection == collection)
+                        .collect()
+                })
+                .await;
+                recent_pages(
+                    pages,
+                    *load_config().feed_item_count(),
+                    search_query.crop.unwrap_or(EXCERPT_WORDS),
+                )
+            } else {
+                let searched = search_index.read().await.search(&search_query).await;
+                match searched {
+                    Ok(results) => results,
+                    Err(e) if !search_index.read().await.is_readable() => {
+                        tracing::error!("💥 Search index is unreadable, rebuilding it: {}", e);
+                        spawn_recovery(search_index.clone());
+                        SearchResults::default()
+                    }
+                    Err(e) => {
+                        tracing::error!("💥 Search for {:?} failed: {}", query, e);
+                        return render_search_error(
+                            StatusCode::SERVICE_UNAVAILABLE,
+                            &query,
+                            "Search unavailable",
+                            "Search is temporarily unavailable, please try again later.",
+                        );
+                    }
+                }
+            };
+            let total = results.total;
+            let mut response = if debug {
+                Json(&results).into_response()
+            } else {
+                render_search_results(query, results)
+            };
+            response
+                .headers_mut()
+                .insert("x-total-results", HeaderValue::from(total));
+            response
         }),
     )
 }
 
+/// Lists the most recently modified published pages, shown in place of
+/// search results when the query is empty, with excerpts of `words` words.
+fn recent_pages(mut pages: Vec<Page>, limit: usize, words: usize) -> SearchResults {
+    pages.retain(|page| !page.draft);
+    pages.sort_by_key(|page| Reverse(page.modified));
+
+    SearchResults {
+        total: pages.len() as u64,
+        hits: pages
+            .iter()
+            .take(limit)
+            .map(|page| SearchHit::from_page(page, words))
+            .collect(),
+    }
+}
+
 pub struct SearchIndex {
     active_index: Index,
     staging_index: Index,
@@ -135,6 +399,38 @@ pub struct SearchIndex {
     staging_path: PathBuf,
     alpha_path: PathBuf,
     beta_path: PathBuf,
+    /// Bumped whenever the staging index is written to or swapped, so a
+    /// compacted copy that has since gone stale is not installed.
+    staging_generation: AtomicU64,
+    last_reindex: Mutex<Option<ReindexStats>>,
+    /// Set while an unreadable index is being rebuilt.
+    recovering: AtomicBool,
+}
+
+/// Outcome of the most recent completed reindex.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReindexStats {
+    #[serde(with = "time::serde::rfc3339")]
+    finished: OffsetDateTime,
+    duration_ms: u128,
+    indexed: usize,
+    skipped: usize,
+}
+
+/// Contents of the active index, reported by the admin stats endpoint.
+#[derive(Debug, Serialize)]
+pub struct IndexStats {
+    documents: u64,
+    field_distribution: FieldDistribution,
+    searchable_fields: usize,
+    last_reindex: Option<ReindexStats>,
+}
+
+/// A compacted copy of the staging index waiting to replace it.
+pub struct CompactedIndex {
+    path: PathBuf,
+    generation: u64,
+    size_before: u64,
 }
 
 impl SearchIndex {
@@ -163,17 +459,60 @@ impl SearchIndex {
             staging_path,
             alpha_path,
             beta_path,
+            staging_generation: AtomicU64::new(0),
+            last_reindex: Mutex::new(None),
+            recovering: AtomicBool::new(false),
         })
     }
 
-    pub async fn search(&self, query: &str) -> Result<Vec<SearchHit>> {
-        tracing::debug!("Searching with query: {}", query);
+    pub async fn search(&self, query: &SearchQuery) -> Result<SearchResults> {
+        tracing::debug!("Searching with query: {:?}", query);
         let rtxn = self.active_index.read_txn()?;
+        let filter_expression = query.filter_expression();
+        let filter = match &filter_expression {
+            Some(expression) => Filter::from_str(expression)?,
+            None => None,
+        };
+        let mut universe = filtered_universe(&self.active_index, &rtxn, &filter)?;
+
+        // Documents within the universe whose title contains all the terms.
+        let title_fields = ["title".to_string()];
+        let title_matches = |terms: &[String], universe: &RoaringBitmap| -> Result<_> {
+            let mut ctx = SearchContext::new(&self.active_index, &rtxn)?;
+            ctx.attributes_to_search_on(&title_fields)?;
+            let result = execute_search(
+                &mut ctx,
+                Some(&terms.join(" ")),
+                TermsMatchingStrategy::All,
+                ScoringStrategy::Skip,
+                true,
+                universe.clone(),
+                &None,
+                &None,
+                GeoSortStrategy::default(),
+                0,
+                universe.len() as usize,
+                None,
+                &mut DefaultSearchLogger,
+                &mut DefaultSearchLogger,
+                TimeBudget::default(),
+                None,
+                None,
+            )?;
+            Ok(result.candidates)
+        };
+        if !query.titles.is_empty() {
+            universe &= title_matches(&query.titles, &universe)?;
+        }
+        if !query.excluded_titles.is_empty() {
+            universe -= title_matches(&query.excluded_titles, &universe)?;
+        }
+
+        let text = query.text();
         let mut ctx = SearchContext::new(&self.active_index, &rtxn)?;
-        let universe = filtered_universe(ctx.index, ctx.txn, &None)?;
         let search_result = execute_search(
             &mut ctx,
-            Some(query),
+            (!text.is_empty()).then_some(text.as_str()),
             TermsMatchingStrategy::Last,
             ScoringStrategy::Detailed,
             false,
@@ -191,25 +530,37 @@ impl SearchIndex {
             None,
         )?;
 
+        let total = search_result.candidates.len();
         let document_ids = search_result.documents_ids;
+        let scores: HashMap<DocumentId, f64> = document_ids
+            .iter()
+            .copied()
+            .zip(
+                search_result
+                    .document_scores
+                    .iter()
+                    .map(|details| ScoreDetails::global_score(details.iter())),
+            )
+            .collect();
 
         let matching_words =
             MatchingWords::new(ctx, search_result.located_query_terms.unwrap_or_default());
         let tokenizer = TokenizerBuilder::default().into_tokenizer();
+        let config = load_config();
         let mut matcher_builder = MatcherBuilder::new(matching_words, tokenizer);
-        matcher_builder.highlight_prefix("⚇".to_string());
-        matcher_builder.highlight_suffix("⚉".to_string());
+        matcher_builder.highlight_prefix(config.highlight_prefix().clone());
+        matcher_builder.highlight_suffix(config.highlight_suffix().clone());
 
         let format_options = FormatOptions {
-            highlight: true,
-            crop: Some(20),
+            highlight: !query.plain,
+            crop: Some(query.crop.unwrap_or(EXCERPT_WORDS)),
         };
 
         let documents = self.active_index.documents(&rtxn, document_ids)?;
         let fields_map = self.active_index.fields_ids_map(&rtxn)?;
 
         let mut output = Vec::new();
-        for (_id, obkv_doc) in documents.iter() {
+        for (id, obkv_doc) in documents.iter() {
             let mut doc = serde_json::Map::new();
 
             for (field_id, value_bytes) in obkv_doc.iter() {
@@ -221,91 +572,109 @@ impl SearchIndex {
 
                 let value: Value = serde_json::from_slice(value_bytes)?;
 
-                if field_name == "markdown" && value.is_string() {
+                if (field_name == "markdown" || field_name == "description") && value.is_string() {
                     let text = value.as_str().unwrap();
                     let mut matcher = matcher_builder.build(text, None);
-                    let formatted_text = matcher.format(format_options);
+                    // Descriptions are short enough to be shown whole.
+                    let formatted_text = if field_name == "description" {
+                        matcher.format(FormatOptions {
+                            highlight: !query.plain,
+                            crop: None,
+                        })
+                    } else {
+                        matcher.format(format_options)
+                    };
 
-                    doc.insert(field_name.to_string(), value.clone());
                     doc.insert(
-                        "_formatted_markdown".to_string(),
+                        format!("_formatted_{field_name}"),
                         Value::String(formatted_text.into_owned()),
                     );
+                    doc.insert(field_name.to_string(), value);
                 } else {
                     doc.insert(field_name.to_string(), value);
                 }
             }
 
-            if let Ok(hit) = SearchHit::try_from(Value::Object(doc)) {
+            if let Ok(mut hit) = SearchHit::try_from(Value::Object(doc)) {
+                hit.score = scores.get(id).copied();
                 output.push(hit);
             }
         }
 
-        Ok(output)
+        Ok(SearchResults {
+            hits: output,
+            total,
+        })
     }
 
     pub async fn index_page(&self, page: Page) -> Result<()> {
+        if page.draft {
+            return Ok(());
+        }
         self.commit_batch(vec![page], &self.active_index).await
     }
 
-    async fn reindex(&self) -> Result<()> {
+    /// Rebuilds the staging index from all pages, returning how many were
+    /// indexed. Call [`SearchIndex::swap_indexes`] afterwards to serve it.
+    pub async fn reindex(&self) -> Result<usize> {
         tracing::info!("🔎 Indexing all pages...");
         let start = SystemTime::now();
 
+        self.staging_generation.fetch_add(1, Ordering::SeqCst);
         self.clear_staging().await?;
+        tokio::task::spawn_blocking(Page::index_slugs).await?;
 
-        let (tx, mut rx) = tokio::sync::mpsc::channel(1000);
-
-        let producer = tokio::task::spawn_blocking(async move || {
-            Page::all()
-                .filter_map(|page| {
-                    let _ = tx.blocking_send(page);
-                    Some(())
-                })
-                .count()
-        });
-
-        let mut batch = Vec::with_capacity(100);
-        let mut timeout = tokio::time::interval(tokio::time::Duration::from_secs(1));
-        let mut total = 0;
-
-        loop {
-            tokio::select! {
-                biased;
-                page = rx.recv() => {
-                    if let Some(page) = page {
-                        batch.push(page);
-                        total += 1;
-
-                        if batch.len() >= 100 {
-                            self.commit_batch(batch, &self.staging_index).await?;
-                            batch = Vec::with_capacity(100);
-                        }
-                    } else {
-                        break;                    }
-                },
-                _ = timeout.tick() => {
-                    if !batch.is_empty() {
-                        self.commit_batch(batch, &self.staging_index).await?;
-                        batch = Vec::with_capacity(100);
-                    }
-                }
-            }
-        }
+        let config = load_config();
+        let ids = IdRegistry::new(*config.strict_ids(), config.search_primary_key());
+        let (total, skipped) = index_pages(Page::read_all(), ids, |batch| {
+            self.commit_batch(batch, &self.staging_index)
+        })
+        .await?;
 
-        if !batch.is_empty() {
-            self.commit_batch(batch, &self.staging_index).await?;
-        }
+        let delta = start.elapsed()?;
+        tracing::info!(
+            "\tIndexed {} pages in {:?}, skipped {} unreadable files",
+            total,
+            delta,
+            skipped
+        );
 
-        let _ = producer.await?;
+        *self.last_reindex.lock().unwrap_or_else(|e| e.into_inner()) = Some(ReindexStats {
+            finished: OffsetDateTime::now_utc(),
+            duration_ms: delta.as_millis(),
+            indexed: total,
+            skipped,
+        });
+        self.staging_generation.fetch_add(1, Ordering::SeqCst);
+        Ok(total)
+    }
 
-        let delta = start.elapsed()?;
-        tracing::info!("\tIndexed {} pages in {:?}", total, delta);
+    /// Document count and fields of the active index, along with the
+    /// outcome of the last reindex.
+    pub fn stats(&self) -> Result<IndexStats> {
+        let rtxn = self.active_index.read_txn()?;
+        Ok(IndexStats {
+            documents: self.active_index.number_of_documents(&rtxn)?,
+            field_distribution: self.active_index.field_distribution(&rtxn)?,
+            searchable_fields: self.active_index.searchable_fields(&rtxn)?.len(),
+            last_reindex: self
+                .last_reindex
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+        })
+    }
 
-        Ok(())
+    /// Whether the active index can still be read, telling a corrupt index
+    /// apart from a search that failed for other reasons.
+    pub fn is_readable(&self) -> bool {
+        self.active_index.read_txn().is_ok_and(|rtxn| {
+            self.active_index.number_of_documents(&rtxn).is_ok()
+                && self.active_index.fields_ids_map(&rtxn).is_ok()
+        })
     }
 
-    async fn swap_indexes(&mut self) -> Result<()> {
+    pub async fn swap_indexes(&mut self) -> Result<()> {
         tracing::debug!("Swapping active and staging indexes");
 
         let _ = remove_dummy_index(&self.active_path);
@@ -353,10 +722,89 @@ impl SearchIndex {
         event.wait();
         remove_dummy_index(&self.staging_path)?;
 
+        self.staging_generation.fetch_add(1, Ordering::SeqCst);
         tracing::debug!("Index swap completed successfully");
         Ok(())
     }
 
+    /// Copies the staging index into a compacted file without its free pages,
+    /// to be put in place with [`SearchIndex::install_compacted`]. Searches
+    /// keep using the active index meanwhile, and as the two directories take
+    /// turns being staging both get compacted over consecutive swaps.
+    pub fn compact_staging(&self) -> Result<CompactedIndex> {
+        let path = self.staging_path.with_extension("compact");
+        let _ = fs::remove_file(&path);
+
+        let generation = self.staging_generation.load(Ordering::SeqCst);
+        let size_before = data_file_size(&self.staging_path)?;
+        self.staging_index
+            .copy_to_path(&path, CompactionOption::Enabled)?;
+
+        Ok(CompactedIndex {
+            path,
+            generation,
+            size_before,
+        })
+    }
+
+    /// Replaces the staging index with its compacted copy, unless it has been
+    /// written to since the copy was made. Returns the data file sizes before
+    /// and after.
+    pub async fn install_compacted(&mut self, compacted: CompactedIndex) -> Result<(u64, u64)> {
+        if compacted.generation != self.staging_generation.load(Ordering::SeqCst) {
+            let _ = fs::remove_file(&compacted.path);
+            bail!("staging index changed while compacting");
+        }
+
+        let _ = remove_dummy_index(&self.staging_path);
+        let old_staging_index = std::mem::replace(
+            &mut self.staging_index,
+            create_dummy_index(&self.staging_path)?,
+        );
+        let event = old_staging_index.prepare_for_closing();
+        event.wait();
+
+        let renamed = fs::rename(&compacted.path, self.staging_path.join("data.mdb"));
+
+        let dummy_staging_index = std::mem::replace(
+            &mut self.staging_index,
+            create_or_open_index(&self.staging_path)?,
+        );
+        let event = dummy_staging_index.prepare_for_closing();
+        event.wait();
+        remove_dummy_index(&self.staging_path)?;
+        renamed?;
+
+        Ok((compacted.size_before, data_file_size(&self.staging_path)?))
+    }
+
+    /// Replaces the staging index with an empty one, removing its files
+    /// rather than clearing documents out of what may be a corrupt index.
+    fn recreate_staging(&mut self) -> Result<()> {
+        let _ = remove_dummy_index(&self.staging_path);
+        let old_staging_index = std::mem::replace(
+            &mut self.staging_index,
+            create_dummy_index(&self.staging_path)?,
+        );
+        let event = old_staging_index.prepare_for_closing();
+        event.wait();
+
+        let directory = self.staging_path.canonicalize()?;
+        let removed = fs::remove_dir_all(&directory).and_then(|_| fs::create_dir_all(&directory));
+
+        let dummy_staging_index = std::mem::replace(
+            &mut self.staging_index,
+            create_or_open_index(&self.staging_path)?,
+        );
+        let event = dummy_staging_index.prepare_for_closing();
+        event.wait();
+        remove_dummy_index(&self.staging_path)?;
+        removed?;
+
+        self.staging_generation.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
     async fn clear_staging(&self) -> Result<()> {
         tracing::debug!("Clear out staging");
         let mut wtxn = self.staging_index.write_txn()?;
@@ -379,10 +827,14 @@ impl SearchIndex {
                 serde_json::json!({
                     "id": page.id.to_string(),
                     "title": page.title,
+                    "description": page.description,
                     "markdown": page.markdown,
-                    "modified": page.modified,
+                    "modified": page.modified.unix_timestamp(),
                     "url": page.url,
-                    "tags": page.tags
+                    "url_key": url_key(&page.url),
+                    "tags": page.tags,
+                    "author": page.author,
+                    "collection": page.collection
                 })
                 .as_object()
                 .unwrap(),
@@ -392,9 +844,12 @@ impl SearchIndex {
         let vector = builder.into_inner().unwrap();
         let reader = DocumentsBatchReader::from_reader(Cursor::new(vector))?;
 
-        let (builder, _) =
+        let (builder, added) =
             IndexDocuments::new(&mut wtxn, index, &config, indexing_config, |_| (), || false)?
                 .add_documents(reader)?;
+        // Rejected documents, such as those with invalid ids, are only
+        // reported here and would otherwise leave the batch out silently.
+        added?;
 
         builder.execute()?;
         wtxn.commit()?;
@@ -403,11 +858,167 @@ impl SearchIndex {
     }
 }
 
+/// Reads `pages` on a blocking thread while committing them with `commit` in
+/// batches of 100, or of what arrived within a second. Returns how many pages
+/// were indexed and how many files were skipped as unreadable. When the
+/// consumer fails, the reader is stopped and waited for before the error is
+/// returned, so a failed reindex leaves no crawl running.
+async fn index_pages<F>(
+    pages: impl ParallelIterator<Item = (PathBuf, Result<Page, PageError>)> + 'static,
+    mut ids: IdRegistry,
+    mut commit: impl FnMut(Vec<Page>) -> F,
+) -> Result<(usize, usize)>
+where
+    F: Future<Output = Result<()>>,
+{
+    let (tx, mut rx) = mpsc::channel(1000);
+    let producer = tokio::task::spawn_blocking(move || send_all(pages, tx));
+
+    let consumed = async {
+        let mut batch = Vec::with_capacity(100);
+        let mut timeout = tokio::time::interval(tokio::time::Duration::from_secs(1));
+        let mut total = 0;
+        let mut skipped = 0;
+
+        loop {
+            tokio::select! {
+                biased;
+                page = rx.recv() => {
+                    let Some((path, page)) = page else {
+                        break;
+                    };
+                    let page = match page {
+                        Ok(page) => page,
+                        Err(e) => {
+                            log_unreadable(&path, &e);
+                            skipped += 1;
+                            continue;
+                        }
+                    };
+                    ids.register(&page)?;
+                    // Drafts are only served by URL, so searches leave them out.
+                    if page.draft {
+                        continue;
+                    }
+                    batch.push(page);
+                    total += 1;
+
+                    if batch.len() >= 100 {
+                        commit(std::mem::replace(&mut batch, Vec::with_capacity(100))).await?;
+                    }
+                },
+                _ = timeout.tick() => {
+                    if !batch.is_empty() {
+                        commit(std::mem::replace(&mut batch, Vec::with_capacity(100))).await?;
+                    }
+                }
+            }
+        }
+
+        if !batch.is_empty() {
+            commit(batch).await?;
+        }
+        Ok((total, skipped))
+    }
+    .await;
+
+    let counts = match consumed {
+        Ok(counts) => counts,
+        Err(e) => {
+            // Without a receiver the next send fails, which ends the walk.
+            drop(rx);
+            if let Err(join_error) = producer.await {
+                tracing::error!("💥 Page reader task failed: {}", join_error);
+            }
+            return Err(e);
+        }
+    };
+
+    match producer.await {
+        Ok(Ok(sent)) => tracing::debug!("Sent {} pages for indexing", sent),
+        Ok(Err(e)) => {
+            tracing::error!("💥 Sending pages for indexing failed: {}", e);
+            return Err(e);
+        }
+        Err(e) => {
+            tracing::error!("💥 Page reader task failed: {}", e);
+            return Err(e.into());
+        }
+    }
+
+    Ok(counts)
+}
+
+/// Sends all items down the channel, stopping early with an error if the
+/// receiving end has been dropped.
+fn send_all<T: Send>(items: impl ParallelIterator<Item = T>, tx: mpsc::Sender<T>) -> Result<usize> {
+    items
+        .try_fold(
+            || 0,
+            |count, item| tx.blocking_send(item).map(|_| count + 1),
+        )
+        .try_reduce(|| 0, |a, b| Ok(a + b))
+        .map_err(|_| anyhow!("receiver dropped before all items were sent"))
+}
+
+/// Tracks the primary keys of the pages seen during a reindex, as pages
+/// sharing one replace each other in the index. Besides ids set in the
+/// frontmatter, ids derived from the URL clash when slugs do.
+struct IdRegistry {
+    seen: HashMap<String, PathBuf>,
+    strict: bool,
+    by_url: bool,
+}
+
+impl IdRegistry {
+    fn new(strict: bool, primary_key: &str) -> Self {
+        Self {
+            seen: HashMap::new(),
+            strict,
+            by_url: primary_key == "url",
+        }
+    }
+
+    fn register(&mut self, page: &Page) -> Result<()> {
+        let (name, key) = if self.by_url {
+            ("url", format!("/{}", page.url.to_string_lossy()))
+        } else if page.explicit_id {
+            ("id", page.id.to_string())
+        } else {
+            ("id derived from the URL", page.id.to_string())
+        };
+
+        if let Some(existing) = self.seen.get(&key) {
+            let message = format!(
+                "Duplicate page {} {} in {} and {}, only one of them is searchable",
+                name,
+                key,
+                existing.to_string_lossy(),
+                page.path.to_string_lossy()
+            );
+            if self.strict {
+                bail!(message);
+            }
+            tracing::warn!("⚠️ {message}");
+        } else {
+            self.seen.insert(key, page.path.clone());
+        }
+
+        Ok(())
+    }
+}
+
 fn create_or_open_index(path: &Path) -> Result<Index> {
+    open_index(path, &load_config())
+}
+
+/// Opens the index at `path` with the search settings of `config`, creating
+/// it when missing and recreating it when unreadable.
+fn open_index(path: &Path, config: &Config) -> Result<Index> {
     fs::create_dir_all(path)?;
 
     let mut options = EnvOpenOptions::new();
-    options.map_size(1024 * 1024 * 1024);
+    options.map_size(*config.search_map_size());
     options.max_dbs(1);
     options.max_readers(512);
     let options = options.read_txn_without_tls();
@@ -423,22 +1034,45 @@ fn create_or_open_index(path: &Path) -> Result<Index> {
     };
 
     let mut wtxn = index.write_txn()?;
-    let config = IndexerConfig::default();
-    let mut builder = Settings::new(&mut wtxn, &index, &config);
-    builder.set_primary_key("id".into());
-    builder.set_searchable_fields(vec!["title".into(), "markdown".into(), "tags".into()]);
+    let indexer_config = IndexerConfig::default();
+    let mut builder = Settings::new(&mut wtxn, &index, &indexer_config);
+    builder.set_primary_key(index_primary_key(config.search_primary_key()).into());
+    builder.set_searchable_fields(config.search_searchable_fields().clone());
+    builder.set_filterable_fields(vec![
+        FilterableAttributesRule::Field("tags".into()),
+        FilterableAttributesRule::Field("modified".into()),
+        FilterableAttributesRule::Field("collection".into()),
+    ]);
+    builder.set_criteria(config.ranking_rules().clone());
     builder.execute(|_| (), || false)?;
     wtxn.commit()?;
 
     Ok(index)
 }
 
+/// Document field the index is keyed on for the configured primary key. Index
+/// ids can not hold the slashes of URL paths, so pages keyed on their URL are
+/// keyed on its [`url_key`].
+fn index_primary_key(primary_key: &str) -> &str {
+    if primary_key == "url" {
+        "url_key"
+    } else {
+        primary_key
+    }
+}
+
+/// Hash of a page URL path as 32 hexadecimal digits, a valid index id unlike
+/// the path itself.
+fn url_key(url: &Path) -> String {
+    format!("{:032x}", xxh3_128(url.to_string_lossy().as_bytes()))
+}
+
 fn create_dummy_index(path: &Path) -> Result<Index> {
     let path = path.with_extension("dummy");
     std::fs::create_dir_all(&path)?;
 
     let mut options = EnvOpenOptions::new();
-    options.map_size(1024 * 1024);
+    options.map_size(*load_config().search_map_size());
     let options = options.read_txn_without_tls();
     let index = Index::new(options, path, true)?;
 
@@ -461,12 +1095,189 @@ fn symlink(original: &PathBuf, link: &PathBuf) -> std::io::Result<()> {
     std::os::windows::fs::symlink_dir(original, link)
 }
 
+/// A search query split into free text and field operators.
+///
+/// Supported operators, which can be combined and repeated:
+///
+/// - `title:word` only matches pages with the word in their title
+/// - `tag:name` only matches pages tagged with the name
+/// - `-title:word` and `-tag:name` exclude those pages instead
+/// - `-word` excludes pages containing the word anywhere
+///
+/// Values can be quoted to include spaces, as in `tag:"getting started"`.
+/// Anything else, including unknown prefixes such as `http://`, is searched
+/// for as is.
+#[derive(Debug, Default, PartialEq)]
+pub struct SearchQuery {
+    terms: Vec<String>,
+    titles: Vec<String>,
+    excluded_titles: Vec<String>,
+    tags: Vec<String>,
+    excluded_tags: Vec<String>,
+    /// Only pages modified at or after this time.
+    pub modified_after: Option<OffsetDateTime>,
+    /// Only pages modified before this time.
+    pub modified_before: Option<OffsetDateTime>,
+    /// Only pages in the collection with this name.
+    pub collection: Option<String>,
+    /// Words around the matches in excerpts, [`EXCERPT_WORDS`] when `None`.
+    pub crop: Option<usize>,
+    /// Leave the matches in excerpts unmarked, for clients wanting raw text.
+    pub plain: bool,
+}
+
+impl SearchQuery {
+    pub fn parse(query: &str) -> Self {
+        let mut parsed = Self::default();
+
+        for token in query_tokens(query) {
+            let (negated, operator) = match token.strip_prefix('-') {
+                Some(rest) => (true, rest),
+                None => (false, token.as_str()),
+            };
+            let Some((field, value)) = operator.split_once(':') else {
+                parsed.terms.push(token);
+                continue;
+            };
+            let value = unquote(value);
+            if value.is_empty() {
+                parsed.terms.push(token);
+                continue;
+            }
+
+            let (values, value) = match (field, negated) {
+                ("title", false) => (&mut parsed.titles, value.to_string()),
+                ("title", true) => (&mut parsed.excluded_titles, value.to_string()),
+                // Matched against the tags as normalized when pages are read.
+                ("tag", _) => match normalize_tag(value, *load_config().tag_case()) {
+                    Some(tag) if negated => (&mut parsed.excluded_tags, tag),
+                    Some(tag) => (&mut parsed.tags, tag),
+                    None => {
+                        parsed.terms.push(token);
+                        continue;
+                    }
+                },
+                _ => {
+                    parsed.terms.push(token);
+                    continue;
+                }
+            };
+            values.push(value);
+        }
+
+        parsed
+    }
+
+    /// Whether there is nothing to search or filter for.
+    fn is_empty(&self) -> bool {
+        self.text().trim().is_empty() && self.filter_expression().is_none()
+    }
+
+    /// The free text together with the title terms, so that those are ranked
+    /// and highlighted as well.
+    fn text(&self) -> String {
+        self.terms
+            .iter()
+            .chain(&self.titles)
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The tag operators and modified range as a milli filter.
+    fn filter_expression(&self) -> Option<String> {
+        let conditions: Vec<String> = self
+            .tags
+            .iter()
+            .map(|tag| format!("tags = {}", filter_value(tag)))
+            .chain(
+                self.excluded_tags
+                    .iter()
+                    .map(|tag| format!("NOT tags = {}", filter_value(tag))),
+            )
+            .chain(
+                self.modified_after
+                    .map(|after| format!("modified >= {}", after.unix_timestamp())),
+            )
+            .chain(
+                self.modified_before
+                    .map(|before| format!("modified < {}", before.unix_timestamp())),
+            )
+            .chain(
+                self.collection
+                    .iter()
+                    .map(|collection| format!("collection = {}", filter_value(collection))),
+            )
+            .collect();
+
+        (!conditions.is_empty()).then(|| conditions.join(" AND "))
+    }
+}
+
+/// Splits on whitespace, keeping double quoted sections together.
+fn query_tokens(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                token.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .map(|value| value.strip_suffix('"').unwrap_or(value))
+        .unwrap_or(value)
+        .trim()
+}
+
+/// Quotes a value for use in a milli filter expression.
+fn filter_value(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Number of words shown in a result excerpt.
+pub const EXCERPT_WORDS: usize = 20;
+/// Most words a search may ask excerpts to be cropped to.
+const MAX_CROP_WORDS: usize = 200;
+
+/// One page of search hits together with the number of all matching
+/// documents.
+#[derive(Debug, Default, Serialize)]
+pub struct SearchResults {
+    pub hits: Vec<SearchHit>,
+    pub total: u64,
+}
+
 #[derive(Debug, Serialize)]
 pub struct SearchHit {
     id: Ulid,
     url: PathBuf,
     title: String,
     excerpt: String,
+    tags: Vec<String>,
+    modified: Option<OffsetDateTime>,
+    /// Relevance to the query between 0 and 1, only known for search hits.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score: Option<f64>,
 }
 
 impl TryFrom<serde_json::Value> for SearchHit {
@@ -488,34 +1299,77 @@ impl TryFrom<serde_json::Value> for SearchHit {
             .map(|v| v.as_str())
             .unwrap_or(Some(""))
             .unwrap_or_default();
-        let excerpt = value
-            .get("_formatted_markdown")
-            .map(|v| v.as_str())
-            .unwrap_or(Some(""))
+        let excerpt = ["_formatted_description", "_formatted_markdown"]
+            .into_iter()
+            .filter_map(|field| value.get(field).and_then(|v| v.as_str()))
+            .find(|excerpt| !excerpt.trim().is_empty())
             .unwrap_or_default();
 
+        let tags = value
+            .get("tags")
+            .and_then(|v| v.as_array())
+            .map(|tags| sorted_tags(tags.iter().filter_map(|tag| tag.as_str())))
+            .unwrap_or_default();
+        let modified = value
+            .get("modified")
+            .and_then(|v| v.as_i64())
+            .and_then(|timestamp| OffsetDateTime::from_unix_timestamp(timestamp).ok());
+
         Ok(Self {
             id: Ulid::from_str(id)?,
             url: PathBuf::from_str(url)?,
             title: title.into(),
-            excerpt: format_excerpt(excerpt),
+            excerpt: format_excerpt(excerpt, &load_config()),
+            tags,
+            modified,
+            score: None,
         })
     }
 }
 
-lazy_static! {
-    static ref EXCERPT_AMMONIA_CLEANER: Builder<'static> = {
-        let mut builder = Builder::new();
-        builder
-            .tags(std::iter::once("mark").collect())
-            .strip_comments(true)
-            .link_rel(None)
-            .allowed_classes(std::collections::HashMap::new());
-        builder
-    };
+impl From<&Page> for SearchHit {
+    fn from(page: &Page) -> Self {
+        Self::from_page(page, EXCERPT_WORDS)
+    }
+}
+
+impl SearchHit {
+    /// A hit for `page`, with an excerpt of its first `words` words.
+    fn from_page(page: &Page, words: usize) -> Self {
+        Self {
+            id: page.id,
+            url: page.url.clone(),
+            title: page.title.clone().unwrap_or_default(),
+            excerpt: escape_html(&page.excerpt(words)),
+            tags: sorted_tags(&page.tags),
+            modified: Some(page.modified),
+            score: None,
+        }
+    }
+}
+
+/// Sorts tags for stable display, leaving out blank ones.
+pub fn sorted_tags<T: AsRef<str>>(tags: impl IntoIterator<Item = T>) -> Vec<String> {
+    let mut tags: Vec<String> = tags
+        .into_iter()
+        .filter(|tag| !tag.as_ref().trim().is_empty())
+        .map(|tag| tag.as_ref().to_string())
+        .collect();
+    tags.sort();
+    tags
+}
+
+fn excerpt_cleaner(config: &Config) -> Builder<'_> {
+    let mut builder = Builder::new();
+    builder
+        .tags(std::iter::once(config.highlight_tag().as_str()).collect())
+        .strip_comments(true)
+        .link_rel(None);
+    allow_configured_html(&mut builder, config.allowed_html(), HashMap::new());
+    builder
 }
 
-fn format_excerpt(input: &str) -> String {
+fn format_excerpt(input: &str, config: &Config) -> String {
     let parser = Parser::new(input);
     let mut plain_text = String::new();
 
@@ -530,91 +1384,649 @@ fn format_excerpt(input: &str) -> String {
     }
 
     // Format hit markers
-    plain_text = plain_text.replace("⚇", "<mark>").replace("⚉", "</mark>");
+    let tag = config.highlight_tag();
+    plain_text = plain_text
+        .replace(config.highlight_prefix(), &format!("<{tag}>"))
+        .replace(config.highlight_suffix(), &format!("</{tag}>"));
 
     // Drop characters
     plain_text = plain_text.replace(['[', ']'], "");
 
-    EXCERPT_AMMONIA_CLEANER.clean(&plain_text).to_string()
+    excerpt_cleaner(config).clean(&plain_text).to_string()
+}
+
+fn date_html(modified: OffsetDateTime) -> String {
+    format!(
+        r#"
+                <time datetime="{}">{}</time>"#,
+        modified.format(&Rfc3339).unwrap_or_default(),
+        modified
+            .format(format_description!("[year]-[month]-[day]"))
+            .unwrap_or_default()
+    )
+}
+
+fn tags_html(tags: &[String]) -> String {
+    if tags.is_empty() {
+        return String::new();
+    }
+
+    let chips: String = tags
+        .iter()
+        .map(|tag| format!(r#"<li class="tag">{}</li>"#, escape_html(tag)))
+        .collect();
+    format!(
+        r#"
+                <ul class="tags">{chips}</ul>"#
+    )
+}
+
+/// Shown instead of results when the search can not be run, so a broken index
+/// or invalid parameters are not mistaken for a query without matches.
+fn render_search_error(status: StatusCode, query: &str, title: &str, message: &str) -> Response {
+    let html = render_layout(
+        title,
+        "",
+        query,
+        &format!(
+            r#"<h1>{}</h1>
+            <p class="banner banner--error">{}</p>"#,
+            escape_html(title),
+            escape_html(message)
+        ),
+    );
+
+    (status, [(header::CONTENT_TYPE, HTML_CONTENT_TYPE)], html).into_response()
 }
 
 #[derive(Deserialize)]
 struct SearchParams {
+    #[serde(default)]
     q: String,
+    after: Option<String>,
+    before: Option<String>,
+    /// Name of a collection to limit the search to.
+    collection: Option<String>,
+    /// Set to `1` to get the results as JSON, with the relevance score of
+    /// each hit, for tuning the ranking.
+    debug: Option<String>,
+    /// Number of words excerpts are cropped to.
+    crop: Option<String>,
+    /// Set to `false` to leave matches in excerpts unmarked.
+    highlight: Option<String>,
+}
+
+impl SearchParams {
+    fn debug(&self) -> bool {
+        matches!(self.debug.as_deref(), Some("1" | "true"))
+    }
+
+    fn plain(&self) -> bool {
+        matches!(self.highlight.as_deref(), Some("0" | "false"))
+    }
+
+    /// The `crop` word count, or a message describing why it is invalid.
+    fn crop(&self) -> std::result::Result<Option<usize>, String> {
+        self.crop
+            .as_deref()
+            .filter(|value| !value.trim().is_empty())
+            .map(|value| {
+                value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|crop| (1..=MAX_CROP_WORDS).contains(crop))
+                    .ok_or_else(|| {
+                        format!("Invalid crop {value:?}, expected 1 to {MAX_CROP_WORDS} words")
+                    })
+            })
+            .transpose()
+    }
+
+    /// The `after` and `before` bounds on the modified time, or a message
+    /// describing the malformed one.
+    fn modified_range(
+        &self,
+    ) -> std::result::Result<(Option<OffsetDateTime>, Option<OffsetDateTime>), String> {
+        let parse = |name: &str, value: &Option<String>| {
+            value
+                .as_deref()
+                .filter(|value| !value.trim().is_empty())
+                .map(|value| {
+                    parse_date_param(value.trim()).map_err(|_| {
+                        format!("Invalid {name} date {value:?}, expected a date like 2024-01-31")
+                    })
+                })
+                .transpose()
+        };
+
+        Ok((parse("after", &self.after)?, parse("before", &self.before)?))
+    }
+}
+
+/// Parses a date such as `2024-01-31`, taken as midnight UTC, or a full RFC
+/// 3339 timestamp.
+fn parse_date_param(value: &str) -> std::result::Result<OffsetDateTime, time::error::Parse> {
+    OffsetDateTime::parse(value, &Rfc3339).or_else(|_| {
+        Date::parse(value, format_description!("[year]-[month]-[day]"))
+            .map(|date| date.midnight().assume_utc())
+    })
 }
 
-fn render_search_results(query: String, hits: Vec<SearchHit>) -> Html<String> {
-    let mut results_html = String::new();
+fn render_search_results(query: String, results: SearchResults) -> Response {
+    let config = load_config();
+    let results_html: String = results
+        .hits
+        .iter()
+        .map(|hit| render_search_hit(hit, &config))
+        .collect();
 
-    for hit in &hits {
-        let result_html = format!(
-            r#"
+    let title = if query.trim().is_empty() {
+        "Recent pages".to_string()
+    } else {
+        format!("Search results for: {query}")
+    };
+    let content = format!(
+        r#"<h1>{}</h1>
+            <p>Found {} results</p>
+            {}"#,
+        escape_html(&title),
+        results.total,
+        results_html
+    );
+    let html = render_layout(&title, "", &query, &content);
+
+    ([(header::CONTENT_TYPE, HTML_CONTENT_TYPE)], html).into_response()
+}
+
+fn render_search_hit(hit: &SearchHit, config: &Config) -> String {
+    format!(
+        r#"
             <article class="search-result">
                 <h2>
                     <a href="{}">{}</a>
-                </h2>
-                <p>{}</p>
+                </h2>{}
+                <p>{}</p>{}
             </article>
         "#,
-            hit.url.to_string_lossy(),
-            hit.title,
-            hit.excerpt,
+        config.route(&format!("/{}", hit.url.to_string_lossy())),
+        escape_html(&hit.title),
+        hit.modified.map(date_html).unwrap_or_default(),
+        hit.excerpt,
+        tags_html(&hit.tags),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, sync::atomic::AtomicUsize};
+
+    use rayon::iter::IntoParallelIterator;
+
+    use super::*;
+    use crate::config::ConfigParsed;
+
+    fn page(path: &str, id: &str) -> Page {
+        Page::parse(
+            PathBuf::from(path),
+            &format!("---\nid: {id}\n---\nBody"),
+            OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_send_all_surfaces_dropped_receiver() {
+        let (tx, rx) = mpsc::channel(1);
+        drop(rx);
+        assert!(send_all((0..10).into_par_iter(), tx).is_err());
+    }
+
+    #[test]
+    fn test_send_all_counts_sent_items() {
+        let (tx, mut rx) = mpsc::channel(10);
+        assert_eq!(send_all((0..10).into_par_iter(), tx).unwrap(), 10);
+        let mut received = 0;
+        while rx.try_recv().is_ok() {
+            received += 1;
+        }
+        assert_eq!(received, 10);
+    }
+
+    #[tokio::test]
+    async fn test_commit_error_stops_the_page_reader() {
+        let read = Arc::new(AtomicUsize::new(0));
+        let pages = {
+            let read = read.clone();
+            (0..100_000u128).into_par_iter().map(move |i| {
+                read.fetch_add(1, Ordering::SeqCst);
+                let path = format!("/pages/{i}.md");
+                let page = page(&path, &Ulid::from_parts(0, i).to_string());
+                (PathBuf::from(path), Ok(page))
+            })
+        };
+        let mut commits = 0;
+
+        let error = index_pages(pages, IdRegistry::new(true, "id"), |_| {
+            commits += 1;
+            let result = if commits == 2 {
+                Err(anyhow!("disk full"))
+            } else {
+                Ok(())
+            };
+            async move { result }
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(error.to_string(), "disk full");
+        assert_eq!(commits, 2);
+
+        // The reader stopped early and had already ended when the error was
+        // returned, so it reads nothing more.
+        let read_at_error = read.load(Ordering::SeqCst);
+        assert!(read_at_error < 100_000);
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+        assert_eq!(read.load(Ordering::SeqCst), read_at_error);
+    }
+
+    #[tokio::test]
+    async fn test_drafts_are_not_indexed() {
+        let pages = ["published", "draft"].into_par_iter().map(|name| {
+            let path = PathBuf::from(format!("/pages/{name}.md"));
+            let draft = name == "draft";
+            let page = Page::parse(
+                path.clone(),
+                &format!("---\ndraft: {draft}\n---\nBody"),
+                OffsetDateTime::UNIX_EPOCH,
+            );
+            (path, page)
+        });
+        let mut indexed = Vec::new();
+
+        let (total, skipped) = index_pages(pages, IdRegistry::new(true, "id"), |batch| {
+            indexed.extend(batch.into_iter().map(|page| page.path));
+            async { Ok(()) }
+        })
+        .await
+        .unwrap();
+        assert_eq!((total, skipped), (1, 0));
+        assert_eq!(indexed, [PathBuf::from("/pages/published.md")]);
+    }
+
+    #[tokio::test]
+    async fn test_pages_with_nested_urls_are_indexed_by_url() {
+        let dir = tempfile::tempdir().unwrap();
+        let search_index = SearchIndex::new(&dir.path().join("search")).unwrap();
+        let config =
+            Config::from(toml::from_str::<ConfigParsed>("search_primary_key = \"url\"").unwrap());
+        let index = open_index(&dir.path().join("by-url"), &config).unwrap();
+
+        let page = page("/pages/notes/rust/ownership.md", &Ulid::new().to_string());
+        assert!(page.url.to_string_lossy().contains('/'));
+        search_index.commit_batch(vec![page], &index).await.unwrap();
+
+        assert_eq!(index_primary_key("url"), "url_key");
+        assert_eq!(index_primary_key("id"), "id");
+        let key = url_key(Path::new("notes/rust/ownership"));
+        assert_eq!(key.len(), 32);
+        assert!(key.bytes().all(|b| b.is_ascii_hexdigit()));
+        assert_ne!(key, url_key(Path::new("notes-rust/ownership")));
+    }
+
+    #[test]
+    fn test_duplicate_explicit_ids_fail_in_strict_mode() {
+        let id = Ulid::new().to_string();
+        let first = page("/pages/first.md", &id);
+        let second = page("/pages/second.md", &id);
+
+        let mut ids = IdRegistry::new(true, "id");
+        ids.register(&first).unwrap();
+        let error = ids.register(&second).unwrap_err().to_string();
+        assert!(error.contains("/pages/first.md"));
+        assert!(error.contains("/pages/second.md"));
+
+        let mut ids = IdRegistry::new(false, "id");
+        ids.register(&first).unwrap();
+        ids.register(&second).unwrap();
+    }
+
+    #[test]
+    fn test_duplicate_derived_ids_and_urls_are_detected() {
+        let slugged = |path: &str| {
+            Page::parse(
+                PathBuf::from(path),
+                "---\nslug: guides/start\n---\nBody",
+                OffsetDateTime::UNIX_EPOCH,
+            )
+            .unwrap()
+        };
+        let first = slugged("/pages/first.md");
+        let second = slugged("/pages/second.md");
+        assert!(!first.explicit_id);
+
+        let mut ids = IdRegistry::new(true, "id");
+        ids.register(&first).unwrap();
+        let error = ids.register(&second).unwrap_err().to_string();
+        assert!(error.contains(&first.id.to_string()));
+
+        let mut ids = IdRegistry::new(true, "url");
+        ids.register(&first).unwrap();
+        let error = ids.register(&second).unwrap_err().to_string();
+        assert!(error.contains("url /guides/start"));
+        ids.register(&page("/pages/other.md", &Ulid::new().to_string()))
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_truncated_staging_index_is_recreated() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut index = SearchIndex::new(dir.path()).unwrap();
+
+        let data = dir.path().join("staging").join("data.mdb");
+        fs::File::create(&data).unwrap().set_len(16).unwrap();
+        index.recreate_staging().unwrap();
+
+        {
+            let rtxn = index.staging_index.read_txn().unwrap();
+            assert_eq!(index.staging_index.number_of_documents(&rtxn).unwrap(), 0);
+        }
+        assert!(index.is_readable());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_active_index_is_rebuilt() {
+        let dir = tempfile::tempdir().unwrap();
+        let search_index = Arc::new(RwLock::new(SearchIndex::new(dir.path()).unwrap()));
+        let query = SearchQuery::parse("body");
+
+        // Overwritten in place rather than truncated, as the open index keeps
+        // the file mapped into memory.
+        let data = dir.path().join("active").join("data.mdb");
+        let size = fs::metadata(&data).unwrap().len() as usize;
+        fs::File::options()
+            .write(true)
+            .open(&data)
+            .unwrap()
+            .write_all(&vec![0; size])
+            .unwrap();
+        assert!(search_index.read().await.search(&query).await.is_err());
+        assert!(!search_index.read().await.is_readable());
+
+        spawn_recovery(search_index.clone());
+        for _ in 0..100 {
+            let index = search_index.read().await;
+            if index.is_readable() && !index.recovering.load(Ordering::SeqCst) {
+                break;
+            }
+            drop(index);
+            tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+        }
+
+        let index = search_index.read().await;
+        assert!(index.is_readable());
+        assert!(index.search(&query).await.is_ok());
+        // The rebuilt index took over from the corrupt one.
+        assert_eq!(
+            fs::canonicalize(dir.path().join("active")).unwrap(),
+            fs::canonicalize(&index.beta_path).unwrap()
         );
-        results_html.push_str(&result_html);
-    }
-
-    let html = format!(
-        r#"<!DOCTYPE html>
-<html lang="en-US">
-    <head>
-        <meta http-equiv="Content-Type" content="text/html; charset=UTF-8">
-        <meta http-equiv="X-UA-Compatible" content="IE=Edge">
-        <meta name="viewport" content="width=device-width,initial-scale=1">
-        <title>Search results for: {}</title>
-        <style>
-            @view-transition {{
-                navigation: auto;
-            }}
-
-            ::view-transition-old(root),
-            ::view-transition-new(root),
-            ::view-transition-old(article),
-            ::view-transition-new(article) {{
-                animation-duration: 50ms;
-                animation-timing-function: ease-in-out;
-            }}
-
-            article {{
-                view-transition-name: article;
-            }}
-        </style>
-        <link rel="stylesheet" href="{}">
-        <script type="module" src="{}"></script>
-    </head>
-    <body>
-        <main>
-            <search>
-                <form method="get" action="/search">
-                    <label for="search">Search</label>
-                    <input id="search" type="search" name="q" value="{}">
-                    <button>Search</button>
-                </form>
-            </search>
-
-            <h1>Search results for: {}</h1>
-            <p>Found {} results</p>
-            {}
-        </main>
-    </body>
-</html>"#,
-        &query,
-        ASSET_MANAGER.hashed_route("styles.css").unwrap_or_default(),
-        ASSET_MANAGER.hashed_route("script.js").unwrap_or_default(),
-        &query,
-        &query,
-        hits.len(),
-        results_html
-    );
+    }
+
+    #[test]
+    fn test_format_excerpt_uses_configured_highlight() {
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>(
+                r#"
+                highlight_prefix = "{{"
+                highlight_suffix = "}}"
+                highlight_tag = "<strong>"
+                "#,
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            format_excerpt("Find {{this}} word", &config),
+            "Find <strong>this</strong> word "
+        );
+
+        let config = Config::from(ConfigParsed::default());
+        let excerpt = format_excerpt("Find \u{E000}this\u{E001} ⚇word⚉", &config);
+        assert_eq!(excerpt, "Find <mark>this</mark> ⚇word⚉ ");
+    }
+
+    #[test]
+    fn test_recent_pages_newest_first_without_drafts() {
+        let page = |path: &str, frontmatter: &str, modified: i64| {
+            Page::parse(
+                PathBuf::from(path),
+                &format!("---\n{frontmatter}\n---\nBody"),
+                OffsetDateTime::from_unix_timestamp(modified).unwrap(),
+            )
+            .unwrap()
+        };
+        let pages = vec![
+            page("/pages/old.md", "title: Old", 1),
+            page("/pages/draft.md", "title: Draft\ndraft: true", 3),
+            page("/pages/new.md", "title: New", 2),
+        ];
+
+        let results = recent_pages(pages, 1, EXCERPT_WORDS);
+        assert_eq!(results.total, 2);
+        let titles: Vec<_> = results.hits.iter().map(|hit| hit.title.as_str()).collect();
+        assert_eq!(titles, ["New"]);
+    }
+
+    #[tokio::test]
+    async fn test_search_results_render_complete_page() {
+        let pages = vec![
+            page("/pages/first.md", &Ulid::new().to_string()),
+            page("/pages/second.md", &Ulid::new().to_string()),
+        ];
+        let response =
+            render_search_results("query".into(), recent_pages(pages, 10, EXCERPT_WORDS));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<p>Found 2 results</p>"));
+        assert_eq!(
+            html.matches(r#"<article class="search-result">"#).count(),
+            2
+        );
+        assert!(html.ends_with("</html>"));
+    }
+
+    #[test]
+    fn test_search_unavailable_is_not_an_empty_result() {
+        let response = render_search_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "query",
+            "Search unavailable",
+            "Search is temporarily unavailable, please try again later.",
+        );
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            HTML_CONTENT_TYPE
+        );
+    }
+
+    #[test]
+    fn test_parse_search_query() {
+        let query = SearchQuery::parse(
+            r#"install title:setup -tag:deprecated tag:"getting started" -legacy"#,
+        );
+        assert_eq!(query.terms, ["install", "-legacy"]);
+        assert_eq!(query.titles, ["setup"]);
+        assert_eq!(query.tags, ["getting started"]);
+        assert_eq!(query.excluded_tags, ["deprecated"]);
+        assert_eq!(query.text(), "install -legacy setup");
+        assert_eq!(
+            query.filter_expression().as_deref(),
+            Some(r#"tags = "getting started" AND NOT tags = "deprecated""#)
+        );
+
+        let query = SearchQuery::parse("-title:draft");
+        assert_eq!(query.excluded_titles, ["draft"]);
+        assert_eq!(query.filter_expression(), None);
+    }
+
+    #[test]
+    fn test_unknown_operators_are_literal_text() {
+        let query = SearchQuery::parse("https://example.com author:me tag: title:");
+        assert_eq!(
+            query.terms,
+            ["https://example.com", "author:me", "tag:", "title:"]
+        );
+        assert!(query.tags.is_empty());
+        assert!(query.titles.is_empty());
+    }
+
+    #[test]
+    fn test_filter_values_are_escaped() {
+        let query = SearchQuery::parse(r#"tag:"a\b" tag:x"y"#);
+        assert_eq!(
+            query.filter_expression().as_deref(),
+            Some(r#"tags = "a\\b" AND tags = "x\"y""#)
+        );
+    }
+
+    #[test]
+    fn test_collection_filter() {
+        let mut query = SearchQuery::parse("tag:guide");
+        query.collection = Some("docs".into());
+        assert_eq!(
+            query.filter_expression().as_deref(),
+            Some(r#"tags = "guide" AND collection = "docs""#)
+        );
+    }
+
+    #[test]
+    fn test_modified_range_filter() {
+        let params = |after: &str, before: &str| SearchParams {
+            q: "tag:changelog".into(),
+            after: Some(after.into()),
+            before: Some(before.into()),
+            collection: None,
+            debug: None,
+            crop: None,
+            highlight: None,
+        };
+
+        let (after, before) = params("2024-01-01", "2024-06-01T12:00:00Z")
+            .modified_range()
+            .unwrap();
+        let mut query = SearchQuery::parse("tag:changelog");
+        query.modified_after = after;
+        query.modified_before = before;
+        assert_eq!(
+            query.filter_expression().as_deref(),
+            Some(r#"tags = "changelog" AND modified >= 1704067200 AND modified < 1717243200"#)
+        );
+
+        let (after, before) = params("", " ").modified_range().unwrap();
+        assert_eq!((after, before), (None, None));
+
+        let error = params("2024-13-01", "").modified_range().unwrap_err();
+        assert!(error.contains("after"));
+        assert!(params("", "yesterday").modified_range().is_err());
+    }
+
+    #[test]
+    fn test_crop_and_highlight_params() {
+        let params = |crop: Option<&str>, highlight: Option<&str>| SearchParams {
+            q: "query".into(),
+            after: None,
+            before: None,
+            collection: None,
+            debug: None,
+            crop: crop.map(str::to_string),
+            highlight: highlight.map(str::to_string),
+        };
+
+        assert_eq!(params(None, None).crop(), Ok(None));
+        assert_eq!(params(Some(" "), None).crop(), Ok(None));
+        assert_eq!(params(Some("5"), None).crop(), Ok(Some(5)));
+        assert_eq!(params(Some("200"), None).crop(), Ok(Some(200)));
+        for crop in ["0", "201", "-1", "ten"] {
+            assert!(params(Some(crop), None).crop().unwrap_err().contains(crop));
+        }
+
+        assert!(!params(None, None).plain());
+        assert!(!params(None, Some("true")).plain());
+        assert!(params(None, Some("false")).plain());
+        assert!(params(None, Some("0")).plain());
+    }
+
+    #[test]
+    fn test_date_range_alone_is_a_search() {
+        let mut query = SearchQuery::parse(" ");
+        assert!(query.is_empty());
+        query.modified_after = Some(OffsetDateTime::UNIX_EPOCH);
+        assert!(!query.is_empty());
+    }
 
-    Html(html)
+    #[test]
+    fn test_hit_excerpt_prefers_description() {
+        let hit = SearchHit::try_from(serde_json::json!({
+            "id": Ulid::new().to_string(),
+            "url": "notes/rust",
+            "_formatted_description": "A \u{E000}short\u{E001} summary",
+            "_formatted_markdown": "The long body",
+        }))
+        .unwrap();
+        assert_eq!(hit.excerpt, "A <mark>short</mark> summary ");
+
+        let hit = SearchHit::try_from(serde_json::json!({
+            "id": Ulid::new().to_string(),
+            "url": "notes/rust",
+            "_formatted_description": "",
+            "_formatted_markdown": "The long body",
+        }))
+        .unwrap();
+        assert_eq!(hit.excerpt, "The long body ");
+
+        let page = Page::parse(
+            PathBuf::from("/pages/notes.md"),
+            "---\ndescription: A summary\n---\nThe long body",
+            OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap();
+        assert_eq!(SearchHit::from(&page).excerpt, "A summary");
+    }
+
+    #[test]
+    fn test_hit_from_index_document() {
+        let hit = SearchHit::try_from(serde_json::json!({
+            "id": Ulid::new().to_string(),
+            "url": "notes/rust",
+            "title": "Rust",
+            "modified": 0,
+            "tags": ["rust", "", "axum"],
+        }))
+        .unwrap();
+        assert_eq!(hit.tags, ["axum", "rust"]);
+        assert_eq!(hit.modified, Some(OffsetDateTime::UNIX_EPOCH));
+
+        let hit = SearchHit::try_from(serde_json::json!({
+            "id": Ulid::new().to_string(),
+            "url": "notes/rust",
+        }))
+        .unwrap();
+        assert!(hit.tags.is_empty());
+        assert_eq!(hit.modified, None);
+        assert_eq!(tags_html(&hit.tags), "");
+    }
+
+    #[test]
+    fn test_score_only_in_debug_output() {
+        let mut hit = SearchHit::from(&page("/pages/notes.md", &Ulid::new().to_string()));
+        assert!(serde_json::to_value(&hit).unwrap().get("score").is_none());
+
+        hit.score = Some(0.75);
+        assert_eq!(serde_json::to_value(&hit).unwrap()["score"], 0.75);
+        assert!(!render_search_hit(&hit, &Config::from(ConfigParsed::default())).contains("0.75"));
+    }
 }
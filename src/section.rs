@@ -0,0 +1,184 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::SystemTime,
+};
+
+use lazy_static::lazy_static;
+use serde::Deserialize;
+
+use crate::page::PageRoot;
+
+/// Name of the files holding section settings in page directories.
+pub const SECTION_FILE: &str = "_section.toml";
+
+/// A parsed section file along with the modification time it was read at,
+/// both `None` for missing files.
+type CachedSection = (Option<SystemTime>, Option<SectionConfig>);
+
+lazy_static! {
+    static ref SECTIONS: RwLock<HashMap<PathBuf, CachedSection>> = RwLock::new(HashMap::new());
+}
+
+/// Defaults for the pages in a directory and its subdirectories, read from
+/// `_section.toml` files.
+///
+/// Settings are resolved in order of precedence from the page frontmatter, the
+/// section file nearest to the page, the section files of the directories
+/// above it up to the pages root and finally the built in defaults.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+pub struct SectionConfig {
+    /// Tags for pages that set none in their frontmatter.
+    pub tags: Option<HashSet<String>>,
+    /// Layout hint, added as a `layout-<name>` class to the page article.
+    pub layout: Option<String>,
+    /// Whether pages show their modification date, defaults to false.
+    pub show_dates: Option<bool>,
+    /// Whether pages are linked as previous and next pages, defaults to true.
+    pub navigation: Option<bool>,
+    /// Shown after page titles in place of the site title, empty for none.
+    pub title_suffix: Option<String>,
+}
+
+impl SectionConfig {
+    /// Settings for the page at `path`, a file below the pages root or the
+    /// directory of a collection.
+    pub fn for_page(path: &Path) -> Self {
+        let Some(root) = PageRoot::for_path(path) else {
+            return Self::default();
+        };
+        path.parent()
+            .map(|dir| Self::for_dir(dir, &root.path))
+            .unwrap_or_default()
+    }
+
+    /// Merges the section files in `dir` and the directories above it up to
+    /// and including `root`, nearer files taking precedence.
+    fn for_dir(dir: &Path, root: &Path) -> Self {
+        dir.ancestors()
+            .take_while(|dir| dir.starts_with(root))
+            .filter_map(|dir| cached_section(&dir.join(SECTION_FILE)))
+            .fold(Self::default(), |section, parent| section.or(parent))
+    }
+
+    /// Keeps the settings set in `self`, taking the rest from `parent`.
+    fn or(self, parent: Self) -> Self {
+        Self {
+            tags: self.tags.or(parent.tags),
+            layout: self.layout.or(parent.layout),
+            show_dates: self.show_dates.or(parent.show_dates),
+            navigation: self.navigation.or(parent.navigation),
+            title_suffix: self.title_suffix.or(parent.title_suffix),
+        }
+    }
+
+    pub fn show_dates(&self) -> bool {
+        self.show_dates.unwrap_or(false)
+    }
+
+    pub fn in_navigation(&self) -> bool {
+        self.navigation.unwrap_or(true)
+    }
+}
+
+/// Reads a section file, reusing the parsed copy while its modification time
+/// is unchanged.
+fn cached_section(path: &Path) -> Option<SectionConfig> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+    if let Some((cached_modified, section)) =
+        SECTIONS.read().unwrap_or_else(|e| e.into_inner()).get(path)
+    {
+        if *cached_modified == modified {
+            return section.clone();
+        }
+    }
+
+    let section = modified.and_then(|_| read_section(path));
+    SECTIONS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(path.to_path_buf(), (modified, section.clone()));
+    section
+}
+
+fn read_section(path: &Path) -> Option<SectionConfig> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| tracing::warn!("⚠️ Unable to read {}: {}", path.to_string_lossy(), e))
+        .ok()?;
+    toml::from_str(&content)
+        .map_err(|e| tracing::warn!("⚠️ Invalid section file {}: {}", path.to_string_lossy(), e))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn test_nearer_sections_take_precedence() {
+        let root = tempfile::Builder::new().prefix("pages").tempdir().unwrap();
+        let blog = root.path().join("blog");
+        let posts = blog.join("posts");
+        fs::create_dir_all(&posts).unwrap();
+        fs::write(
+            root.path().join(SECTION_FILE),
+            "tags = [\"site\"]\nshow_dates = false\nlayout = \"plain\"",
+        )
+        .unwrap();
+        fs::write(
+            blog.join(SECTION_FILE),
+            "tags = [\"blog\"]\nshow_dates = true\nnavigation = false",
+        )
+        .unwrap();
+
+        let section = SectionConfig::for_dir(&posts, root.path());
+        assert_eq!(section.tags, Some(HashSet::from(["blog".to_string()])));
+        assert_eq!(section.layout.as_deref(), Some("plain"));
+        assert!(section.show_dates());
+        assert!(!section.in_navigation());
+
+        let section = SectionConfig::for_dir(root.path(), root.path());
+        assert!(!section.show_dates());
+        assert!(section.in_navigation());
+    }
+
+    #[test]
+    fn test_sections_outside_root_are_ignored() {
+        let outside = tempfile::Builder::new().prefix("pages").tempdir().unwrap();
+        let root = outside.path().join("pages");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(outside.path().join(SECTION_FILE), "layout = \"outside\"").unwrap();
+
+        assert_eq!(
+            SectionConfig::for_dir(&root, &root),
+            SectionConfig::default()
+        );
+    }
+
+    #[test]
+    fn test_changed_section_files_are_reread() {
+        let root = tempfile::Builder::new().prefix("pages").tempdir().unwrap();
+        let path = root.path().join(SECTION_FILE);
+        fs::write(&path, "layout = \"first\"").unwrap();
+        assert_eq!(
+            cached_section(&path).and_then(|section| section.layout),
+            Some("first".to_string())
+        );
+
+        fs::write(&path, "layout = \"second\"").unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(
+            cached_section(&path).and_then(|section| section.layout),
+            Some("second".to_string())
+        );
+    }
+}
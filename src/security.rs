@@ -4,15 +4,48 @@ use axum::{
     middleware::Next,
 };
 
+tokio::task_local! {
+    /// Nonce of the request being handled, allowing the inline styles and
+    /// scripts rendered for it.
+    static CSP_NONCE: String;
+}
+
+/// Nonce for the `nonce` attribute of inline `<style>` and `<script>`
+/// elements, `None` outside of a request.
+pub fn csp_nonce() -> Option<String> {
+    CSP_NONCE.try_with(Clone::clone).ok()
+}
+
+/// A random nonce of 128 bits, hex encoded.
+fn generate_nonce() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).expect("the system random number generator is available");
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// The content security policy allowing inline styles and scripts only when
+/// they carry `nonce`.
+pub fn content_security_policy(nonce: &str) -> HeaderValue {
+    HeaderValue::from_str(&format!(
+        "default-src 'self'; script-src 'self' 'wasm-unsafe-eval' 'nonce-{nonce}'; style-src 'self' 'nonce-{nonce}'; img-src 'self' data:; font-src 'self'; frame-ancestors 'none'; form-action 'self'; base-uri 'self';"
+    ))
+    .unwrap()
+}
+
+/// Adds the security headers, generating a nonce for each request that the
+/// HTML rendered while handling it can use through [`csp_nonce`]. Responses
+/// that already carry a content security policy keep it, as cached pages must
+/// keep the one matching the nonce in their body.
 pub async fn add_security_headers(request: Request<Body>, next: Next) -> Response<Body> {
-    let mut response = next.run(request).await;
+    let nonce = generate_nonce();
+    let mut response = CSP_NONCE.scope(nonce.clone(), next.run(request)).await;
     let headers = response.headers_mut();
 
+    headers
+        .entry(header::CONTENT_SECURITY_POLICY)
+        .or_insert_with(|| content_security_policy(&nonce));
+
     let security_headers = [
-        (
-            header::CONTENT_SECURITY_POLICY,
-            "default-src 'self'; script-src 'self' 'wasm-unsafe-eval'; style-src 'self' 'unsafe-inline'; img-src 'self' data:; font-src 'self'; frame-ancestors 'none'; form-action 'self'; base-uri 'self';",
-        ),
         (
             header::STRICT_TRANSPORT_SECURITY,
             "max-age=31536000; includeSubDomains",
@@ -44,3 +77,66 @@ pub async fn add_security_headers(request: Request<Body>, next: Next) -> Respons
 
     response
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, middleware, routing::get};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn nonce_in(policy: &str) -> &str {
+        policy
+            .split("'nonce-")
+            .nth(1)
+            .and_then(|rest| rest.split('\'').next())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_nonce_is_shared_with_handler_and_unique_per_request() {
+        let app = Router::new()
+            .route("/", get(|| async { csp_nonce().unwrap_or_default() }))
+            .route(
+                "/own",
+                get(|| async {
+                    (
+                        [(header::CONTENT_SECURITY_POLICY, "default-src 'none'")],
+                        "",
+                    )
+                }),
+            )
+            .layer(middleware::from_fn(add_security_headers));
+
+        let mut nonces = Vec::new();
+        for _ in 0..2 {
+            let response = app
+                .clone()
+                .oneshot(Request::get("/").body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let policy = response.headers()[header::CONTENT_SECURITY_POLICY]
+                .to_str()
+                .unwrap()
+                .to_string();
+            assert!(!policy.contains("unsafe-inline"), "{policy}");
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(nonce_in(&policy), body);
+            assert_eq!(body.len(), 32);
+            nonces.push(body);
+        }
+        assert_ne!(nonces[0], nonces[1]);
+
+        let response = app
+            .oneshot(Request::get("/own").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[header::CONTENT_SECURITY_POLICY],
+            "default-src 'none'"
+        );
+        assert_eq!(csp_nonce(), None);
+    }
+}
@@ -0,0 +1,222 @@
+use axum::{
+    Router,
+    extract::Path,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use rayon::iter::ParallelIterator;
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::{
+    config::{Config, load_config},
+    page::Page,
+    web::escape_html,
+};
+
+const URLSET_START: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+"#;
+const URLSET_END: &str = "</urlset>\n";
+
+pub fn sitemap_routes() -> Router {
+    Router::new()
+        .route("/sitemap.xml", get(sitemap_handler))
+        .route("/sitemap-{shard}", get(shard_handler))
+}
+
+/// Serves all pages as one sitemap, or a sitemap index of the
+/// `sitemap-N.xml` shards when there are more than `sitemap_shard_size`.
+async fn sitemap_handler() -> Response {
+    let config = load_config();
+    let entries = listed_entries().await;
+
+    let shards = shard_count(entries.len(), *config.sitemap_shard_size());
+    if shards < 2 {
+        return xml_response(render_urlset(&entries, &config));
+    }
+
+    let mut lastmods = vec![None; shards];
+    for entry in &entries {
+        let lastmod = &mut lastmods[(entry.key % shards as u64) as usize];
+        *lastmod = (*lastmod).max(Some(entry.modified));
+    }
+    xml_response(render_sitemap_index(&lastmods, &config))
+}
+
+async fn shard_handler(Path(shard): Path<String>) -> Response {
+    let Some(number) = shard
+        .strip_suffix(".xml")
+        .and_then(|number| number.parse::<usize>().ok())
+        .filter(|number| *number > 0)
+    else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let config = load_config();
+    let mut entries = listed_entries().await;
+    let shards = shard_count(entries.len(), *config.sitemap_shard_size());
+    if shards < 2 || number > shards {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    entries.retain(|entry| entry.key % shards as u64 == number as u64 - 1);
+    xml_response(render_urlset(&entries, &config))
+}
+
+fn xml_response(body: String) -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+        body,
+    )
+        .into_response()
+}
+
+/// What the sitemaps list of a page, small enough to keep for every page.
+struct SitemapEntry {
+    /// Stable hash of the page URL deciding which shard lists the page.
+    key: u64,
+    url: String,
+    modified: OffsetDateTime,
+}
+
+impl From<&Page> for SitemapEntry {
+    fn from(page: &Page) -> Self {
+        let url = page.url.to_string_lossy().into_owned();
+        Self {
+            key: xxh3_64(url.as_bytes()),
+            url,
+            modified: page.modified,
+        }
+    }
+}
+
+/// Reads the published pages that allow indexing, in a single pass off the
+/// async runtime.
+async fn listed_entries() -> Vec<SitemapEntry> {
+    Page::read_blocking(|| {
+        Page::all()
+            .filter(|page| !page.draft && page.is_indexable())
+            .map(|page| SitemapEntry::from(&page))
+            .collect()
+    })
+    .await
+}
+
+/// Number of sitemap files needed for `count` pages, spread over the shards
+/// by [`SitemapEntry::key`] so each holds about `shard_size` of them.
+fn shard_count(count: usize, shard_size: usize) -> usize {
+    count.div_ceil(shard_size)
+}
+
+fn render_urlset(entries: &[SitemapEntry], config: &Config) -> String {
+    let mut xml = String::from(URLSET_START);
+    for entry in entries {
+        xml.push_str(&render_url(entry, config));
+    }
+    xml.push_str(URLSET_END);
+    xml
+}
+
+fn render_url(entry: &SitemapEntry, config: &Config) -> String {
+    format!(
+        "    <url>\n        <loc>{}</loc>\n        <lastmod>{}</lastmod>\n    </url>\n",
+        escape_html(&config.absolute_url(&format!("/{}", entry.url))),
+        entry.modified.format(&Rfc3339).unwrap_or_default()
+    )
+}
+
+/// Renders the sitemap index listing one `sitemap-N.xml` file per entry of
+/// `lastmods`, the latest modification time of the pages in that shard.
+fn render_sitemap_index(lastmods: &[Option<OffsetDateTime>], config: &Config) -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+"#,
+    );
+
+    for (index, lastmod) in lastmods.iter().enumerate() {
+        xml.push_str(&format!(
+            "    <sitemap>\n        <loc>{}</loc>\n",
+            escape_html(&config.absolute_url(&format!("/sitemap-{}.xml", index + 1)))
+        ));
+        if let Some(lastmod) = lastmod.and_then(|lastmod| lastmod.format(&Rfc3339).ok()) {
+            xml.push_str(&format!("        <lastmod>{lastmod}</lastmod>\n"));
+        }
+        xml.push_str("    </sitemap>\n");
+    }
+
+    xml.push_str("</sitemapindex>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+    use time::{Duration, OffsetDateTime};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::config::ConfigParsed;
+
+    fn page(path: &str, frontmatter: &str) -> Page {
+        Page::parse(
+            path.into(),
+            &format!("---\n{frontmatter}\n---\n# Page"),
+            OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap()
+    }
+
+    fn config() -> Config {
+        Config::from(
+            toml::from_str::<ConfigParsed>(r#"base_url = "https://example.com/""#).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_sitemap_excludes_noindex_pages() {
+        let config = config();
+        let visible = page("visible", "title: Visible");
+        let hidden = page("hidden", "robots: noindex");
+
+        assert!(visible.is_indexable());
+        assert!(!hidden.is_indexable());
+        assert!(
+            render_url(&SitemapEntry::from(&visible), &config)
+                .contains("<loc>https://example.com/visible</loc>")
+        );
+    }
+
+    #[test]
+    fn test_shard_count() {
+        assert_eq!(shard_count(0, 10), 0);
+        assert_eq!(shard_count(10, 10), 1);
+        assert_eq!(shard_count(11, 10), 2);
+        assert_eq!(shard_count(95, 10), 10);
+    }
+
+    #[test]
+    fn test_sitemap_index_lists_shards() {
+        let modified = OffsetDateTime::UNIX_EPOCH + Duration::days(1);
+        let xml = render_sitemap_index(&[Some(modified), None], &config());
+
+        assert!(xml.contains("<sitemapindex"));
+        assert!(xml.contains(
+            "<loc>https://example.com/sitemap-1.xml</loc>\n        <lastmod>1970-01-02T00:00:00Z</lastmod>"
+        ));
+        assert!(xml.contains("<loc>https://example.com/sitemap-2.xml</loc>\n    </sitemap>"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_shards_are_not_found() {
+        for uri in ["/sitemap-0.xml", "/sitemap-abc.xml", "/sitemap-1.txt"] {
+            let response = sitemap_routes()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{uri}");
+        }
+    }
+}
@@ -1,31 +1,62 @@
-use std::{collections::HashSet, sync::Arc};
+use std::{
+    collections::HashSet,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
 
 use axum::{
-    Router,
+    Router, ServiceExt,
     body::Body,
-    extract::{Path, Query, Request},
-    http::{HeaderValue, StatusCode},
+    extract::{Path, Request},
+    http::{Extensions, HeaderMap, HeaderValue, Method, StatusCode, Version},
     middleware::{self, Next},
-    response::{Html, IntoResponse, Json, Response},
+    response::{IntoResponse, Json, Response},
     routing::get,
 };
-use axum_response_cache::CacheLayer;
 use hyper::header;
 use serde::{Deserialize, Serialize};
-use time::OffsetDateTime;
+use serde_json::{Map, Value};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339, macros::format_description};
 use tokio::sync::RwLock;
-use tower_http::compression::CompressionLayer;
+use tower::{Layer, limit::ConcurrencyLimitLayer};
+use tower_http::{
+    CompressionLevel,
+    catch_panic::CatchPanicLayer,
+    compression::{
+        CompressionLayer,
+        predicate::{NotForContentType, Predicate, SizeAbove},
+    },
+    timeout::TimeoutLayer,
+};
 use ulid::Ulid;
+use xxhash_rust::xxh3::xxh3_64;
 
 use crate::{
-    assets::{ASSET_MANAGER, asset_routes},
-    config::{Config, load_config},
-    error_handler::error_handler,
-    page::Page,
-    search::{SearchIndex, search_route},
-    security::add_security_headers,
+    access_log::log_access,
+    admin::admin_routes,
+    assets::{ASSET_MANAGER, asset_routes, image_mime_type},
+    config::{
+        Config, ConfigCompressionLevel, ConfigCompressionLevelName, ConfigEncoding, load_config,
+    },
+    error_handler::{ValidQuery, error_handler, handle_panic, render_error_page},
+    feed::feed_routes,
+    gone::is_gone,
+    listing::{author_route, listing_routes},
+    llms::llms_routes,
+    navigation::{Adjacent, PageLink, adjacent_pages},
+    og_image::{OG_IMAGE_HEIGHT, OG_IMAGE_WIDTH, og_image_route, og_image_routes},
+    page::{Page, Translation, split_language},
+    page_index::page_index_routes,
+    response_cache::{ResponseCache, cache_responses},
+    search::{EXCERPT_WORDS, SearchIndex, search_route},
+    section::SectionConfig,
+    security::{add_security_headers, content_security_policy, csp_nonce},
+    sitemap::sitemap_routes,
+    webmanifest::{WEB_MANIFEST_PATH, webmanifest_routes},
 };
 
+pub const HTML_CONTENT_TYPE: &str = "text/html; charset=utf-8";
+
 #[derive(Debug, Deserialize)]
 struct QueryParams {
     q: Option<String>,
@@ -37,6 +68,7 @@ struct QueryParams {
 enum Mode {
     Fragment,
     Edit,
+    Meta,
 }
 
 #[derive(Debug, Serialize)]
@@ -44,66 +76,755 @@ enum Mode {
 struct Fragment {
     id: Ulid,
     title: Option<String>,
+    excerpt: String,
     html: String,
     #[serde(with = "time::serde::iso8601")]
     modified: OffsetDateTime,
     tags: HashSet<String>,
+    author: Option<String>,
+    extra: Map<String, Value>,
+    previous: Option<PageLink>,
+    next: Option<PageLink>,
 }
 
-pub async fn start_server(
-    config: &Config,
-    search_index: Arc<RwLock<SearchIndex>>,
-) -> anyhow::Result<()> {
-    let compression_layer = CompressionLayer::new()
-        .gzip(true)
-        .deflate(true)
-        .br(true)
-        .zstd(true);
+/// Metadata of a page without its body, for link previews.
+#[derive(Debug, Serialize)]
+struct PageMeta {
+    id: Ulid,
+    title: Option<String>,
+    excerpt: String,
+    #[serde(with = "time::serde::iso8601")]
+    modified: OffsetDateTime,
+    tags: HashSet<String>,
+    reading_minutes: usize,
+}
 
-    let app = Router::new()
+/// All routes of the site, below any configured base path.
+fn routes(search_index: Arc<RwLock<SearchIndex>>) -> Router {
+    // Renders are shared for a moment, keyed on the request headers pages
+    // are negotiated on as well as the URI.
+    let page_cache = ResponseCache::with_lifespan(Duration::from_secs(1));
+
+    Router::new()
         .merge(asset_routes())
+        .merge(admin_routes(search_index.clone()))
         .merge(search_route(search_index))
-        .route("/", get(page_handler).layer(CacheLayer::with_lifespan(1)))
+        .merge(feed_routes())
+        .merge(listing_routes())
+        .merge(llms_routes())
+        .merge(og_image_routes())
+        .merge(page_index_routes())
+        .merge(sitemap_routes())
+        .merge(webmanifest_routes())
+        .route(
+            "/",
+            get(page_handler).layer(middleware::from_fn_with_state(
+                page_cache.clone(),
+                cache_responses,
+            )),
+        )
         .route(
             "/{*path}",
-            get(page_handler).layer(CacheLayer::with_lifespan(1)),
+            get(page_handler).layer(middleware::from_fn_with_state(page_cache, cache_responses)),
         )
+}
+
+pub async fn start_server(
+    config: &Config,
+    search_index: Arc<RwLock<SearchIndex>>,
+) -> anyhow::Result<()> {
+    let routes = routes(search_index);
+
+    let app = if config.base_path().is_empty() {
+        routes
+    } else {
+        Router::new().nest(config.base_path(), routes)
+    };
+
+    let app = app
+        .layer(CatchPanicLayer::custom(handle_panic))
+        .layer(TimeoutLayer::with_status_code(
+            StatusCode::REQUEST_TIMEOUT,
+            *config.request_timeout(),
+        ))
         .layer(middleware::from_fn(error_handler))
         .layer(middleware::from_fn(add_security_headers))
-        .layer(middleware::from_fn(add_performance_headers))
-        .layer(compression_layer);
+        .layer(middleware::from_fn(add_performance_headers));
+
+    let app = add_compression(app, config)
+        .layer(middleware::from_fn(log_access))
+        .layer(ConcurrencyLimitLayer::new(
+            *config.max_concurrent_requests(),
+        ));
+
+    // Wraps the router rather than being one of its layers, as the router only
+    // adds the `Allow` header once the response has passed through those.
+    let app = middleware::from_fn(answer_options).layer(app);
 
     let address = format!("0.0.0.0:{}", config.port());
     let listener = tokio::net::TcpListener::bind(&address).await?;
 
     tracing::info!("🚀 Starting website server at: http://{address}");
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app.into_make_service()).await?;
 
     Ok(())
 }
 
+/// Answers `OPTIONS` requests with `204 No Content` and the methods the route
+/// supports in the `Allow` header, taken from the `405 Method Not Allowed`
+/// response of the router. Unknown routes are left alone.
+async fn answer_options(request: Request<Body>, next: Next) -> Response {
+    if request.method() != Method::OPTIONS {
+        return next.run(request).await;
+    }
+
+    let response = next.run(request).await;
+    if response.status() != StatusCode::METHOD_NOT_ALLOWED {
+        return response;
+    }
+
+    let mut methods: Vec<String> = response
+        .headers()
+        .get_all(header::ALLOW)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|method| method.trim().to_string())
+        .filter(|method| !method.is_empty())
+        .collect();
+    methods.push(Method::OPTIONS.to_string());
+
+    match HeaderValue::from_str(&methods.join(", ")) {
+        Ok(allow) => (StatusCode::NO_CONTENT, [(header::ALLOW, allow)]).into_response(),
+        Err(_) => response,
+    }
+}
+
+/// Adds one compression layer per configured encoding, innermost first. A
+/// layer leaves responses alone that an inner layer already compressed, so
+/// each encoding gets its own level and the first one the client accepts wins.
+/// The `Vary` headers of the responses are merged afterwards.
+fn add_compression(mut app: Router, config: &Config) -> Router {
+    let predicate = SizeAbove::new(*config.compression_min_size())
+        .and(NotForContentType::GRPC)
+        .and(NotForContentType::SSE)
+        .and(
+            |_: StatusCode, _: Version, headers: &HeaderMap, _: &Extensions| {
+                !headers
+                    .get(header::CONTENT_TYPE)
+                    .and_then(media_type)
+                    .is_some_and(|media_type| is_precompressed(&media_type))
+            },
+        );
+
+    for encoding in config.compression_encodings() {
+        let level =
+            config
+                .compression_levels()
+                .get(encoding)
+                .map_or(CompressionLevel::Default, |level| match level {
+                    ConfigCompressionLevel::Named(ConfigCompressionLevelName::Fastest) => {
+                        CompressionLevel::Fastest
+                    }
+                    ConfigCompressionLevel::Named(ConfigCompressionLevelName::Default) => {
+                        CompressionLevel::Default
+                    }
+                    ConfigCompressionLevel::Named(ConfigCompressionLevelName::Best) => {
+                        CompressionLevel::Best
+                    }
+                    ConfigCompressionLevel::Precise(level) => CompressionLevel::Precise(*level),
+                });
+        let layer = CompressionLayer::new()
+            .gzip(*encoding == ConfigEncoding::Gzip)
+            .deflate(*encoding == ConfigEncoding::Deflate)
+            .br(*encoding == ConfigEncoding::Br)
+            .zstd(*encoding == ConfigEncoding::Zstd)
+            .quality(level)
+            .compress_when(predicate.clone());
+        app = app.layer(layer);
+    }
+
+    app.layer(middleware::from_fn(merge_vary))
+}
+
+/// Folds the `Vary` headers added by content negotiation and compression into
+/// one, so shared caches key on all of them. Compressed responses always vary
+/// on `Accept-Encoding`.
+async fn merge_vary(request: Request<Body>, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+
+    let mut fields: Vec<String> = Vec::new();
+    for field in headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+    {
+        if !fields.iter().any(|known| known.eq_ignore_ascii_case(field)) {
+            fields.push(field.to_string());
+        }
+    }
+    if headers.contains_key(header::CONTENT_ENCODING)
+        && !fields
+            .iter()
+            .any(|field| field.eq_ignore_ascii_case("accept-encoding"))
+    {
+        fields.push("Accept-Encoding".to_string());
+    }
+
+    if fields.is_empty() {
+        return response;
+    }
+    let vary = if fields.iter().any(|field| field == "*") {
+        HeaderValue::from_static("*")
+    } else {
+        match HeaderValue::from_str(&fields.join(", ")) {
+            Ok(vary) => vary,
+            Err(_) => return response,
+        }
+    };
+    headers.insert(header::VARY, vary);
+
+    response
+}
+
+/// Media types already compressed by their format, so compressing them again
+/// costs CPU without making them smaller. Entries ending in `/` match the
+/// whole type.
+const PRECOMPRESSED_MEDIA_TYPES: [&str; 10] = [
+    "image/",
+    "audio/",
+    "video/",
+    "font/woff",
+    "font/woff2",
+    "application/zip",
+    "application/gzip",
+    "application/zstd",
+    "application/x-7z-compressed",
+    "application/pdf",
+];
+
+/// Whether compressing a response of this media type would be wasted. SVG is
+/// text and compresses well despite being an image.
+fn is_precompressed(media_type: &str) -> bool {
+    media_type != "image/svg+xml"
+        && PRECOMPRESSED_MEDIA_TYPES.iter().any(|precompressed| {
+            if precompressed.ends_with('/') {
+                media_type.starts_with(precompressed)
+            } else {
+                media_type == *precompressed
+            }
+        })
+}
+
 async fn page_handler(
     path: Option<Path<String>>,
-    Query(query): Query<QueryParams>,
+    ValidQuery(query): ValidQuery<QueryParams>,
+    headers: HeaderMap,
 ) -> Result<impl IntoResponse, StatusCode> {
     let path = path.unwrap_or(Path("/".into())).0;
-    let page = Page::read(path).map_err(|_| StatusCode::NOT_FOUND)?;
+    let fragment = wants_fragment(query.mode.as_ref(), &headers);
+    let meta = query.mode == Some(Mode::Meta);
+    if is_gone(&path) {
+        return Ok(if fragment || meta {
+            (
+                StatusCode::GONE,
+                Json(serde_json::json!({ "error": "Page removed" })),
+            )
+                .into_response()
+        } else {
+            render_error_page(
+                StatusCode::GONE,
+                Some("This page has been removed for good."),
+            )
+        });
+    }
+    let config = load_config();
+    if let Some(slug) = Page::slug_redirect(&path) {
+        let location = config.route(&format!("/{}", slug.to_string_lossy()));
+        return Ok((
+            StatusCode::MOVED_PERMANENTLY,
+            [(header::LOCATION, location)],
+        )
+            .into_response());
+    }
+    let translations = Page::translations(&path, config.default_language());
+    let translation = choose_translation(&path, &translations, &headers, &config);
+    let source = translation.map_or(path, |translation| translation.url.clone());
+    let mut timing = ServerTiming::start();
+    let page = Page::read_source(source).and_then(|(path, content, modified)| {
+        timing.mark("read");
+        let hash = content_hash(&content);
+        let section = SectionConfig::for_page(&path);
+        let page = if meta {
+            Page::parse_metadata(path, &content, modified, section)
+        } else {
+            Page::parse_in_section(path, &content, modified, section)
+        };
+        timing.mark("render");
+        page.map(|page| (page, hash))
+    });
+    let (page, hash) = match page {
+        Ok(page) => page,
+        // Fragment and metadata clients expect JSON, even for missing pages.
+        Err(_) if fragment || meta => {
+            return Ok((
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Page not found" })),
+            )
+                .into_response());
+        }
+        Err(_) => return Err(StatusCode::NOT_FOUND),
+    };
+
+    let modified = page.modified;
+    if is_not_modified(&headers, modified) {
+        return Ok(with_last_modified(
+            StatusCode::NOT_MODIFIED.into_response(),
+            modified,
+        ));
+    }
 
-    if query.mode == Some(Mode::Fragment) {
+    let preview = page.draft || query.mode == Some(Mode::Edit);
+
+    let mut response = if meta {
+        Json(PageMeta {
+            id: page.id,
+            excerpt: page.excerpt(EXCERPT_WORDS),
+            reading_minutes: page.reading_minutes(),
+            title: page.title,
+            modified: page.modified,
+            tags: page.tags,
+        })
+        .into_response()
+    } else if fragment {
+        let adjacent = adjacent_pages(&page);
+        let article_start = article_start(&page);
         let fragment = Fragment {
             id: page.id,
+            excerpt: page.excerpt(EXCERPT_WORDS),
             title: page.title,
-            html: format!("<main><article>{}</article></main>", page.html),
+            html: format!("<main>{}{}</article></main>", article_start, page.html),
             modified: page.modified,
             tags: page.tags,
+            author: page.author,
+            extra: page.extra,
+            previous: adjacent.previous,
+            next: adjacent.next,
         };
-        Ok(Json(&fragment).into_response())
+        Json(&fragment).into_response()
+    } else {
+        let adjacent = adjacent_pages(&page);
+        let html = full_page_html(&page, &adjacent, &translations, query.q);
+        let length = HeaderValue::from(html.len());
+        let mut response = html_response(html);
+        let headers = response.headers_mut();
+        headers.insert(header::CONTENT_LENGTH, length);
+        headers.insert("X-Content-Hash", HeaderValue::from_str(&hash).unwrap());
+        // Set along with the body rather than by the security middleware, so
+        // a cached page keeps the policy matching the nonce it was rendered
+        // with.
+        if let Some(nonce) = csp_nonce() {
+            headers.insert(
+                header::CONTENT_SECURITY_POLICY,
+                content_security_policy(&nonce),
+            );
+        }
+        response
+    };
+    if let Some(translation) = translation {
+        response.headers_mut().insert(
+            header::VARY,
+            HeaderValue::from_static("Accept, Accept-Language"),
+        );
+        if let Ok(language) = HeaderValue::from_str(&translation.language) {
+            response
+                .headers_mut()
+                .insert(header::CONTENT_LANGUAGE, language);
+        }
     } else {
-        Ok(Html(full_page_html(&page, query.q)).into_response())
+        response
+            .headers_mut()
+            .insert(header::VARY, HeaderValue::from_static("Accept"));
+    }
+    if let Some(cache_control) = page_cache_control(&config, preview) {
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, cache_control);
+    }
+    if *config.server_timing() {
+        response
+            .headers_mut()
+            .insert("Server-Timing", timing.header_value());
     }
+
+    Ok(with_last_modified(response, modified))
+}
+
+/// Value of the `X-Content-Hash` header, the xxh3 64 bit hash of the page
+/// source including its frontmatter as 16 lowercase hexadecimal digits.
+fn content_hash(source: &str) -> String {
+    format!("{:016x}", xxh3_64(source.as_bytes()))
+}
+
+/// Durations of the steps of handling a request, reported in the
+/// `Server-Timing` header.
+struct ServerTiming {
+    start: Instant,
+    last: Instant,
+    steps: Vec<(&'static str, Duration)>,
 }
 
-fn full_page_html(page: &Page, query: Option<String>) -> String {
+impl ServerTiming {
+    fn start() -> Self {
+        let now = Instant::now();
+        Self {
+            start: now,
+            last: now,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Records the time since the previous step as `name`.
+    fn mark(&mut self, name: &'static str) {
+        let now = Instant::now();
+        self.steps.push((name, now - self.last));
+        self.last = now;
+    }
+
+    /// The recorded steps followed by the total time so far.
+    fn header_value(&self) -> HeaderValue {
+        let total = ("total", self.start.elapsed());
+        let value = self
+            .steps
+            .iter()
+            .chain(std::iter::once(&total))
+            .map(|(name, duration)| format!("{name};dur={:.1}", duration.as_secs_f64() * 1000.0))
+            .collect::<Vec<_>>()
+            .join(", ");
+        HeaderValue::from_str(&value).unwrap()
+    }
+}
+
+/// `Cache-Control` for a page response. Drafts and previews must never be
+/// cached, as they are not meant to be public yet.
+pub fn page_cache_control(config: &Config, preview: bool) -> Option<HeaderValue> {
+    if preview {
+        return Some(HeaderValue::from_static("no-store"));
+    }
+
+    HeaderValue::from_str(config.page_cache_control())
+        .inspect_err(|_| tracing::warn!("⚠️ Invalid page_cache_control in config"))
+        .ok()
+}
+
+/// Whether to answer with fragment JSON rather than the full page. An explicit
+/// `mode` wins, otherwise the `Accept` header decides as in [`prefers_json`].
+fn wants_fragment(mode: Option<&Mode>, headers: &HeaderMap) -> bool {
+    match mode {
+        Some(mode) => *mode == Mode::Fragment,
+        None => prefers_json(headers),
+    }
+}
+
+/// Whether `application/json` is preferred over HTML in the `Accept` header,
+/// with exact media types beating wildcards on equal quality.
+pub fn prefers_json(headers: &HeaderMap) -> bool {
+    let mut json = (0.0, 0);
+    let mut html = (0.0, 0);
+    let accepted = headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','));
+    for entry in accepted {
+        let mut parts = entry.split(';');
+        let media_type = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+        let quality = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+
+        let (preference, specificity) = match media_type.as_str() {
+            "application/json" => (&mut json, 2),
+            "text/html" => (&mut html, 2),
+            "text/*" => (&mut html, 1),
+            "*/*" => (&mut html, 0),
+            _ => continue,
+        };
+        if specificity >= preference.1 {
+            *preference = (quality, specificity);
+        }
+    }
+
+    json.0 > 0.0 && json > html
+}
+
+/// The version of a translated page to serve at the URL path `path`: the one
+/// it names when it ends in a language, otherwise the best match for the
+/// `Accept-Language` header, then the default language and then the first.
+fn choose_translation<'a>(
+    path: &str,
+    translations: &'a [Translation],
+    headers: &HeaderMap,
+    config: &Config,
+) -> Option<&'a Translation> {
+    let path = path.trim_matches('/');
+    let names_language = path.rsplit('/').next().and_then(split_language).is_some();
+    if let Some(requested) = translations
+        .iter()
+        .find(|translation| names_language && translation.url == path)
+    {
+        return Some(requested);
+    }
+
+    let languages: Vec<&str> = translations
+        .iter()
+        .map(|translation| translation.language.as_str())
+        .collect();
+    let language =
+        preferred_language(headers, &languages).unwrap_or(config.default_language().as_str());
+    translations
+        .iter()
+        .find(|translation| translation.language.eq_ignore_ascii_case(language))
+        .or(translations.first())
+}
+
+/// The language of `available` preferred in the `Accept-Language` header,
+/// with ranges like `sv` matching `sv-FI` and the other way around. Exact
+/// matches win on equal quality, and earlier ranges over later ones. `None`
+/// when no acceptable language is available.
+pub fn preferred_language<'a>(headers: &HeaderMap, available: &[&'a str]) -> Option<&'a str> {
+    let mut ranges: Vec<(String, f32)> = headers
+        .get_all(header::ACCEPT_LANGUAGE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let range = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (!range.is_empty() && range != "*" && quality > 0.0).then_some((range, quality))
+        })
+        .collect();
+    // Stable, so ranges of equal quality keep their order.
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    ranges.iter().find_map(|(range, _)| {
+        available
+            .iter()
+            .find(|language| language.eq_ignore_ascii_case(range))
+            .or_else(|| {
+                available.iter().find(|language| {
+                    let language = language.to_ascii_lowercase();
+                    language.starts_with(&format!("{range}-"))
+                        || range.starts_with(&format!("{language}-"))
+                })
+            })
+            .copied()
+    })
+}
+
+/// Whether the client's `If-Modified-Since` is at or after the modification
+/// time. HTTP dates only have whole seconds, so sub-second parts are ignored.
+fn is_not_modified(headers: &HeaderMap, modified: OffsetDateTime) -> bool {
+    headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| httpdate::parse_http_date(value).ok())
+        .is_some_and(|since| since >= SystemTime::from(modified.replace_nanosecond(0).unwrap()))
+}
+
+pub fn with_last_modified(mut response: Response, modified: OffsetDateTime) -> Response {
+    if let Ok(value) = HeaderValue::from_str(&httpdate::fmt_http_date(modified.into())) {
+        response.headers_mut().insert(header::LAST_MODIFIED, value);
+    }
+    response
+}
+
+fn html_response(html: String) -> Response {
+    ([(header::CONTENT_TYPE, HTML_CONTENT_TYPE)], html).into_response()
+}
+
+fn full_page_html(
+    page: &Page,
+    adjacent: &Adjacent,
+    translations: &[Translation],
+    query: Option<String>,
+) -> String {
+    let config = load_config();
+    let title = formulate_title(page, &config);
+
+    let mut head = format!(
+        r#"<meta property="og:title" content="{}">
+        <meta property="og:type" content="article">
+        <link rel="manifest" href="{}">"#,
+        escape_html(&title),
+        config.route(WEB_MANIFEST_PATH)
+    );
+    if config.og_image().is_some() {
+        head.push_str(&format!(
+            r#"
+        <meta property="og:image" content="{}">
+        <meta property="og:image:width" content="{OG_IMAGE_WIDTH}">
+        <meta property="og:image:height" content="{OG_IMAGE_HEIGHT}">"#,
+            escape_html(&config.absolute_url(&og_image_route(page)))
+        ));
+    }
+    if let Some(robots) = &page.robots {
+        head.push_str(&format!(
+            r#"
+        <meta name="robots" content="{}">"#,
+            escape_html(robots)
+        ));
+    }
+    for translation in translations {
+        head.push_str(&format!(
+            r#"
+        <link rel="alternate" hreflang="{}" href="{}">"#,
+            escape_html(&translation.language),
+            escape_html(&config.absolute_url(&format!("/{}", translation.url)))
+        ));
+    }
+    if let Some(translation) = translations.first() {
+        head.push_str(&format!(
+            r#"
+        <link rel="alternate" hreflang="x-default" href="{}">"#,
+            escape_html(&config.absolute_url(&format!("/{}", translation.negotiated_url())))
+        ));
+    }
+    head.push_str(&format!(
+        r#"
+        <script type="application/ld+json">{}</script>"#,
+        article_json_ld(page, &config)
+    ));
+    let mut byline = String::new();
+    if let Some(author) = &page.author {
+        head.push_str(&format!(
+            r#"
+        <meta property="article:author" content="{}">"#,
+            escape_html(author)
+        ));
+        byline = format!(
+            r#"<p class="byline">by <a href="{}">{}</a></p>"#,
+            escape_html(&config.route(&author_route(author))),
+            escape_html(author)
+        );
+    }
+
+    let dateline = if page.section.show_dates() {
+        format!(
+            r#"<p class="dateline"><time datetime="{}">{}</time></p>"#,
+            page.modified.format(&Rfc3339).unwrap_or_default(),
+            page.modified
+                .format(format_description!("[year]-[month]-[day]"))
+                .unwrap_or_default()
+        )
+    } else {
+        String::new()
+    };
+
+    render_layout(
+        &title,
+        &head,
+        &query.unwrap_or_default(),
+        &format!(
+            "{}{}{}{}</article>{}",
+            article_start(page),
+            dateline,
+            page.html,
+            byline,
+            pagination_html(adjacent)
+        ),
+    )
+}
+
+/// Schema.org `Article` structured data of the page as JSON-LD, leaving out
+/// what the page does not set. Being data rather than code, the script block
+/// is not subject to the `script-src` of the content security policy.
+fn article_json_ld(page: &Page, config: &Config) -> String {
+    let mut article = Map::new();
+    article.insert("@context".into(), "https://schema.org".into());
+    article.insert("@type".into(), "Article".into());
+    if let Some(title) = &page.title {
+        article.insert("headline".into(), title.as_str().into());
+    }
+    if let Some(date) = page.date.and_then(|date| {
+        date.format(format_description!("[year]-[month]-[day]"))
+            .ok()
+    }) {
+        article.insert("datePublished".into(), date.into());
+    }
+    if let Ok(modified) = page.modified.format(&Rfc3339) {
+        article.insert("dateModified".into(), modified.into());
+    }
+    if let Some(author) = &page.author {
+        article.insert(
+            "author".into(),
+            serde_json::json!({ "@type": "Person", "name": author }),
+        );
+    }
+    article.insert(
+        "url".into(),
+        config
+            .absolute_url(&format!("/{}", page.url.to_string_lossy()))
+            .into(),
+    );
+
+    // Escaped so no value can close the script element early.
+    Value::Object(article)
+        .to_string()
+        .replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .replace('&', "\\u0026")
+}
+
+/// Opens the article element, classed after the layout hint of the section.
+fn article_start(page: &Page) -> String {
+    match &page.section.layout {
+        Some(layout) => format!(r#"<article class="layout-{}">"#, escape_html(layout)),
+        None => "<article>".to_string(),
+    }
+}
+
+fn pagination_html(adjacent: &Adjacent) -> String {
+    if adjacent.previous.is_none() && adjacent.next.is_none() {
+        return String::new();
+    }
+
+    let link = |link: &PageLink, rel: &str| {
+        format!(
+            r#"<a rel="{}" href="{}">{}</a>"#,
+            rel,
+            escape_html(&link.url),
+            escape_html(link.title.as_deref().unwrap_or(&link.url))
+        )
+    };
+
+    format!(
+        r#"<nav class="pagination">{}{}</nav>"#,
+        adjacent
+            .previous
+            .as_ref()
+            .map(|previous| link(previous, "prev"))
+            .unwrap_or_default(),
+        adjacent
+            .next
+            .as_ref()
+            .map(|next| link(next, "next"))
+            .unwrap_or_default()
+    )
+}
+
+/// Renders the page shell shared by all HTML pages around the main content.
+pub fn render_layout(title: &str, head: &str, query: &str, content: &str) -> String {
+    let config = load_config();
     format!(
         r#"<!DOCTYPE html>
 <html lang="en-US">
@@ -112,7 +833,43 @@ fn full_page_html(page: &Page, query: Option<String>) -> String {
         <meta http-equiv="X-UA-Compatible" content="IE=Edge">
         <meta name="viewport" content="width=device-width,initial-scale=1">
         <title>{}</title>
-        <style>
+        {}{}
+        {}
+        <link rel="stylesheet" href="{}">{}
+        <script type="module" src="{}"></script>
+    </head>
+    <body>
+        <main>
+            {}
+            {}
+        </main>
+    </body>
+</html>"#,
+        escape_html(title),
+        head,
+        view_transition_style(&config),
+        favicon_link(&config),
+        ASSET_MANAGER.hashed_route("styles.css").unwrap_or_default(),
+        theme_html(&config, |asset| ASSET_MANAGER.hashed_route(asset)),
+        ASSET_MANAGER.hashed_route("script.js").unwrap_or_default(),
+        search_form(&config, query),
+        content
+    )
+}
+
+/// Inline style animating navigation between pages, empty when view
+/// transitions are turned off.
+fn view_transition_style(config: &Config) -> String {
+    if !config.view_transitions() {
+        return String::new();
+    }
+
+    let nonce = csp_nonce()
+        .map(|nonce| format!(r#" nonce="{nonce}""#))
+        .unwrap_or_default();
+    format!(
+        r#"
+        <style{nonce}>
             @view-transition {{
                 navigation: auto;
             }}
@@ -121,55 +878,877 @@ fn full_page_html(page: &Page, query: Option<String>) -> String {
             ::view-transition-new(root),
             ::view-transition-old(article),
             ::view-transition-new(article) {{
-                animation-duration: 50ms;
-                animation-timing-function: ease-in-out;
+                animation-duration: {}ms;
+                animation-timing-function: {};
             }}
 
             article {{
                 view-transition-name: article;
             }}
-        </style>
-        <link rel="stylesheet" href="{}">
-        <script type="module" src="{}"></script>
-    </head>
-    <body>
-        <main>
-            <search>
-                <form method="get" action="/search">
+        </style>"#,
+        config.view_transition_duration().as_millis(),
+        config.view_transition_timing()
+    )
+}
+
+/// Theme color meta tags, with a variant for dark color schemes when
+/// configured, and the dark stylesheet resolved to its hashed route.
+fn theme_html(config: &Config, resolve: impl Fn(&str) -> Option<String>) -> String {
+    const DARK: &str = "(prefers-color-scheme: dark)";
+    let mut html = String::new();
+
+    let theme_color = |color: &str, media: Option<&str>| {
+        let media = media
+            .map(|media| format!(r#" media="{media}""#))
+            .unwrap_or_default();
+        format!(
+            r#"
+        <meta name="theme-color" content="{}"{media}>"#,
+            escape_html(color)
+        )
+    };
+    match (config.theme_color(), config.theme_color_dark()) {
+        (Some(light), Some(dark)) => {
+            html.push_str(&theme_color(light, Some("(prefers-color-scheme: light)")));
+            html.push_str(&theme_color(dark, Some(DARK)));
+        }
+        (Some(color), None) => html.push_str(&theme_color(color, None)),
+        (None, Some(dark)) => html.push_str(&theme_color(dark, Some(DARK))),
+        (None, None) => {}
+    }
+
+    if let Some(href) = config.dark_stylesheet().as_deref().and_then(resolve) {
+        html.push_str(&format!(
+            r#"
+        <link rel="stylesheet" href="{}" media="{DARK}">"#,
+            escape_html(&href)
+        ));
+    }
+
+    html
+}
+
+/// Links the configured favicon by its hashed route, so it can be cached like
+/// other assets, or the placeholder at `/favicon.ico`.
+fn favicon_link(config: &Config) -> String {
+    let favicon = config.favicon().as_deref().and_then(|favicon| {
+        Some((
+            ASSET_MANAGER.hashed_route(favicon)?,
+            image_mime_type(favicon)?,
+        ))
+    });
+    let (href, mime_type) = favicon.unwrap_or((config.route("/favicon.ico"), "image/x-icon"));
+    format!(r#"<link rel="icon" href="{href}" type="{mime_type}">"#)
+}
+
+fn search_form(config: &Config, query: &str) -> String {
+    format!(
+        r#"<search>
+                <form method="get" action="{}">
                     <label for="search">Search</label>
                     <input id="search" type="search" name="q" value="{}">
                     <button>Search</button>
                 </form>
-            </search>
-            <article>{}</article>
-        </main>
-    </body>
-</html>"#,
-        formulate_title(page),
-        ASSET_MANAGER.hashed_route("styles.css").unwrap_or_default(),
-        ASSET_MANAGER.hashed_route("script.js").unwrap_or_default(),
-        &query.unwrap_or_default(),
-        &page.html
+            </search>"#,
+        config.route("/search"),
+        escape_html(query)
     )
 }
 
-fn formulate_title(page: &Page) -> String {
-    let config = load_config();
-    if let Some(page_title) = &page.title {
-        format!("{} - {}", page_title, config.title())
-    } else {
-        config.title().clone()
+/// Escapes text for use in HTML content and quoted attribute values.
+pub fn escape_html(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#x27;"),
+            c => output.push(c),
+        }
+    }
+    output
+}
+
+/// The document title, the page title followed by the site title or the
+/// suffix the page or its section sets instead. An empty suffix is left out
+/// along with the separator.
+fn formulate_title(page: &Page, config: &Config) -> String {
+    let suffix = page
+        .title_suffix
+        .as_deref()
+        .map(str::trim)
+        .unwrap_or(config.title());
+    match &page.title {
+        Some(page_title) if suffix.is_empty() => page_title.clone(),
+        Some(page_title) => format!("{page_title}{}{suffix}", config.title_separator()),
+        None if suffix.is_empty() => config.title().clone(),
+        None => suffix.to_string(),
     }
 }
 
 async fn add_performance_headers(request: Request<Body>, next: Next) -> Response {
     let mut response = next.run(request).await;
-    if let Some(content_type) = response.headers().get(header::CONTENT_TYPE) {
-        if content_type == "text/html" {
+    if response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(is_html)
+    {
+        response
+            .headers_mut()
+            .insert("View-Transition", HeaderValue::from_static("same-origin"));
+    }
+    response
+}
+
+/// Matches the `text/html` media type, ignoring parameters such as `charset`.
+pub fn is_html(content_type: &HeaderValue) -> bool {
+    media_type(content_type).is_some_and(|media_type| media_type == "text/html")
+}
+
+/// Matches `application/json` and `+json` media types such as
+/// `application/feed+json`.
+pub fn is_json(content_type: &HeaderValue) -> bool {
+    media_type(content_type).is_some_and(|media_type| {
+        media_type == "application/json"
+            || (media_type.starts_with("application/") && media_type.ends_with("+json"))
+    })
+}
+
+fn media_type(content_type: &HeaderValue) -> Option<String> {
+    let content_type = content_type.to_str().ok()?;
+    let media_type = content_type.split(';').next()?;
+    Some(media_type.trim().to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::config::ConfigParsed;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_content_hash() {
+        let hash = content_hash("---\ntitle: A\n---\n# A");
+        assert_eq!(hash.len(), 16);
+        assert!(hash.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(hash, content_hash("---\ntitle: A\n---\n# A"));
+        assert_ne!(hash, content_hash("---\ntitle: B\n---\n# A"));
+    }
+
+    #[test]
+    fn test_search_form_action_includes_base_path() {
+        let config =
+            Config::from(toml::from_str::<ConfigParsed>(r#"base_path = "/docs/""#).unwrap());
+        let html = search_form(&config, "\"query\"");
+        assert!(html.contains(r#"action="/docs/search""#));
+        assert!(html.contains(r#"value="&quot;query&quot;""#));
+    }
+
+    #[test]
+    fn test_robots_meta() {
+        let page = Page::parse(
+            "/pages/hidden.md".into(),
+            "---\nrobots: noindex, nofollow\n---\n# Hidden",
+            OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap();
+        let html = full_page_html(&page, &Adjacent::default(), &[], None);
+        assert!(html.contains(r#"<meta name="robots" content="noindex, nofollow">"#));
+    }
+
+    #[test]
+    fn test_article_json_ld() {
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>("base_url = \"https://example.com\"").unwrap(),
+        );
+        let page = Page::parse(
+            "guides/start.md".into(),
+            "---\ntitle: Start </script> here\nauthor: Ada\ndate: 2024-01-31\n---\nBody",
+            OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap();
+
+        let json_ld = article_json_ld(&page, &config);
+        assert!(!json_ld.contains("</script>"), "{json_ld}");
+        let article: Value = serde_json::from_str(&json_ld).unwrap();
+        assert_eq!(article["@type"], "Article");
+        assert_eq!(article["headline"], "Start </script> here");
+        assert_eq!(article["datePublished"], "2024-01-31");
+        assert_eq!(article["dateModified"], "1970-01-01T00:00:00Z");
+        assert_eq!(article["author"]["name"], "Ada");
+        assert_eq!(article["url"], "https://example.com/guides/start");
+
+        let page =
+            Page::parse("guides/plain.md".into(), "Body", OffsetDateTime::UNIX_EPOCH).unwrap();
+        let article: Value = serde_json::from_str(&article_json_ld(&page, &config)).unwrap();
+        let mut fields: Vec<&String> = article.as_object().unwrap().keys().collect();
+        fields.sort();
+        assert_eq!(
+            fields,
+            ["@context", "@type", "dateModified", "headline", "url"]
+        );
+    }
+
+    #[test]
+    fn test_frontmatter_title_is_normalized() {
+        let page = Page::parse(
+            "/pages/padded.md".into(),
+            "---\ntitle: \"  Padded\\n  <Title>  \"\n---\n# Content",
+            OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap();
+        assert_eq!(page.title.as_deref(), Some("Padded <Title>"));
+        let html = full_page_html(&page, &Adjacent::default(), &[], None);
+        assert!(html.contains(&format!(
+            "<title>Padded &lt;Title&gt; - {}</title>",
+            load_config().title()
+        )));
+    }
+
+    #[test]
+    fn test_title_suffix_and_separator() {
+        let config = |toml: &str| Config::from(toml::from_str::<ConfigParsed>(toml).unwrap());
+        let page = |frontmatter: &str, section: SectionConfig| {
+            Page::parse_in_section(
+                "/pages/page.md".into(),
+                &format!("---\n{frontmatter}\n---\n# Content"),
+                OffsetDateTime::UNIX_EPOCH,
+                section,
+            )
+            .unwrap()
+        };
+        let site = config("title = \"Site\"");
+        let docs = SectionConfig {
+            title_suffix: Some("Docs".into()),
+            ..Default::default()
+        };
+
+        let plain = page("title: Page", SectionConfig::default());
+        assert_eq!(formulate_title(&plain, &site), "Page - Site");
+        assert_eq!(
+            formulate_title(
+                &plain,
+                &config("title = \"Site\"\ntitle_separator = \" | \"")
+            ),
+            "Page | Site"
+        );
+
+        let suppressed = page("title: Home\ntitle_suffix: \"\"", docs.clone());
+        assert_eq!(formulate_title(&suppressed, &site), "Home");
+
+        assert_eq!(
+            formulate_title(&page("title: Setup", docs.clone()), &site),
+            "Setup - Docs"
+        );
+        assert_eq!(
+            formulate_title(&page("title: Setup\ntitle_suffix: Guide", docs), &site),
+            "Setup - Guide"
+        );
+    }
+
+    #[test]
+    fn test_if_modified_since() {
+        let modified = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap()
+            + time::Duration::milliseconds(250);
+        let headers = |since: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                header::IF_MODIFIED_SINCE,
+                HeaderValue::from_str(since).unwrap(),
+            );
+            headers
+        };
+
+        let response = with_last_modified(StatusCode::OK.into_response(), modified);
+        let last_modified = response.headers().get(header::LAST_MODIFIED).unwrap();
+        assert_eq!(last_modified, "Tue, 14 Nov 2023 22:13:20 GMT");
+
+        assert!(is_not_modified(
+            &headers("Tue, 14 Nov 2023 22:13:20 GMT"),
+            modified
+        ));
+        assert!(is_not_modified(
+            &headers("Wed, 15 Nov 2023 00:00:00 GMT"),
+            modified
+        ));
+        assert!(!is_not_modified(
+            &headers("Tue, 14 Nov 2023 22:13:19 GMT"),
+            modified
+        ));
+        assert!(!is_not_modified(&headers("not a date"), modified));
+        assert!(!is_not_modified(&HeaderMap::new(), modified));
+    }
+
+    #[test]
+    fn test_is_html() {
+        assert!(is_html(&HeaderValue::from_static("text/html")));
+        assert!(is_html(&HeaderValue::from_static(HTML_CONTENT_TYPE)));
+        assert!(is_html(&HeaderValue::from_static(
+            "Text/HTML ; charset=UTF-8"
+        )));
+        assert!(!is_html(&HeaderValue::from_static("text/html-sandboxed")));
+        assert!(!is_html(&HeaderValue::from_static("application/json")));
+    }
+
+    #[test]
+    fn test_server_timing_header() {
+        let start = Instant::now();
+        let timing = ServerTiming {
+            start,
+            last: start,
+            steps: vec![
+                ("read", Duration::from_micros(1240)),
+                ("render", Duration::from_micros(3400)),
+            ],
+        };
+        let value = timing.header_value();
+        let value = value.to_str().unwrap();
+        assert!(value.starts_with("read;dur=1.2, render;dur=3.4, total;dur="));
+    }
+
+    #[test]
+    fn test_theme_html() {
+        let config = |toml: &str| Config::from(toml::from_str::<ConfigParsed>(toml).unwrap());
+        let resolve = |asset: &str| Some(format!("/assets/{asset}.1a2b"));
+
+        assert_eq!(theme_html(&config(""), resolve), "");
+
+        let html = theme_html(&config(r##"theme_color = "#fff""##), resolve);
+        assert_eq!(html.trim(), r##"<meta name="theme-color" content="#fff">"##);
+
+        let html = theme_html(
+            &config(
+                r##"
+                theme_color = "#fff"
+                theme_color_dark = "#000"
+                dark_stylesheet = "dark.css"
+                "##,
+            ),
+            resolve,
+        );
+        assert!(html.contains(
+            r##"<meta name="theme-color" content="#fff" media="(prefers-color-scheme: light)">"##
+        ));
+        assert!(html.contains(
+            r##"<meta name="theme-color" content="#000" media="(prefers-color-scheme: dark)">"##
+        ));
+        assert!(html.contains(
+            r#"<link rel="stylesheet" href="/assets/dark.css.1a2b" media="(prefers-color-scheme: dark)">"#
+        ));
+
+        let html = theme_html(&config(r#"dark_stylesheet = "dark.css""#), |_| None);
+        assert_eq!(html, "");
+    }
+
+    #[test]
+    fn test_view_transition_style() {
+        let config = |toml: &str| Config::from(toml::from_str::<ConfigParsed>(toml).unwrap());
+
+        let style = view_transition_style(&config(""));
+        assert!(style.contains("animation-duration: 50ms;"));
+        assert!(style.contains("animation-timing-function: ease-in-out;"));
+
+        let style = view_transition_style(&config(
+            "view_transition_duration = \"1s\"\nview_transition_timing = \"linear\"",
+        ));
+        assert!(style.contains("animation-duration: 1000ms;"));
+        assert!(style.contains("animation-timing-function: linear;"));
+
+        assert_eq!(
+            view_transition_style(&config("view_transitions = false")),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_page_cache_control() {
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>(r#"page_cache_control = "public, max-age=60""#).unwrap(),
+        );
+        assert_eq!(
+            page_cache_control(&config, false).unwrap(),
+            "public, max-age=60"
+        );
+        assert_eq!(page_cache_control(&config, true).unwrap(), "no-store");
+    }
+
+    #[test]
+    fn test_wants_fragment() {
+        let accept = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::ACCEPT, HeaderValue::from_static(value));
+            headers
+        };
+
+        assert!(!wants_fragment(None, &HeaderMap::new()));
+        assert!(wants_fragment(None, &accept("application/json")));
+        assert!(wants_fragment(None, &accept("application/json, */*")));
+        assert!(!wants_fragment(
+            None,
+            &accept("text/html,application/xhtml+xml,*/*;q=0.8")
+        ));
+        assert!(!wants_fragment(
+            None,
+            &accept("application/json;q=0.5, text/html")
+        ));
+        assert!(!wants_fragment(None, &accept("application/json;q=0")));
+        assert!(!wants_fragment(
+            Some(&Mode::Edit),
+            &accept("application/json")
+        ));
+        assert!(wants_fragment(Some(&Mode::Fragment), &accept("text/html")));
+    }
+
+    #[test]
+    fn test_preferred_language() {
+        let preferred = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::ACCEPT_LANGUAGE, HeaderValue::from_static(value));
+            preferred_language(&headers, &["en", "sv", "pt-BR"])
+        };
+
+        assert_eq!(preferred_language(&HeaderMap::new(), &["en", "sv"]), None);
+        assert_eq!(preferred("sv-SE,sv;q=0.9,en;q=0.8"), Some("sv"));
+        assert_eq!(preferred("de, en;q=0.5, sv;q=0.7"), Some("sv"));
+        assert_eq!(preferred("pt"), Some("pt-BR"));
+        assert_eq!(preferred("PT-br"), Some("pt-BR"));
+        assert_eq!(preferred("en;q=0.5, sv;q=0.5"), Some("en"));
+        assert_eq!(preferred("sv;q=0, de"), None);
+        assert_eq!(preferred("*"), None);
+    }
+
+    #[test]
+    fn test_choose_translation() {
+        let config =
+            Config::from(toml::from_str::<ConfigParsed>("default_language = \"sv\"").unwrap());
+        let translations = [
+            Translation {
+                language: "en".into(),
+                url: "about.en".into(),
+            },
+            Translation {
+                language: "sv".into(),
+                url: "about".into(),
+            },
+        ];
+        let choose = |path: &str, accept_language: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                header::ACCEPT_LANGUAGE,
+                HeaderValue::from_static(accept_language),
+            );
+            choose_translation(path, &translations, &headers, &config)
+                .map(|translation| translation.url.as_str())
+        };
+
+        assert_eq!(choose("about", "en-GB"), Some("about.en"));
+        assert_eq!(choose("/about/", "de, sv;q=0.5"), Some("about"));
+        assert_eq!(choose("about", "de"), Some("about"));
+        assert_eq!(choose("about.en", "sv"), Some("about.en"));
+        assert_eq!(choose("about", ""), Some("about"));
+        assert_eq!(
+            choose_translation("about", &[], &HeaderMap::new(), &config),
+            None
+        );
+    }
+
+    #[test]
+    fn test_translation_alternates() {
+        let page = Page::parse(
+            "/pages/about.sv.md".into(),
+            "# Om oss",
+            OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap();
+        let translations = [
+            Translation {
+                language: "en".into(),
+                url: "docs/about".into(),
+            },
+            Translation {
+                language: "sv".into(),
+                url: "docs/about.sv".into(),
+            },
+        ];
+
+        let html = full_page_html(&page, &Adjacent::default(), &translations, None);
+        let base_url = load_config().base_url().clone();
+        for (language, path) in [
+            ("en", "docs/about"),
+            ("sv", "docs/about.sv"),
+            ("x-default", "docs/about"),
+        ] {
+            assert!(html.contains(&format!(
+                r#"<link rel="alternate" hreflang="{language}" href="{base_url}/{path}">"#
+            )));
+        }
+    }
+
+    #[test]
+    fn test_is_json() {
+        assert!(is_json(&HeaderValue::from_static("application/json")));
+        assert!(is_json(&HeaderValue::from_static(
+            "Application/JSON; charset=utf-8"
+        )));
+        assert!(is_json(&HeaderValue::from_static("application/feed+json")));
+        assert!(!is_json(&HeaderValue::from_static("text/json-seq")));
+        assert!(!is_json(&HeaderValue::from_static(HTML_CONTENT_TYPE)));
+    }
+
+    #[tokio::test]
+    async fn test_compression_prefers_configured_encoding_order() {
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>(r#"compression_encodings = ["br", "gzip"]"#).unwrap(),
+        );
+        let app = add_compression(
+            Router::new().route("/", get(async || html_response("<p>Page</p>".repeat(100)))),
+            &config,
+        );
+        let encoding = async |accept: &str| {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri("/")
+                        .header(header::ACCEPT_ENCODING, accept)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
             response
-                .headers_mut()
-                .insert("View-Transition", HeaderValue::from_static("same-origin"));
+                .headers()
+                .get(header::CONTENT_ENCODING)
+                .map(|value| value.to_str().unwrap().to_string())
+        };
+
+        assert_eq!(encoding("gzip, br").await.as_deref(), Some("br"));
+        assert_eq!(encoding("gzip").await.as_deref(), Some("gzip"));
+        assert_eq!(encoding("deflate").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_compressed_responses_vary_on_accept_encoding() {
+        let config = Config::from(ConfigParsed::default());
+        let app = add_compression(
+            Router::new()
+                .route(
+                    "/page",
+                    get(async || {
+                        let mut response = html_response("<p>Page</p>".repeat(100));
+                        response
+                            .headers_mut()
+                            .insert(header::VARY, HeaderValue::from_static("Accept"));
+                        response
+                    }),
+                )
+                .route("/tiny", get(async || html_response("<p>Hi</p>".into()))),
+            &config,
+        );
+        let vary = async |uri: &str, accept_encoding: &str| {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri(uri)
+                        .header(header::ACCEPT_ENCODING, accept_encoding)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            response
+                .headers()
+                .get_all(header::VARY)
+                .iter()
+                .map(|value| value.to_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(vary("/page", "gzip").await, ["Accept, accept-encoding"]);
+        // Clients without compression must not be served a cached compressed
+        // body either.
+        assert_eq!(vary("/page", "identity").await, ["Accept, accept-encoding"]);
+        assert!(vary("/tiny", "gzip").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_options_lists_allowed_methods() {
+        let app = middleware::from_fn(answer_options).layer(
+            Router::new()
+                .route("/", get(async || "page"))
+                .route("/admin/reindex", axum::routing::post(async || "reindexed")),
+        );
+
+        for (uri, allow) in [
+            ("/", "GET, HEAD, OPTIONS"),
+            ("/admin/reindex", "POST, OPTIONS"),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method(Method::OPTIONS)
+                        .uri(uri)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::NO_CONTENT, "{uri}");
+            assert_eq!(response.headers().get(header::ALLOW).unwrap(), allow);
         }
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(Method::DELETE)
+                    .uri("/")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn test_merge_vary_adds_accept_encoding_to_encoded_responses() {
+        let app = Router::new()
+            .route(
+                "/",
+                get(async || {
+                    (
+                        [(header::CONTENT_ENCODING, "gzip"), (header::VARY, "accept")],
+                        "compressed",
+                    )
+                }),
+            )
+            .layer(middleware::from_fn(merge_vary));
+
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers().get(header::VARY).unwrap(),
+            "accept, Accept-Encoding"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_precompressed_content_is_not_compressed() {
+        let config = Config::from(ConfigParsed::default());
+        let body = || vec![0u8; 1024];
+        let app = add_compression(
+            Router::new()
+                .route(
+                    "/image.png",
+                    get(async move || ([(header::CONTENT_TYPE, "image/png")], body())),
+                )
+                .route(
+                    "/font.woff2",
+                    get(async move || ([(header::CONTENT_TYPE, "font/woff2")], body())),
+                )
+                .route(
+                    "/image.svg",
+                    get(async move || ([(header::CONTENT_TYPE, "image/svg+xml")], body())),
+                ),
+            &config,
+        );
+        let encoding = async |uri: &str| {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri(uri)
+                        .header(header::ACCEPT_ENCODING, "gzip")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            response.headers().get(header::CONTENT_ENCODING).cloned()
+        };
+
+        assert_eq!(encoding("/image.png").await, None);
+        assert_eq!(encoding("/font.woff2").await, None);
+        assert_eq!(encoding("/image.svg").await.unwrap(), "gzip");
+    }
+
+    #[test]
+    fn test_is_precompressed() {
+        assert!(is_precompressed("image/png"));
+        assert!(is_precompressed("application/zip"));
+        assert!(!is_precompressed("image/svg+xml"));
+        assert!(!is_precompressed("text/css"));
+        assert!(!is_precompressed("application/json"));
+        assert!(!is_precompressed("font/woff-ish"));
+    }
+
+    #[tokio::test]
+    async fn test_cached_pages_keep_representations_apart() {
+        let pages_path = load_config().pages_path();
+        fs::create_dir_all(&pages_path).unwrap();
+        let name = format!("cached-{}", Ulid::new());
+        fs::write(pages_path.join(format!("{name}.md")), "# Cached").unwrap();
+        let index_dir = tempfile::tempdir().unwrap();
+        let search_index = Arc::new(RwLock::new(SearchIndex::new(index_dir.path()).unwrap()));
+        let app = routes(search_index);
+
+        let request = |accept: &'static str| {
+            Request::builder()
+                .uri(format!("/{name}"))
+                .header(header::ACCEPT, accept)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let content_type = |response: Response| {
+            response
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+
+        for _ in 0..2 {
+            let html = app.clone().oneshot(request("text/html")).await.unwrap();
+            assert_eq!(content_type(html), HTML_CONTENT_TYPE);
+            let json = app
+                .clone()
+                .oneshot(request("application/json"))
+                .await
+                .unwrap();
+            assert_eq!(content_type(json), "application/json");
+        }
+
+        fs::remove_file(pages_path.join(format!("{name}.md"))).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cached_pages_keep_translations_apart() {
+        let pages_path = load_config().pages_path();
+        fs::create_dir_all(&pages_path).unwrap();
+        let name = format!("translated-{}", Ulid::new());
+        let files = [format!("{name}.md"), format!("{name}.sv.md")];
+        fs::write(pages_path.join(&files[0]), "# Hello").unwrap();
+        fs::write(pages_path.join(&files[1]), "# Hej").unwrap();
+        let index_dir = tempfile::tempdir().unwrap();
+        let search_index = Arc::new(RwLock::new(SearchIndex::new(index_dir.path()).unwrap()));
+        let app = routes(search_index);
+
+        for _ in 0..2 {
+            for (accept_language, language) in [("sv", "sv"), ("en", "en")] {
+                let response = app
+                    .clone()
+                    .oneshot(
+                        Request::builder()
+                            .uri(format!("/{name}"))
+                            .header(header::ACCEPT_LANGUAGE, accept_language)
+                            .body(Body::empty())
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    response.headers().get(header::CONTENT_LANGUAGE).unwrap(),
+                    language
+                );
+            }
+        }
+
+        for file in files {
+            fs::remove_file(pages_path.join(file)).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_path_of_slugged_page_redirects() {
+        let pages_path = load_config().pages_path();
+        fs::create_dir_all(&pages_path).unwrap();
+        let name = format!("named-{}", Ulid::new());
+        let file = pages_path.join(format!("{name}.md"));
+        fs::write(&file, format!("---\nslug: slugged/{name}\n---\n# Slugged")).unwrap();
+        Page::index_slugs();
+        let app = Router::new().route("/{*path}", get(page_handler));
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/{name}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            response.headers().get(header::LOCATION).unwrap(),
+            &format!("/slugged/{name}")
+        );
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/slugged/{name}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        fs::remove_file(file).unwrap();
+        Page::index_slugs();
+    }
+
+    #[tokio::test]
+    async fn test_missing_page_metadata_is_json() {
+        let app = Router::new().route("/{*path}", get(page_handler));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/missing/page-for-metadata?mode=meta")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "Page not found");
+    }
+
+    #[tokio::test]
+    async fn test_view_transition_header_on_page_response() {
+        let app = Router::new()
+            .route("/", get(async || html_response("<p>Page</p>".into())))
+            .layer(middleware::from_fn(add_performance_headers));
+
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            HTML_CONTENT_TYPE
+        );
+        assert_eq!(
+            response.headers().get("View-Transition").unwrap(),
+            "same-origin"
+        );
     }
-    response
 }
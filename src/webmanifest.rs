@@ -0,0 +1,129 @@
+use axum::{
+    Json, Router,
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use serde::Serialize;
+
+use crate::{
+    assets::{ASSET_MANAGER, image_mime_type},
+    config::{Config, load_config},
+};
+
+pub const WEB_MANIFEST_PATH: &str = "/manifest.webmanifest";
+
+/// Serves the web app manifest that makes the site installable as a PWA.
+pub fn webmanifest_routes() -> Router {
+    Router::new().route(WEB_MANIFEST_PATH, get(webmanifest_handler))
+}
+
+async fn webmanifest_handler() -> Response {
+    let manifest = WebManifest::new(&load_config(), |src| ASSET_MANAGER.hashed_route(src));
+
+    (
+        [(header::CONTENT_TYPE, "application/manifest+json")],
+        Json(manifest),
+    )
+        .into_response()
+}
+
+#[derive(Serialize)]
+struct WebManifest {
+    name: String,
+    short_name: String,
+    start_url: String,
+    scope: String,
+    display: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    theme_color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    background_color: Option<String>,
+    icons: Vec<WebManifestIcon>,
+}
+
+#[derive(Serialize)]
+struct WebManifestIcon {
+    src: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sizes: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    mime_type: Option<String>,
+}
+
+impl WebManifest {
+    /// Builds the manifest from config, resolving icons to their hashed asset
+    /// routes with `resolve`. Icons missing from the assets are left out.
+    fn new(config: &Config, resolve: impl Fn(&str) -> Option<String>) -> Self {
+        let icons = config
+            .icons()
+            .iter()
+            .filter_map(|icon| {
+                let Some(src) = resolve(icon.src()) else {
+                    tracing::warn!("⚠️ Web app icon {} not found in the assets", icon.src());
+                    return None;
+                };
+                Some(WebManifestIcon {
+                    src,
+                    sizes: icon.sizes().clone(),
+                    mime_type: icon
+                        .mime_type()
+                        .clone()
+                        .or_else(|| image_mime_type(icon.src()).map(String::from)),
+                })
+            })
+            .collect();
+
+        Self {
+            name: config.title().clone(),
+            short_name: config.title().clone(),
+            start_url: config.route("/"),
+            scope: config.route("/"),
+            display: "standalone",
+            theme_color: config.theme_color().clone(),
+            background_color: config.background_color().clone(),
+            icons,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigParsed;
+
+    #[test]
+    fn test_web_manifest() {
+        let config = Config::from(
+            toml::from_str::<ConfigParsed>(
+                r##"
+                title = "Notes"
+                base_path = "/docs"
+                theme_color = "#336699"
+                icons = [
+                    { src = "icon-512.png", sizes = "512x512" },
+                    { src = "missing.png" },
+                ]
+                "##,
+            )
+            .unwrap(),
+        );
+
+        let manifest = WebManifest::new(&config, |src| {
+            (src == "icon-512.png").then(|| "/docs/assets/icon-512.abc.png".to_string())
+        });
+        let manifest = serde_json::to_value(manifest).unwrap();
+        assert_eq!(manifest["name"], "Notes");
+        assert_eq!(manifest["start_url"], "/docs/");
+        assert_eq!(manifest["theme_color"], "#336699");
+        assert!(manifest.get("background_color").is_none());
+        assert_eq!(
+            manifest["icons"],
+            serde_json::json!([{
+                "src": "/docs/assets/icon-512.abc.png",
+                "sizes": "512x512",
+                "type": "image/png",
+            }])
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::SystemTime,
+};
+
+use ignore::gitignore::Gitignore;
+use lazy_static::lazy_static;
+
+/// Name of the file at the pages root listing, in gitignore syntax, files that
+/// are neither indexed nor served.
+pub const IGNORE_FILE: &str = ".websiteignore";
+
+/// Parsed ignore file along with the modification time it was read at.
+type CachedIgnore = (Option<SystemTime>, Arc<Gitignore>);
+
+lazy_static! {
+    static ref IGNORES: RwLock<HashMap<PathBuf, CachedIgnore>> = RwLock::new(HashMap::new());
+}
+
+/// Whether the `.websiteignore` file in the pages `root` excludes `path`,
+/// directly or through one of its parent directories. Paths outside the root
+/// are never ignored.
+pub fn is_ignored(root: &Path, path: &Path, is_dir: bool) -> bool {
+    if !path.starts_with(root) || path == root {
+        return false;
+    }
+
+    ignore_rules(root)
+        .matched_path_or_any_parents(path, is_dir)
+        .is_ignore()
+}
+
+/// Reads the ignore file of `root`, reusing the parsed copy while its
+/// modification time is unchanged. Without a file nothing is ignored.
+fn ignore_rules(root: &Path) -> Arc<Gitignore> {
+    let path = root.join(IGNORE_FILE);
+    let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+    if let Some((cached_modified, rules)) =
+        IGNORES.read().unwrap_or_else(|e| e.into_inner()).get(root)
+    {
+        if *cached_modified == modified {
+            return rules.clone();
+        }
+    }
+
+    let rules = if modified.is_some() {
+        let (rules, error) = Gitignore::new(&path);
+        if let Some(error) = error {
+            tracing::warn!("⚠️ Problem in {}: {}", path.to_string_lossy(), error);
+        }
+        rules
+    } else {
+        Gitignore::empty()
+    };
+    let rules = Arc::new(rules);
+    IGNORES
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(root.to_path_buf(), (modified, rules.clone()));
+    rules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignore_file_patterns() {
+        let dir = tempfile::Builder::new().prefix("pages").tempdir().unwrap();
+        let root = dir.path();
+        fs::write(
+            root.join(IGNORE_FILE),
+            "drafts/\n*.scratch.md\n!keep.scratch.md\n",
+        )
+        .unwrap();
+
+        assert!(is_ignored(root, &root.join("drafts"), true));
+        assert!(is_ignored(root, &root.join("drafts/idea.md"), false));
+        assert!(is_ignored(root, &root.join("docs/todo.scratch.md"), false));
+        assert!(!is_ignored(root, &root.join("keep.scratch.md"), false));
+        assert!(!is_ignored(root, &root.join("docs/index.md"), false));
+        assert!(!is_ignored(
+            root,
+            Path::new("/elsewhere/drafts/a.md"),
+            false
+        ));
+    }
+
+    #[test]
+    fn test_nothing_is_ignored_without_file() {
+        let dir = tempfile::Builder::new().prefix("pages").tempdir().unwrap();
+        assert!(!is_ignored(
+            dir.path(),
+            &dir.path().join("drafts/idea.md"),
+            false
+        ));
+    }
+}